@@ -1,24 +1,54 @@
+mod access;
+mod act;
+mod heartbeat;
+mod query;
+mod reconnect;
+mod request;
+mod router;
+mod subscribe;
 mod wait_ready;
 
+pub use query::QueryError;
+pub use subscribe::{FlowUpdate, Subscription, TreeSubscription};
+
+use crate::config::ClientConfig;
 use anyhow::Error;
 use async_trait::async_trait;
 use derive_more::From;
+use meio::task::HeartBeat;
 use meio::{
     ActionHandler, Actor, Address, Context, IdOf, InstantActionHandler, InterruptedBy, StartedBy,
-    TaskEliminated, TaskError,
+    TaskAddress, TaskEliminated, TaskError,
 };
 use meio_connect::{
-    client::{WsClient, WsClientStatus, WsSender},
+    client::{WsClient, WsClientStatus},
     WsIncoming,
 };
+use rill_protocol::encoding::CodecKind;
+use rill_protocol::io::auth::Credentials;
 use rill_protocol::io::client::{
-    ClientProtocol, ClientRequest, ClientResponse, ClientServiceRequest, ClientServiceResponse,
+    AccessLevel, ClientProtocol, ClientReqId, ClientRequest, ClientResponse, ClientServiceRequest,
+    ClientServiceResponse,
+};
+use rill_protocol::io::codec::{Frame, Settings};
+use rill_protocol::io::handshake::{Handshake, Negotiated};
+use rill_protocol::io::heartbeat::Heartbeat;
+use rill_protocol::io::provider::{FlowControl, Path, RecorderRequest};
+use rill_protocol::io::transport::{Envelope, ServiceEnvelope};
+use rill_transport::{
+    Backoff, ConnectionStatus, Endpoint, TlsConfig, TlsConnector, TransportClient, TransportSender,
+    TransportStatus,
 };
-use rill_protocol::io::transport::ServiceEnvelope;
+use router::Router;
 use std::collections::VecDeque;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+type OutgoingMessage = ServiceEnvelope<ClientProtocol, ClientRequest, ClientServiceResponse>;
 
-type WsOutgoing = WsSender<ServiceEnvelope<ClientProtocol, ClientRequest, ClientServiceResponse>>;
+type IncomingMessage = ServiceEnvelope<ClientProtocol, ClientResponse, ClientServiceRequest>;
+
+type Outgoing = TransportSender<Frame<OutgoingMessage>>;
 
 #[derive(From)]
 pub struct RillClientLink {
@@ -27,23 +57,241 @@ pub struct RillClientLink {
 
 pub struct RillClient {
     url: String,
-    sender: Option<WsOutgoing>,
+    tls: TlsConfig,
+    tls_connector: Option<TlsConnector>,
+    ws_client: Option<TaskAddress<WsClient<ClientProtocol, Self>>>,
+    transport_client: Option<TaskAddress<TransportClient<ClientProtocol, Self>>>,
+    backoff: Backoff,
+    status: watch::Sender<ConnectionStatus>,
+    sender: Option<Outgoing>,
+    /// Settings of the connection agreed in the handshake.
+    settings: Settings,
+    /// Features of the connection. Set when the node answers the handshake.
+    negotiated: Option<Negotiated>,
+    /// The time the handshake was sent if the node hasn't answered yet.
+    hello: Option<Instant>,
     awaiting_clients: VecDeque<wait_ready::Notifier>,
+    router: Router,
+    access_level: watch::Sender<Option<AccessLevel>>,
+    credentials: Option<Credentials>,
+    codec: CodecKind,
+    ping_interval: Duration,
+    ping_deadline: Duration,
+    heartbeat: Heartbeat,
+    latency: watch::Sender<Option<Duration>>,
 }
 
 impl RillClient {
     pub fn new(url: Option<String>) -> Self {
-        let url = url.unwrap_or_else(|| "http://localhost:1636".into());
+        let config = ClientConfig {
+            url,
+            ..ClientConfig::default()
+        };
+        Self::from_config(config)
+    }
+
+    pub fn from_config(config: ClientConfig) -> Self {
+        let credentials = config.credentials();
+        let ping_interval = config.ping_interval();
+        let ping_deadline = config.ping_deadline();
+        let heartbeat = Heartbeat::new(ping_deadline);
+        let url = config
+            .url
+            .unwrap_or_else(|| "ws://localhost:1636/live/client".into());
         Self {
             url,
+            tls: config.tls,
+            tls_connector: None,
+            ws_client: None,
+            transport_client: None,
+            backoff: Backoff::new(config.reconnect),
+            status: watch::channel(ConnectionStatus::Connecting).0,
+            credentials,
+            codec: config.codec.unwrap_or(CodecKind::Flexbuffers),
             sender: None,
+            settings: Settings::default(),
+            negotiated: None,
+            hello: None,
             awaiting_clients: VecDeque::new(),
+            router: Router::default(),
+            access_level: watch::channel(None).0,
+            ping_interval,
+            ping_deadline,
+            heartbeat,
+            latency: watch::channel(None).0,
+        }
+    }
+
+    /// TLS settings loaded once for all attempts to connect.
+    fn tls_connector(&mut self) -> Result<TlsConnector, Error> {
+        match &self.tls_connector {
+            Some(tls) => Ok(tls.clone()),
+            None => {
+                let tls = self.tls.load()?;
+                self.tls_connector = Some(tls.clone());
+                Ok(tls)
+            }
+        }
+    }
+
+    fn spawn_client(&mut self, ctx: &mut Context<Self>) -> Result<(), Error> {
+        self.set_status(ConnectionStatus::Connecting);
+        // Attempts are repeated by the client according to the policy
+        let repeat = None;
+        match self.url.parse()? {
+            Endpoint::Ws(url) => {
+                let client = WsClient::new(url, repeat, ctx.address().clone());
+                let task = ctx.spawn_task(client, (), Group::WsConnection);
+                self.ws_client = Some(task);
+            }
+            endpoint => {
+                let tls = match endpoint {
+                    Endpoint::Wss(_) => Some(self.tls_connector()?),
+                    _ => None,
+                };
+                let client = TransportClient::new(endpoint, tls, repeat, ctx.address().clone());
+                let task = ctx.spawn_task(client, (), Group::WsConnection);
+                self.transport_client = Some(task);
+            }
+        }
+        Ok(())
+    }
+
+    fn stop_client(&mut self) -> Result<(), Error> {
+        if let Some(client) = self.ws_client.take() {
+            client.stop()?;
+        }
+        if let Some(client) = self.transport_client.take() {
+            client.stop()?;
+        }
+        Ok(())
+    }
+
+    fn connected(&mut self, sender: Outgoing) -> Result<(), Error> {
+        // The node may not support the settings of the previous connection
+        self.settings = Settings::default();
+        self.negotiated = None;
+        self.sender = Some(sender);
+        self.heartbeat.reset();
+        self.backoff.reset();
+        self.set_status(ConnectionStatus::Connected);
+        self.send_service(ClientServiceResponse::Hello(Handshake::default()));
+        self.hello = Some(Instant::now());
+        Ok(())
+    }
+
+    /// Resets the session when the connection is lost.
+    fn disconnected(&mut self) {
+        self.sender.take();
+        self.heartbeat.reset();
+        self.hello = None;
+        self.latency.send_replace(None);
+        self.set_access_level(None);
+        // Streams will be restored after reconnection
+        self.router.interrupt();
+    }
+
+    /// The session is ready when it's authorized and the features are known:
+    /// the handshake is answered or the node has the first version.
+    fn is_ready(&self) -> bool {
+        *self.access_level.borrow() == Some(AccessLevel::ReadyToWork) && self.negotiated.is_some()
+    }
+
+    /// Restores subscriptions and notifies listeners when the session becomes ready.
+    fn check_ready(&mut self) {
+        if self.is_ready() {
+            self.resubscribe();
+            self.notify_awaiting_clients();
+        }
+    }
+
+    /// Nodes of the first version don't answer the handshake. The session
+    /// with such a node is ready to work without features of the protocol
+    /// when it's authorized and the node didn't answer in time.
+    fn check_v1(&mut self, now: Instant) {
+        let ready = *self.access_level.borrow() == Some(AccessLevel::ReadyToWork);
+        match self.hello {
+            Some(sent) if ready && now.duration_since(sent) >= self.ping_deadline => {
+                log::info!(
+                    "Node {} has no handshake. Using the first version.",
+                    self.url
+                );
+                self.hello = None;
+                self.negotiated = Some(Negotiated::v1());
+                self.check_ready();
+            }
+            _ => {}
+        }
+    }
+
+    /// Returns `true` if the node supports the feature.
+    fn has_feature(&self, feature: &str) -> bool {
+        self.negotiated
+            .as_ref()
+            .map(|negotiated| negotiated.has_feature(feature))
+            .unwrap_or(false)
+    }
+
+    /// The sender for requests to flows. Available when the session is ready to work.
+    fn outgoing(&self) -> Result<&Outgoing, Error> {
+        let sender = self
+            .sender
+            .as_ref()
+            .ok_or_else(|| Error::msg("Client is not connected."))?;
+        if self.is_ready() {
+            Ok(sender)
+        } else {
+            Err(Error::msg("Client is not ready to work."))
+        }
+    }
+
+    fn send_request(
+        &self,
+        direct_id: ClientReqId,
+        path: Path,
+        request: RecorderRequest,
+    ) -> Result<(), Error> {
+        let data = ClientRequest { path, request };
+        let envelope = Envelope { direct_id, data };
+        let frame = self.settings.frame(ServiceEnvelope::Envelope(envelope))?;
+        self.outgoing()?.send(frame);
+        Ok(())
+    }
+
+    fn send_service(&self, response: ClientServiceResponse) {
+        if let Some(sender) = self.sender.as_ref() {
+            match self.settings.frame(ServiceEnvelope::Service(response)) {
+                Ok(frame) => sender.send(frame),
+                Err(err) => log::error!("Can't encode a service message: {}", err),
+            }
+        }
+    }
+
+    /// Sends credentials to the server if they are provided.
+    fn authorize(&self) {
+        if let Some(credentials) = self.credentials.clone() {
+            let response = ClientServiceResponse::Authorize(credentials);
+            self.send_service(response);
+        } else {
+            log::warn!("Server requested credentials, but they are not provided.");
+        }
+    }
+
+    /// Starts all active streams again over a new connection.
+    fn resubscribe(&self) {
+        for (direct_id, path) in self.router.streams() {
+            log::debug!("Resubscribing to {} with {:?}", path, direct_id);
+            let request = RecorderRequest::ControlStream(FlowControl::StartStream);
+            if let Err(err) = self.send_request(direct_id, path.clone(), request) {
+                log::error!("Can't resubscribe to {}: {}", path, err);
+            }
         }
     }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Group {
+    HeartBeat,
     WsConnection,
 }
 
@@ -55,14 +303,12 @@ impl Actor for RillClient {
 impl<T: Actor> StartedBy<T> for RillClient {
     async fn handle(&mut self, ctx: &mut Context<Self>) -> Result<(), Error> {
         // TODO: Use `strum` here
-        ctx.termination_sequence(vec![Group::WsConnection]);
+        ctx.termination_sequence(vec![Group::HeartBeat, Group::WsConnection]);
+
+        self.spawn_client(ctx)?;
 
-        let client = WsClient::new(
-            self.url.clone(),
-            Some(Duration::from_secs(1)),
-            ctx.address().clone(),
-        );
-        ctx.spawn_task(client, (), Group::WsConnection);
+        let heartbeat = HeartBeat::new(self.ping_interval, ctx.address().clone());
+        ctx.spawn_task(heartbeat, (), Group::HeartBeat);
 
         Ok(())
     }
@@ -85,12 +331,11 @@ impl InstantActionHandler<WsClientStatus<ClientProtocol>> for RillClient {
     ) -> Result<(), Error> {
         match status {
             WsClientStatus::Connected { sender } => {
-                self.sender = Some(sender);
-                self.notify_awaiting_clients();
+                self.connected(sender.into())?;
             }
             WsClientStatus::Failed { reason } => {
                 log::error!("Connection failed: {}", reason);
-                self.sender.take();
+                self.disconnected();
             }
         }
         Ok(())
@@ -98,31 +343,91 @@ impl InstantActionHandler<WsClientStatus<ClientProtocol>> for RillClient {
 }
 
 #[async_trait]
-impl
-    ActionHandler<WsIncoming<ServiceEnvelope<ClientProtocol, ClientResponse, ClientServiceRequest>>>
-    for RillClient
-{
+impl InstantActionHandler<TransportStatus<ClientProtocol>> for RillClient {
     async fn handle(
         &mut self,
-        msg: WsIncoming<ServiceEnvelope<ClientProtocol, ClientResponse, ClientServiceRequest>>,
+        status: TransportStatus<ClientProtocol>,
+        _ctx: &mut Context<Self>,
+    ) -> Result<(), Error> {
+        match status {
+            TransportStatus::Connected { sender } => {
+                self.connected(sender)?;
+            }
+            TransportStatus::Failed { reason } => {
+                log::error!("Connection failed: {}", reason);
+                self.disconnected();
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ActionHandler<WsIncoming<Frame<IncomingMessage>>> for RillClient {
+    async fn handle(
+        &mut self,
+        msg: WsIncoming<Frame<IncomingMessage>>,
         _ctx: &mut Context<Self>,
     ) -> Result<(), Error> {
         log::trace!("Incoming to exporter: {:?}", msg);
-        match msg.0 {
+        match msg.0.into_message()? {
             ServiceEnvelope::Envelope(envelope) => {
-                let _direct_id = envelope.direct_id;
+                let direct_id = envelope.direct_id;
                 match envelope.data {
                     ClientResponse::Declare(entry_id) => {
                         log::info!("Connected to: {}", entry_id);
                     }
-                    evt => {
-                        log::error!("Not implemented for {:?}", evt);
+                    response => {
+                        self.router.route(direct_id, response);
                     }
                 }
             }
-            ServiceEnvelope::Service(_) => {
-                log::error!("Service message is not supported yet.");
-            }
+            ServiceEnvelope::Service(request) => match request {
+                ClientServiceRequest::Ping => {
+                    self.pong();
+                }
+                ClientServiceRequest::Pong => {
+                    self.pong_received();
+                }
+                ClientServiceRequest::AccessLevel(level) => {
+                    log::debug!("Access level changed to {:?}", level);
+                    self.set_access_level(Some(level));
+                    match level {
+                        AccessLevel::SessionCreated => {}
+                        AccessLevel::ReadyToAuth => {
+                            self.authorize();
+                        }
+                        AccessLevel::ReadyToWork => {
+                            self.check_ready();
+                        }
+                    }
+                }
+                ClientServiceRequest::AccessDenied { reason } => {
+                    log::error!("Access denied: {}", reason);
+                    self.reject_awaiting_clients(&reason);
+                }
+                ClientServiceRequest::Accepted(negotiated) => {
+                    log::debug!("Protocol negotiated with {}: {:?}", self.url, negotiated);
+                    let preferred = Settings {
+                        codec: self.codec,
+                        blobs: true,
+                        ..Settings::default()
+                    };
+                    self.settings = negotiated.settings(&preferred);
+                    self.negotiated = Some(negotiated);
+                    self.hello = None;
+                    self.check_ready();
+                }
+                ClientServiceRequest::Incompatible { reason } => {
+                    log::error!("Node {} rejected the client: {}", self.url, reason);
+                    self.set_status(ConnectionStatus::Rejected {
+                        reason: reason.clone(),
+                    });
+                    self.reject_awaiting_clients(&reason);
+                    self.disconnected();
+                    self.stop_client()?;
+                }
+            },
         }
         Ok(())
     }
@@ -135,9 +440,24 @@ impl TaskEliminated<WsClient<ClientProtocol, Self>, ()> for RillClient {
         _id: IdOf<WsClient<ClientProtocol, Self>>,
         _tag: (),
         _result: Result<(), TaskError>,
-        _ctx: &mut Context<Self>,
+        ctx: &mut Context<Self>,
     ) -> Result<(), Error> {
         // TODO: Drop unfinished tasks
-        Ok(())
+        self.ws_client.take();
+        self.schedule_reconnect(ctx)
+    }
+}
+
+#[async_trait]
+impl TaskEliminated<TransportClient<ClientProtocol, Self>, ()> for RillClient {
+    async fn handle(
+        &mut self,
+        _id: IdOf<TransportClient<ClientProtocol, Self>>,
+        _tag: (),
+        _result: Result<(), TaskError>,
+        ctx: &mut Context<Self>,
+    ) -> Result<(), Error> {
+        self.transport_client.take();
+        self.schedule_reconnect(ctx)
     }
 }
@@ -0,0 +1,40 @@
+use super::RillClient;
+use super::RillClientLink;
+use anyhow::Error;
+use async_trait::async_trait;
+use meio::{Context, Interaction, InteractionHandler};
+use rill_protocol::io::client::AccessLevel;
+use tokio::sync::watch;
+
+pub struct WatchAccessLevel;
+
+impl Interaction for WatchAccessLevel {
+    type Output = watch::Receiver<Option<AccessLevel>>;
+}
+
+impl RillClientLink {
+    /// Watches the `AccessLevel` of the session.
+    ///
+    /// The level is `None` while the client is not connected.
+    pub async fn access_level(&mut self) -> Result<watch::Receiver<Option<AccessLevel>>, Error> {
+        let msg = WatchAccessLevel;
+        self.address.interact(msg).recv().await
+    }
+}
+
+#[async_trait]
+impl InteractionHandler<WatchAccessLevel> for RillClient {
+    async fn handle(
+        &mut self,
+        _: WatchAccessLevel,
+        _ctx: &mut Context<Self>,
+    ) -> Result<watch::Receiver<Option<AccessLevel>>, Error> {
+        Ok(self.access_level.subscribe())
+    }
+}
+
+impl RillClient {
+    pub(super) fn set_access_level(&mut self, level: Option<AccessLevel>) {
+        self.access_level.send_replace(level);
+    }
+}
@@ -0,0 +1,30 @@
+use super::query::QueryError;
+use super::RillClientLink;
+use rill_protocol::flow::core::Flow;
+use rill_protocol::io::client::ClientResponse;
+use rill_protocol::io::provider::{Path, RecorderAction};
+use std::time::Duration;
+
+impl RillClientLink {
+    /// Sends an action to a `Flow` and waits until a watcher receives it.
+    ///
+    /// Fails with `QueryError::Timeout` if the delivery isn't confirmed in time
+    /// and with `QueryError::Unconfirmed` if the node or the provider
+    /// doesn't support confirmations.
+    pub async fn act<T: Flow>(
+        &mut self,
+        path: Path,
+        action: T::Action,
+        duration: Duration,
+    ) -> Result<(), QueryError> {
+        let data = T::pack_action(&action).map_err(QueryError::Codec)?;
+        let response = self
+            .query(path, RecorderAction::DoAction(data), duration)
+            .await?;
+        match response {
+            ClientResponse::Delivered => Ok(()),
+            ClientResponse::Unconfirmed => Err(QueryError::Unconfirmed),
+            other => Err(QueryError::Unexpected(other)),
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use super::RillClient;
+use super::RillClientLink;
+use anyhow::Error;
+use async_trait::async_trait;
+use meio::task::{OnTick, Tick};
+use meio::{Context, Interaction, InteractionHandler};
+use rill_protocol::io::client::ClientServiceResponse;
+use rill_protocol::io::handshake::FEATURE_HEARTBEAT;
+use rill_protocol::io::heartbeat::Pulse;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+
+impl RillClient {
+    pub(super) fn pong(&self) {
+        self.send_service(ClientServiceResponse::Pong);
+    }
+
+    pub(super) fn pong_received(&mut self) {
+        if let Some(latency) = self.heartbeat.pong(Instant::now()) {
+            self.latency.send_replace(Some(latency));
+        }
+    }
+}
+
+#[async_trait]
+impl OnTick for RillClient {
+    async fn tick(&mut self, tick: Tick, _ctx: &mut Context<Self>) -> Result<(), Error> {
+        self.check_v1(tick.0);
+        // Nodes without heartbeats never answer pings
+        if self.sender.is_some() && self.has_feature(FEATURE_HEARTBEAT) {
+            match self.heartbeat.tick(tick.0) {
+                Pulse::Ping => {
+                    self.send_service(ClientServiceResponse::Ping);
+                }
+                Pulse::Wait => {}
+                Pulse::Dead => {
+                    log::warn!("No pong from {}. Reconnecting...", self.url);
+                    self.disconnected();
+                    // The next attempt will be scheduled when the client finished
+                    self.stop_client()?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn done(&mut self, _ctx: &mut Context<Self>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+pub struct WatchLatency;
+
+impl Interaction for WatchLatency {
+    type Output = watch::Receiver<Option<Duration>>;
+}
+
+impl RillClientLink {
+    /// Watches the round-trip latency of the connection.
+    ///
+    /// The latency is `None` until the first pong received.
+    pub async fn latency(&mut self) -> Result<watch::Receiver<Option<Duration>>, Error> {
+        let msg = WatchLatency;
+        self.address.interact(msg).recv().await
+    }
+}
+
+#[async_trait]
+impl InteractionHandler<WatchLatency> for RillClient {
+    async fn handle(
+        &mut self,
+        _: WatchLatency,
+        _ctx: &mut Context<Self>,
+    ) -> Result<watch::Receiver<Option<Duration>>, Error> {
+        Ok(self.latency.subscribe())
+    }
+}
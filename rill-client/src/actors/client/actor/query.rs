@@ -0,0 +1,87 @@
+use super::request::DoRequest;
+use super::RillClientLink;
+use anyhow::Error;
+use rill_protocol::flow::core::Flow;
+use rill_protocol::io::client::ClientResponse;
+use rill_protocol::io::provider::{Description, Path, RecorderAction};
+use std::time::Duration;
+use thiserror::Error;
+use tokio::time::timeout;
+
+#[derive(Error, Debug)]
+pub enum QueryError {
+    #[error("Server failed: {0}")]
+    Server(String),
+    #[error("No response in time.")]
+    Timeout,
+    #[error("Client disconnected.")]
+    Disconnected,
+    #[error("The action was sent, but the node doesn't confirm actions.")]
+    Unconfirmed,
+    #[error("Unexpected response: {0:?}")]
+    Unexpected(ClientResponse),
+    #[error("Encoding failed: {0}")]
+    Codec(Error),
+}
+
+impl RillClientLink {
+    /// Requests the current state of a `Flow`.
+    pub async fn snapshot<T: Flow>(
+        &mut self,
+        path: Path,
+        duration: Duration,
+    ) -> Result<T, QueryError> {
+        let response = self
+            .query(path, RecorderAction::GetSnapshot, duration)
+            .await?;
+        match response {
+            ClientResponse::State(state) => T::unpack_state(&state).map_err(QueryError::Codec),
+            other => Err(QueryError::Unexpected(other)),
+        }
+    }
+
+    /// Requests the `Description` of a `Flow`.
+    pub async fn describe(
+        &mut self,
+        path: Path,
+        duration: Duration,
+    ) -> Result<Description, QueryError> {
+        let response = self.query(path, RecorderAction::GetFlow, duration).await?;
+        match response {
+            ClientResponse::Flow(description) => Ok(description),
+            other => Err(QueryError::Unexpected(other)),
+        }
+    }
+
+    /// Sends a request and waits for the response not longer than `duration`.
+    pub(super) async fn query(
+        &mut self,
+        path: Path,
+        action: RecorderAction,
+        duration: Duration,
+    ) -> Result<ClientResponse, QueryError> {
+        timeout(duration, self.exchange(path, action))
+            .await
+            .map_err(|_| QueryError::Timeout)?
+    }
+
+    /// Sends a request and converts the error response to `QueryError`.
+    async fn exchange(
+        &mut self,
+        path: Path,
+        action: RecorderAction,
+    ) -> Result<ClientResponse, QueryError> {
+        let msg = DoRequest { path, action };
+        let rx = self
+            .address
+            .interact(msg)
+            .recv()
+            .await
+            .map_err(|_| QueryError::Disconnected)?;
+        let response = rx.await.map_err(|_| QueryError::Disconnected)?;
+        match response {
+            ClientResponse::Error(reason) => Err(QueryError::Server(reason)),
+            response => Ok(response),
+        }
+    }
+}
@@ -0,0 +1,85 @@
+use super::RillClient;
+use super::RillClientLink;
+use anyhow::Error;
+use async_trait::async_trait;
+use meio::{Context, Interaction, InteractionHandler, Scheduled};
+use rill_transport::ConnectionStatus;
+use std::time::Instant;
+use tokio::sync::watch;
+
+/// The moment to make the next attempt to connect.
+pub(super) struct Reconnect;
+
+pub struct WatchStatus;
+
+impl Interaction for WatchStatus {
+    type Output = watch::Receiver<ConnectionStatus>;
+}
+
+impl RillClientLink {
+    /// Watches the state of the connection to the node.
+    pub async fn status(&mut self) -> Result<watch::Receiver<ConnectionStatus>, Error> {
+        let msg = WatchStatus;
+        self.address.interact(msg).recv().await
+    }
+}
+
+#[async_trait]
+impl InteractionHandler<WatchStatus> for RillClient {
+    async fn handle(
+        &mut self,
+        _: WatchStatus,
+        _ctx: &mut Context<Self>,
+    ) -> Result<watch::Receiver<ConnectionStatus>, Error> {
+        Ok(self.status.subscribe())
+    }
+}
+
+impl RillClient {
+    pub(super) fn set_status(&mut self, status: ConnectionStatus) {
+        self.status.send_replace(status);
+    }
+
+    /// Returns `true` if the connection won't be restored.
+    pub(super) fn is_final(&self) -> bool {
+        self.status.borrow().is_final()
+    }
+
+    /// Schedules the next attempt to connect or gives up if attempts are exhausted.
+    pub(super) fn schedule_reconnect(&mut self, ctx: &mut Context<Self>) -> Result<(), Error> {
+        if ctx.is_terminating() || self.is_final() {
+            return Ok(());
+        }
+        if let Some(delay) = self.backoff.next_delay() {
+            log::debug!("Next attempt to connect to {} in {:?}", self.url, delay);
+            let attempt = self.backoff.attempts();
+            self.set_status(ConnectionStatus::Backoff { attempt, delay });
+            ctx.address().schedule(Reconnect, Instant::now() + delay)?;
+        } else {
+            let reason = format!(
+                "gave up connecting to {} after {} attempts",
+                self.url,
+                self.backoff.attempts()
+            );
+            log::error!("Client {}.", reason);
+            self.set_status(ConnectionStatus::GaveUp);
+            self.reject_awaiting_clients(&reason);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Scheduled<Reconnect> for RillClient {
+    async fn handle(
+        &mut self,
+        _timestamp: Instant,
+        _reconnect: Reconnect,
+        ctx: &mut Context<Self>,
+    ) -> Result<(), Error> {
+        if !ctx.is_terminating() {
+            self.spawn_client(ctx)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,54 @@
+use super::RillClient;
+use super::RillClientLink;
+use anyhow::Error;
+use async_trait::async_trait;
+use futures::channel::oneshot;
+use meio::{Context, Interaction, InteractionHandler};
+use rill_protocol::io::client::ClientResponse;
+use rill_protocol::io::handshake::FEATURE_ACTIONS;
+use rill_protocol::io::provider::{Path, RecorderAction, RecorderRequest};
+
+pub struct DoRequest {
+    pub path: Path,
+    pub action: RecorderAction,
+}
+
+impl Interaction for DoRequest {
+    type Output = oneshot::Receiver<ClientResponse>;
+}
+
+impl RillClientLink {
+    /// Sends a one-shot request and waits for the response.
+    pub async fn request(
+        &mut self,
+        path: Path,
+        action: RecorderAction,
+    ) -> Result<ClientResponse, Error> {
+        let msg = DoRequest { path, action };
+        let rx = self.address.interact(msg).recv().await?;
+        rx.await
+            .map_err(|_| Error::msg("Connection closed before a response."))
+    }
+}
+
+#[async_trait]
+impl InteractionHandler<DoRequest> for RillClient {
+    async fn handle(
+        &mut self,
+        msg: DoRequest,
+        _ctx: &mut Context<Self>,
+    ) -> Result<oneshot::Receiver<ClientResponse>, Error> {
+        self.outgoing()?;
+        let (tx, rx) = oneshot::channel();
+        let direct_id = self.router.add_request(tx);
+        let unconfirmed =
+            matches!(msg.action, RecorderAction::DoAction(_)) && !self.has_feature(FEATURE_ACTIONS);
+        let request = RecorderRequest::Action(msg.action);
+        self.send_request(direct_id, msg.path, request)?;
+        if unconfirmed {
+            // The node doesn't confirm actions
+            self.router.route(direct_id, ClientResponse::Unconfirmed);
+        }
+        Ok(rx)
+    }
+}
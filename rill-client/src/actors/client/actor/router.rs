@@ -0,0 +1,292 @@
+use futures::channel::{mpsc, oneshot};
+use rill_protocol::io::client::{ClientReqId, ClientResponse};
+use rill_protocol::io::provider::Path;
+use std::collections::HashMap;
+
+/// A message delivered to a subscriber of a stream.
+#[derive(Debug)]
+pub enum StreamMessage {
+    Response(ClientResponse),
+    /// The connection was lost and the stream will be restored
+    /// with a fresh state after reconnection.
+    Interrupted,
+}
+
+/// The destination of responses with the specific `ClientReqId`.
+pub(super) enum Route {
+    /// Forwards all responses till the end of the stream.
+    Stream {
+        path: Path,
+        sender: mpsc::UnboundedSender<StreamMessage>,
+    },
+    /// Expects a single response only.
+    Request {
+        sender: oneshot::Sender<ClientResponse>,
+    },
+    /// The stream was stopped by a client and waits for
+    /// the final response to release the id.
+    Closing,
+}
+
+/// Routing table that multiplexes requests over a single connection.
+#[derive(Default)]
+pub(super) struct Router {
+    counter: usize,
+    routes: HashMap<ClientReqId, Route>,
+}
+
+impl Router {
+    /// Allocates an id that is not used by any active route.
+    fn allocate(&mut self) -> ClientReqId {
+        loop {
+            self.counter = self.counter.wrapping_add(1);
+            let direct_id = ClientReqId::from(self.counter);
+            if !self.routes.contains_key(&direct_id) {
+                return direct_id;
+            }
+        }
+    }
+
+    pub fn add_stream(
+        &mut self,
+        path: Path,
+        sender: mpsc::UnboundedSender<StreamMessage>,
+    ) -> ClientReqId {
+        let direct_id = self.allocate();
+        self.routes
+            .insert(direct_id, Route::Stream { path, sender });
+        direct_id
+    }
+
+    pub fn add_request(&mut self, sender: oneshot::Sender<ClientResponse>) -> ClientReqId {
+        // Releases requests that were abandoned by timeouts
+        self.routes.retain(|_, route| match route {
+            Route::Request { sender } => !sender.is_canceled(),
+            _ => true,
+        });
+        let direct_id = self.allocate();
+        self.routes.insert(direct_id, Route::Request { sender });
+        direct_id
+    }
+
+    /// Marks the stream as closing and returns its `Path`.
+    pub fn close(&mut self, direct_id: ClientReqId) -> Option<Path> {
+        match self.routes.remove(&direct_id) {
+            Some(Route::Stream { path, .. }) => {
+                self.routes.insert(direct_id, Route::Closing);
+                Some(path)
+            }
+            Some(route) => {
+                self.routes.insert(direct_id, route);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Active streams that have to be restored after reconnection.
+    pub fn streams(&self) -> impl Iterator<Item = (ClientReqId, &Path)> {
+        self.routes
+            .iter()
+            .filter_map(|(direct_id, route)| match route {
+                Route::Stream { path, .. } => Some((*direct_id, path)),
+                _ => None,
+            })
+    }
+
+    /// Drops pending requests and notifies streams about the lost connection.
+    ///
+    /// Streams keep their ids to be resubscribed when the connection is restored.
+    pub fn interrupt(&mut self) {
+        self.routes.retain(|direct_id, route| match route {
+            Route::Stream { path, sender } => {
+                let alive = sender.unbounded_send(StreamMessage::Interrupted).is_ok();
+                if !alive {
+                    log::debug!("Subscription {:?} to {} was dropped.", direct_id, path);
+                }
+                alive
+            }
+            Route::Request { .. } | Route::Closing => false,
+        });
+    }
+
+    /// Forwards a response to its route and releases the route
+    /// if no more responses expected.
+    pub fn route(&mut self, direct_id: ClientReqId, response: ClientResponse) {
+        match self.routes.remove(&direct_id) {
+            Some(Route::Stream { path, sender }) => {
+                let last = matches!(response, ClientResponse::Done | ClientResponse::Error(_));
+                if sender
+                    .unbounded_send(StreamMessage::Response(response))
+                    .is_err()
+                {
+                    log::debug!("Subscription to {} was dropped.", path);
+                } else if !last {
+                    self.routes
+                        .insert(direct_id, Route::Stream { path, sender });
+                }
+            }
+            Some(Route::Request { sender }) => {
+                if sender.send(response).is_err() {
+                    log::debug!("Requester of {:?} has gone.", direct_id);
+                }
+            }
+            Some(Route::Closing) => {
+                let last = matches!(response, ClientResponse::Done | ClientResponse::Error(_));
+                if !last {
+                    self.routes.insert(direct_id, Route::Closing);
+                }
+            }
+            None => {
+                log::trace!("No route for {:?}: {:?}", direct_id, response);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Error;
+
+    fn response(rx: &mut mpsc::UnboundedReceiver<StreamMessage>) -> Option<StreamMessage> {
+        rx.try_recv().ok()
+    }
+
+    #[test]
+    fn responses_are_routed_by_ids() -> Result<(), Error> {
+        let mut router = Router::default();
+        let (stream_tx, mut stream_rx) = mpsc::unbounded();
+        let stream_id = router.add_stream("a.b".parse()?, stream_tx);
+        let (first_tx, mut first_rx) = oneshot::channel();
+        let first_id = router.add_request(first_tx);
+        let (second_tx, mut second_rx) = oneshot::channel();
+        let second_id = router.add_request(second_tx);
+        assert_ne!(first_id, stream_id);
+        assert_ne!(second_id, first_id);
+
+        router.route(second_id, ClientResponse::Delivered);
+        router.route(stream_id, ClientResponse::Done);
+        router.route(first_id, ClientResponse::Error("first".into()));
+        assert!(matches!(
+            second_rx.try_recv(),
+            Ok(Some(ClientResponse::Delivered))
+        ));
+        assert!(matches!(
+            first_rx.try_recv(),
+            Ok(Some(ClientResponse::Error(reason))) if reason == "first"
+        ));
+        assert!(matches!(
+            response(&mut stream_rx),
+            Some(StreamMessage::Response(ClientResponse::Done))
+        ));
+        // All routes are released
+        assert!(router.routes.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn streams_live_till_the_end() -> Result<(), Error> {
+        let mut router = Router::default();
+        let (tx, mut rx) = mpsc::unbounded();
+        let direct_id = router.add_stream("a.b".parse()?, tx);
+        router.route(direct_id, ClientResponse::Delivered);
+        router.route(direct_id, ClientResponse::Delivered);
+        assert_eq!(router.streams().count(), 1);
+        router.route(direct_id, ClientResponse::Error("failed".into()));
+        assert_eq!(router.streams().count(), 0);
+        // Responses after the end are dropped
+        router.route(direct_id, ClientResponse::Delivered);
+
+        let mut received = 0;
+        while let Some(StreamMessage::Response(response)) = response(&mut rx) {
+            received += 1;
+            if received == 3 {
+                assert!(matches!(response, ClientResponse::Error(_)));
+            }
+        }
+        assert_eq!(received, 3);
+        Ok(())
+    }
+
+    #[test]
+    fn closed_streams_wait_for_the_final_response() -> Result<(), Error> {
+        let mut router = Router::default();
+        let path: Path = "a.b".parse()?;
+        let (tx, mut rx) = mpsc::unbounded();
+        let direct_id = router.add_stream(path.clone(), tx);
+        assert_eq!(router.close(direct_id), Some(path));
+        // Only streams can be closed and only once
+        assert_eq!(router.close(direct_id), None);
+        let (req_tx, _req_rx) = oneshot::channel();
+        let request_id = router.add_request(req_tx);
+        assert_eq!(router.close(request_id), None);
+
+        // The id is not reused till the stream ends
+        router.route(direct_id, ClientResponse::Delivered);
+        assert!(router.routes.contains_key(&direct_id));
+        router.route(direct_id, ClientResponse::Done);
+        assert!(!router.routes.contains_key(&direct_id));
+        assert_eq!(router.streams().count(), 0);
+        assert!(response(&mut rx).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn interrupted_streams_keep_ids() -> Result<(), Error> {
+        let mut router = Router::default();
+        let (tx, mut rx) = mpsc::unbounded();
+        let stream_id = router.add_stream("a.b".parse()?, tx);
+        let (dropped_tx, dropped_rx) = mpsc::unbounded();
+        let dropped_id = router.add_stream("a.c".parse()?, dropped_tx);
+        drop(dropped_rx);
+        let (closing_tx, _closing_rx) = mpsc::unbounded();
+        let closing_id = router.add_stream("a.d".parse()?, closing_tx);
+        router.close(closing_id);
+        let (req_tx, mut req_rx) = oneshot::channel();
+        router.add_request(req_tx);
+
+        router.interrupt();
+        assert!(matches!(
+            response(&mut rx),
+            Some(StreamMessage::Interrupted)
+        ));
+        // Pending requests are rejected
+        assert!(req_rx.try_recv().is_err());
+        // Live streams will be resubscribed with the same ids
+        let streams: Vec<_> = router
+            .streams()
+            .map(|(direct_id, path)| (direct_id, path.to_string()))
+            .collect();
+        assert_eq!(streams, vec![(stream_id, "a.b".to_string())]);
+        assert!(!router.routes.contains_key(&dropped_id));
+        assert!(!router.routes.contains_key(&closing_id));
+        Ok(())
+    }
+
+    #[test]
+    fn abandoned_requests_are_released() {
+        let mut router = Router::default();
+        let (tx, rx) = oneshot::channel();
+        let abandoned_id = router.add_request(tx);
+        drop(rx);
+        let (tx, _rx) = oneshot::channel();
+        let direct_id = router.add_request(tx);
+        assert!(!router.routes.contains_key(&abandoned_id));
+        assert!(router.routes.contains_key(&direct_id));
+    }
+
+    #[test]
+    fn ids_of_active_routes_are_skipped() -> Result<(), Error> {
+        let mut router = Router::default();
+        let (tx, _rx) = mpsc::unbounded();
+        let direct_id = router.add_stream("a.b".parse()?, tx);
+        // The counter wrapped around and reached the id of the stream
+        router.counter = 0;
+        let (tx, _rx) = oneshot::channel();
+        let next_id = router.add_request(tx);
+        assert_ne!(next_id, direct_id);
+        assert_eq!(next_id, ClientReqId::from(2));
+        Ok(())
+    }
+}
@@ -0,0 +1,340 @@
+use super::router::StreamMessage;
+use super::RillClient;
+use super::RillClientLink;
+use anyhow::Error;
+use async_trait::async_trait;
+use futures::channel::mpsc;
+use futures::{ready, Stream, StreamExt};
+use meio::{
+    Address, Context, InstantAction, InstantActionHandler, Interaction, InteractionHandler,
+};
+use rill_protocol::flow::core::Flow;
+use rill_protocol::io::client::{ClientReqId, ClientResponse};
+use rill_protocol::io::provider::{FlowControl, Path, PathPattern, RecorderRequest};
+use std::pin::Pin;
+use std::task::{Context as TaskContext, Poll};
+
+pub struct SubscribeToPath {
+    pub path: Path,
+}
+
+impl Interaction for SubscribeToPath {
+    type Output = (ClientReqId, mpsc::UnboundedReceiver<StreamMessage>);
+}
+
+impl RillClientLink {
+    /// Subscribes to a `Flow` and waits for its initial state.
+    pub async fn subscribe<T: Flow>(&mut self, path: Path) -> Result<Subscription<T>, Error> {
+        let msg = SubscribeToPath { path: path.clone() };
+        let (direct_id, mut rx) = self.address.interact(msg).recv().await?;
+        let mut stopper = Stopper {
+            direct_id,
+            address: self.address.clone(),
+            ended: false,
+        };
+        while let Some(message) = rx.next().await {
+            match message {
+                StreamMessage::Response(ClientResponse::State(state)) => {
+                    let state = T::unpack_state(&state)?;
+                    return Ok(Subscription::new(state, rx, stopper));
+                }
+                StreamMessage::Response(ClientResponse::Error(reason)) => {
+                    stopper.ended = true;
+                    return Err(Error::msg(reason));
+                }
+                StreamMessage::Response(ClientResponse::Done) => {
+                    stopper.ended = true;
+                    break;
+                }
+                StreamMessage::Interrupted => {
+                    // The state will be received after reconnection
+                }
+                StreamMessage::Response(other) => {
+                    log::warn!(
+                        "Unexpected response before a state of {}: {:?}",
+                        path,
+                        other
+                    );
+                }
+            }
+        }
+        Err(Error::msg(format!(
+            "Stream {} closed without a state.",
+            path
+        )))
+    }
+}
+
+impl RillClientLink {
+    /// Subscribes to all flows of a subtree or flows that match a pattern.
+    ///
+    /// Flows added to the subtree later are subscribed automatically.
+    pub async fn subscribe_tree(
+        &mut self,
+        pattern: PathPattern,
+    ) -> Result<TreeSubscription, Error> {
+        let msg = SubscribeToPath { path: pattern.path };
+        let (direct_id, rx) = self.address.interact(msg).recv().await?;
+        let stopper = Stopper {
+            direct_id,
+            address: self.address.clone(),
+            ended: false,
+        };
+        Ok(TreeSubscription {
+            rx,
+            stopper,
+            done: false,
+        })
+    }
+}
+
+#[async_trait]
+impl InteractionHandler<SubscribeToPath> for RillClient {
+    async fn handle(
+        &mut self,
+        msg: SubscribeToPath,
+        _ctx: &mut Context<Self>,
+    ) -> Result<(ClientReqId, mpsc::UnboundedReceiver<StreamMessage>), Error> {
+        self.outgoing()?;
+        let (tx, rx) = mpsc::unbounded();
+        let direct_id = self.router.add_stream(msg.path.clone(), tx);
+        let request = RecorderRequest::ControlStream(FlowControl::StartStream);
+        self.send_request(direct_id, msg.path, request)?;
+        Ok((direct_id, rx))
+    }
+}
+
+/// Stops a stream that is not used anymore.
+pub(super) struct StopSubscription {
+    direct_id: ClientReqId,
+}
+
+impl InstantAction for StopSubscription {}
+
+#[async_trait]
+impl InstantActionHandler<StopSubscription> for RillClient {
+    async fn handle(
+        &mut self,
+        msg: StopSubscription,
+        _ctx: &mut Context<Self>,
+    ) -> Result<(), Error> {
+        let direct_id = msg.direct_id;
+        if let Some(path) = self.router.close(direct_id) {
+            let request = RecorderRequest::ControlStream(FlowControl::StopStream);
+            if let Err(err) = self.send_request(direct_id, path, request) {
+                log::debug!("Can't stop the stream {:?}: {}", direct_id, err);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Sends `StopStream` request for an abandoned subscription.
+struct Stopper {
+    direct_id: ClientReqId,
+    address: Address<RillClient>,
+    /// The stream was finished by a server.
+    ended: bool,
+}
+
+impl Drop for Stopper {
+    fn drop(&mut self) {
+        if !self.ended {
+            let msg = StopSubscription {
+                direct_id: self.direct_id,
+            };
+            if let Err(err) = self.address.instant(msg) {
+                log::debug!("Can't stop the stream {:?}: {}", self.direct_id, err);
+            }
+        }
+    }
+}
+
+/// An update of a subscribed `Flow`.
+#[derive(Debug, Clone)]
+pub enum FlowUpdate<T: Flow> {
+    /// The event and the state with the applied event.
+    Event { state: T, event: T::Event },
+    /// The stream was restored after reconnection and
+    /// the state was replaced with a fresh one.
+    Resynced { state: T },
+    /// The stream failed. It's the last update of the stream.
+    Failed { reason: String },
+}
+
+/// The stream of updates of a `Flow`.
+///
+/// The stream survives reconnections of the client and yields
+/// `FlowUpdate::Resynced` with a fresh state when it's restored.
+/// The stream ends when the server closes it. If the server reports
+/// an error the stream yields `FlowUpdate::Failed` and ends.
+/// Dropping of the subscription stops the stream on the server.
+pub struct Subscription<T: Flow> {
+    state: T,
+    rx: mpsc::UnboundedReceiver<StreamMessage>,
+    stopper: Stopper,
+    /// The connection was interrupted and the next state is a fresh one.
+    resyncing: bool,
+    done: bool,
+}
+
+impl<T: Flow> Subscription<T> {
+    fn new(state: T, rx: mpsc::UnboundedReceiver<StreamMessage>, stopper: Stopper) -> Self {
+        Self {
+            state,
+            rx,
+            stopper,
+            resyncing: false,
+            done: false,
+        }
+    }
+
+    /// The last known state of the `Flow`.
+    pub fn state(&self) -> &T {
+        &self.state
+    }
+
+    fn finish(&mut self) -> Poll<Option<FlowUpdate<T>>> {
+        self.done = true;
+        self.rx.close();
+        Poll::Ready(None)
+    }
+
+    fn fail(&mut self, reason: String) -> Poll<Option<FlowUpdate<T>>> {
+        log::error!("Stream of {} failed: {}", T::stream_type(), reason);
+        self.done = true;
+        self.rx.close();
+        Poll::Ready(Some(FlowUpdate::Failed { reason }))
+    }
+}
+
+// The state is never pinned.
+impl<T: Flow> Unpin for Subscription<T> {}
+
+impl<T: Flow> Stream for Subscription<T> {
+    type Item = FlowUpdate<T>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.done {
+            return Poll::Ready(None);
+        }
+        loop {
+            let message = ready!(this.rx.poll_next_unpin(cx));
+            let response = match message {
+                Some(StreamMessage::Response(response)) => response,
+                Some(StreamMessage::Interrupted) => {
+                    this.resyncing = true;
+                    continue;
+                }
+                None => {
+                    this.stopper.ended = true;
+                    return this.finish();
+                }
+            };
+            match response {
+                ClientResponse::Delta(delta) => match T::unpack_event(&delta) {
+                    Ok(event) => {
+                        this.state.apply(event.clone());
+                        let update = FlowUpdate::Event {
+                            state: this.state.clone(),
+                            event,
+                        };
+                        return Poll::Ready(Some(update));
+                    }
+                    Err(err) => {
+                        return this.fail(format!("Can't unpack an event: {}", err));
+                    }
+                },
+                ClientResponse::State(state) => match T::unpack_state(&state) {
+                    Ok(state) => {
+                        this.state = state;
+                        if this.resyncing {
+                            this.resyncing = false;
+                            let update = FlowUpdate::Resynced {
+                                state: this.state.clone(),
+                            };
+                            return Poll::Ready(Some(update));
+                        }
+                    }
+                    Err(err) => {
+                        return this.fail(format!("Can't unpack a state: {}", err));
+                    }
+                },
+                ClientResponse::Error(reason) => {
+                    this.stopper.ended = true;
+                    return this.fail(reason);
+                }
+                ClientResponse::Done => {
+                    this.stopper.ended = true;
+                    return this.finish();
+                }
+                other => {
+                    log::warn!("Unexpected response in a stream: {:?}", other);
+                }
+            }
+        }
+    }
+}
+
+/// The stream of responses of all flows of a subtree.
+///
+/// Every response is paired with the path of its flow. States of flows
+/// are sent again after reconnection. The stream ends when the server
+/// closes it or reports an error.
+/// Dropping of the subscription stops the stream on the server.
+pub struct TreeSubscription {
+    rx: mpsc::UnboundedReceiver<StreamMessage>,
+    stopper: Stopper,
+    done: bool,
+}
+
+impl TreeSubscription {
+    fn finish(&mut self) -> Poll<Option<(Path, ClientResponse)>> {
+        self.done = true;
+        self.rx.close();
+        Poll::Ready(None)
+    }
+}
+
+impl Stream for TreeSubscription {
+    type Item = (Path, ClientResponse);
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut TaskContext<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if this.done {
+            return Poll::Ready(None);
+        }
+        loop {
+            let message = ready!(this.rx.poll_next_unpin(cx));
+            let response = match message {
+                Some(StreamMessage::Response(response)) => response,
+                Some(StreamMessage::Interrupted) => {
+                    // States will be received after reconnection
+                    continue;
+                }
+                None => {
+                    this.stopper.ended = true;
+                    return this.finish();
+                }
+            };
+            match response {
+                ClientResponse::Tagged { path, response } => {
+                    return Poll::Ready(Some((path, *response)));
+                }
+                ClientResponse::Error(reason) => {
+                    log::error!("Stream of a subtree failed: {}", reason);
+                    this.stopper.ended = true;
+                    return this.finish();
+                }
+                ClientResponse::Done => {
+                    this.stopper.ended = true;
+                    return this.finish();
+                }
+                other => {
+                    log::warn!("Unexpected response in a stream of a subtree: {:?}", other);
+                }
+            }
+        }
+    }
+}
@@ -12,6 +12,7 @@ impl Interaction for WaitReady {
 }
 
 impl RillClientLink {
+    /// Waits for the session to reach the `ReadyToWork` level.
     pub async fn wait_ready(&mut self) -> InteractionTask<WaitReady> {
         let msg = WaitReady;
         self.address.interact(msg)
@@ -30,6 +31,13 @@ impl Notifier {
             log::error!("Can't notify a listener that the client is ready.");
         }
     }
+
+    fn fail(self, reason: &str) {
+        let err = Error::msg(format!("Client can't be ready: {}", reason));
+        if self.responder.send(Err(err)).is_err() {
+            log::error!("Can't notify a listener that the client failed.");
+        }
+    }
 }
 
 #[async_trait]
@@ -40,8 +48,10 @@ impl ActionHandler<Interact<WaitReady>> for RillClient {
         _ctx: &mut Context<Self>,
     ) -> Result<(), Error> {
         let notifier = Notifier::from(input.responder);
-        if self.sender.is_some() {
+        if self.is_ready() {
             notifier.notify();
+        } else if self.is_final() {
+            notifier.fail("the connection won't be restored");
         } else {
             self.awaiting_clients.push_back(notifier);
         }
@@ -55,4 +65,10 @@ impl RillClient {
             notifier.notify();
         }
     }
+
+    pub(super) fn reject_awaiting_clients(&mut self, reason: &str) {
+        for notifier in self.awaiting_clients.drain(..) {
+            notifier.fail(reason);
+        }
+    }
 }
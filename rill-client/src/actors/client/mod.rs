@@ -1,2 +1,4 @@
 mod actor;
-pub use actor::{RillClient, RillClientLink};
+pub use actor::{
+    FlowUpdate, QueryError, RillClient, RillClientLink, Subscription, TreeSubscription,
+};
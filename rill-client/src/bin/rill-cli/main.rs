@@ -0,0 +1,124 @@
+mod registry;
+
+use anyhow::Error;
+use clap::{Parser, Subcommand};
+use meio::System;
+use registry::Registry;
+use rill_client::{ClientConfig, RillClient, RillClientLink};
+use rill_protocol::encoding::CodecKind;
+use rill_protocol::io::provider::{EntryId, Path};
+use rill_transport::TlsConfig;
+use serde_json::Value;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Browses, tails and controls flows of providers.
+#[derive(Parser)]
+#[clap(name = "rill-cli", version)]
+struct Opts {
+    /// Url of the node (ws://, wss://, tcp:// or unix://)
+    #[clap(long, default_value = "ws://localhost:1636/live/client")]
+    url: String,
+    /// The token to authorize the client
+    #[clap(long, env = "RILLRATE_TOKEN")]
+    token: Option<String>,
+    /// CA bundle to verify a wss:// node
+    #[clap(long)]
+    ca: Option<PathBuf>,
+    /// Client certificate for mutual TLS
+    #[clap(long, requires = "key")]
+    cert: Option<PathBuf>,
+    /// Client private key for mutual TLS
+    #[clap(long, requires = "cert")]
+    key: Option<PathBuf>,
+    /// Server name to verify instead of the host
+    #[clap(long)]
+    server_name: Option<String>,
+    /// Codec of frames if the node supports it
+    #[clap(long)]
+    codec: Option<CodecKind>,
+    /// Timeout of requests in seconds
+    #[clap(long, default_value = "5")]
+    timeout: u64,
+    #[clap(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Lists all paths of a provider
+    Ls { provider: EntryId },
+    /// Streams the state and events of a flow as JSON lines
+    Tail { path: Path },
+    /// Prints the current state of a flow
+    Snapshot { path: Path },
+    /// Sends an action in JSON to a flow
+    Act { path: Path, action: String },
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    env_logger::try_init()?;
+    let opts = Opts::parse();
+    let config = ClientConfig {
+        url: Some(opts.url.clone()),
+        token: opts.token.clone(),
+        tls: TlsConfig {
+            ca: opts.ca.clone(),
+            cert: opts.cert.clone(),
+            key: opts.key.clone(),
+            server_name: opts.server_name.clone(),
+        },
+        codec: opts.codec,
+        ..ClientConfig::default()
+    };
+    let address = System::spawn(RillClient::from_config(config));
+    let mut link = RillClientLink::from(address.clone());
+    let result = run(&mut link, opts).await;
+    System::interrupt(&address)?;
+    result
+}
+
+async fn run(link: &mut RillClientLink, opts: Opts) -> Result<(), Error> {
+    let timeout = Duration::from_secs(opts.timeout);
+    let ready = link.wait_ready().await.recv();
+    tokio::time::timeout(timeout, ready)
+        .await
+        .map_err(|_| Error::msg("The node is not ready."))??;
+    let registry = Registry::new();
+    match opts.command {
+        Command::Ls { provider } => {
+            let discovery = link.discover(provider).await?;
+            let mut descriptions = discovery.list(None);
+            descriptions.sort_by(|left, right| left.path.cmp(&right.path));
+            for description in descriptions {
+                println!("{}\t{}", description.path, description.stream_type);
+            }
+        }
+        Command::Tail { path } => {
+            let description = link.describe(path.clone(), timeout).await?;
+            registry
+                .get(&description.stream_type)?
+                .tail(link, path)
+                .await?;
+        }
+        Command::Snapshot { path } => {
+            let description = link.describe(path.clone(), timeout).await?;
+            let state = registry
+                .get(&description.stream_type)?
+                .snapshot(link, path, timeout)
+                .await?;
+            println!("{}", serde_json::to_string_pretty(&state)?);
+        }
+        Command::Act { path, action } => {
+            let action: Value = serde_json::from_str(&action)?;
+            let description = link.describe(path.clone(), timeout).await?;
+            registry
+                .get(&description.stream_type)?
+                .act(link, path, action, timeout)
+                .await?;
+            println!("Delivered");
+        }
+    }
+    Ok(())
+}
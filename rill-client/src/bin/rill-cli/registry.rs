@@ -0,0 +1,107 @@
+use anyhow::Error;
+use async_trait::async_trait;
+use futures::StreamExt;
+use rill_client::{FlowUpdate, RillClientLink};
+use rill_protocol::flow::core::Flow;
+use rill_protocol::flow::meta::{AlertState, LatencyState, PathState, ReadyBoardState};
+use rill_protocol::io::provider::{Path, StreamType};
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::time::Duration;
+
+/// Commands that depend on the type of a flow.
+#[async_trait]
+pub trait FlowKind: Send + Sync {
+    async fn tail(&self, link: &mut RillClientLink, path: Path) -> Result<(), Error>;
+
+    async fn snapshot(
+        &self,
+        link: &mut RillClientLink,
+        path: Path,
+        timeout: Duration,
+    ) -> Result<Value, Error>;
+
+    async fn act(
+        &self,
+        link: &mut RillClientLink,
+        path: Path,
+        action: Value,
+        timeout: Duration,
+    ) -> Result<(), Error>;
+}
+
+struct Typed<T> {
+    _flow: PhantomData<T>,
+}
+
+#[async_trait]
+impl<T: Flow> FlowKind for Typed<T> {
+    async fn tail(&self, link: &mut RillClientLink, path: Path) -> Result<(), Error> {
+        let mut subscription = link.subscribe::<T>(path).await?;
+        println!("{}", json!({ "state": subscription.state() }));
+        while let Some(update) = subscription.next().await {
+            let line = match update {
+                FlowUpdate::Event { event, .. } => json!({ "event": event }),
+                FlowUpdate::Resynced { state } => json!({ "resynced": state }),
+                FlowUpdate::Failed { reason } => {
+                    return Err(Error::msg(reason));
+                }
+            };
+            println!("{}", line);
+        }
+        Ok(())
+    }
+
+    async fn snapshot(
+        &self,
+        link: &mut RillClientLink,
+        path: Path,
+        timeout: Duration,
+    ) -> Result<Value, Error> {
+        let state = link.snapshot::<T>(path, timeout).await?;
+        Ok(serde_json::to_value(state)?)
+    }
+
+    async fn act(
+        &self,
+        link: &mut RillClientLink,
+        path: Path,
+        action: Value,
+        timeout: Duration,
+    ) -> Result<(), Error> {
+        let action: T::Action = serde_json::from_value(action)?;
+        link.act::<T>(path, action, timeout).await?;
+        Ok(())
+    }
+}
+
+/// Known flows by their `StreamType`.
+pub struct Registry {
+    flows: HashMap<StreamType, Box<dyn FlowKind>>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        let mut this = Self {
+            flows: HashMap::new(),
+        };
+        this.register::<AlertState>();
+        this.register::<LatencyState>();
+        this.register::<PathState>();
+        this.register::<ReadyBoardState>();
+        this
+    }
+
+    pub fn register<T: Flow>(&mut self) {
+        let kind = Typed::<T> { _flow: PhantomData };
+        self.flows.insert(T::stream_type(), Box::new(kind));
+    }
+
+    pub fn get(&self, stream_type: &StreamType) -> Result<&dyn FlowKind, Error> {
+        self.flows
+            .get(stream_type)
+            .map(Box::as_ref)
+            .ok_or_else(|| Error::msg(format!("Unknown stream type: {}", stream_type)))
+    }
+}
@@ -0,0 +1,46 @@
+//! Configuration of the client
+
+use rill_protocol::encoding::CodecKind;
+use rill_protocol::io::auth::Credentials;
+use rill_protocol::io::heartbeat;
+use rill_transport::{ReconnectPolicy, TlsConfig};
+use std::time::Duration;
+
+/// Client configuration
+#[derive(Debug, Clone, Default)]
+pub struct ClientConfig {
+    /// Url of the node (`ws://localhost:1636/live/client` by default)
+    ///
+    /// `tcp://` and `unix://` urls connect with framed transports.
+    /// `wss://` urls use the `tls` settings.
+    pub url: Option<String>,
+    /// The token to authorize the client
+    pub token: Option<String>,
+    /// Interval between pings (if the node supports heartbeats)
+    pub ping_interval: Option<Duration>,
+    /// Time to wait for a pong before reconnecting
+    pub ping_deadline: Option<Duration>,
+    /// TLS settings of `wss://` connections
+    pub tls: TlsConfig,
+    /// Delays between attempts to connect to the node
+    pub reconnect: ReconnectPolicy,
+    /// The codec used if the node supports it (`flexbuffers` by default)
+    pub codec: Option<CodecKind>,
+}
+
+impl ClientConfig {
+    /// Interval between pings
+    pub fn ping_interval(&self) -> Duration {
+        self.ping_interval.unwrap_or(heartbeat::PING_INTERVAL)
+    }
+
+    /// The deadline of a pong
+    pub fn ping_deadline(&self) -> Duration {
+        self.ping_deadline.unwrap_or(heartbeat::PING_DEADLINE)
+    }
+
+    /// Credentials of the client
+    pub fn credentials(&self) -> Option<Credentials> {
+        self.token.clone().map(Credentials::token)
+    }
+}
@@ -0,0 +1,247 @@
+//! Discovery of paths published by providers.
+
+use crate::{FlowUpdate, RillClientLink, Subscription};
+use anyhow::Error;
+use futures::channel::mpsc;
+use futures::StreamExt;
+use rill_protocol::flow::meta::path::{PathEvent, PathState, PATHS};
+use rill_protocol::io::provider::{Description, EntryId, Path, StreamType};
+use rill_protocol::pathfinder::{Pathfinder, Record};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use tokio::task::JoinHandle;
+
+/// A change of the paths of a provider.
+#[derive(Debug, Clone)]
+pub enum DiscoveryEvent {
+    Added(Description),
+    Removed(Description),
+}
+
+impl DiscoveryEvent {
+    fn description(&self) -> &Description {
+        match self {
+            Self::Added(description) | Self::Removed(description) => description,
+        }
+    }
+}
+
+struct Watcher {
+    stream_type: Option<StreamType>,
+    sender: mpsc::UnboundedSender<DiscoveryEvent>,
+}
+
+impl Watcher {
+    fn matches(&self, description: &Description) -> bool {
+        matches(&self.stream_type, description)
+    }
+}
+
+fn matches(stream_type: &Option<StreamType>, description: &Description) -> bool {
+    stream_type
+        .as_ref()
+        .map(|stream_type| stream_type == &description.stream_type)
+        .unwrap_or(true)
+}
+
+struct Registry {
+    provider: EntryId,
+    description: Description,
+    /// Descriptions with full paths (including the provider).
+    tree: Pathfinder<Description>,
+    watchers: Vec<Watcher>,
+}
+
+impl Registry {
+    fn new(provider: EntryId, state: PathState) -> Self {
+        let mut this = Self {
+            provider,
+            description: state.description.clone(),
+            tree: Pathfinder::new(),
+            watchers: Vec::new(),
+        };
+        this.update(state);
+        this
+    }
+
+    /// Converts a path of a provider to the path available for clients.
+    fn full_path(&self, path: Path) -> Path {
+        let entries: Vec<_> = std::iter::once(self.provider.clone()).chain(path).collect();
+        Path::from(entries)
+    }
+
+    /// Replaces all paths with the new state and notifies watchers about the difference.
+    fn update(&mut self, state: PathState) {
+        self.description = state.description;
+        let mut paths = BTreeMap::new();
+        for (path, mut description) in state.paths {
+            let path = self.full_path(path);
+            description.path = path.clone();
+            paths.insert(path, description);
+        }
+        let removed: Vec<_> = self
+            .tree
+            .walk()
+            .map(|(path, _)| path)
+            .filter(|path| !paths.contains_key(path))
+            .collect();
+        let added: Vec<_> = paths
+            .into_iter()
+            .filter(|(path, description)| self.find(path) != Some(description))
+            .map(|(_, description)| description)
+            .collect();
+        for path in removed {
+            self.remove(&path);
+        }
+        for description in added {
+            self.add(description);
+        }
+    }
+
+    fn apply(&mut self, event: PathEvent) {
+        match event {
+            PathEvent::AddPath {
+                path,
+                mut description,
+            } => {
+                description.path = self.full_path(path);
+                self.add(description);
+            }
+            PathEvent::RemovePath { path } => {
+                let path = self.full_path(path);
+                self.remove(&path);
+            }
+        }
+    }
+
+    fn find(&self, path: &Path) -> Option<&Description> {
+        self.tree.find(path).and_then(Record::get_link)
+    }
+
+    fn add(&mut self, description: Description) {
+        let path = description.path.clone();
+        self.tree.dig(path).set_link(description.clone());
+        self.notify(DiscoveryEvent::Added(description));
+    }
+
+    fn remove(&mut self, path: &Path) {
+        if let Some(description) = self.tree.remove_link(path) {
+            self.notify(DiscoveryEvent::Removed(description));
+        }
+    }
+
+    /// Descriptions of the specific `StreamType` or all descriptions.
+    fn collect(&self, stream_type: &Option<StreamType>) -> Vec<Description> {
+        let mut descriptions: Vec<_> = self
+            .tree
+            .walk()
+            .map(|(_, description)| description)
+            .filter(|description| matches(stream_type, description))
+            .cloned()
+            .collect();
+        descriptions.sort_by(|left, right| left.path.cmp(&right.path));
+        descriptions
+    }
+
+    fn notify(&mut self, event: DiscoveryEvent) {
+        self.watchers.retain(|watcher| {
+            if watcher.matches(event.description()) {
+                watcher.sender.unbounded_send(event.clone()).is_ok()
+            } else {
+                !watcher.sender.is_closed()
+            }
+        });
+    }
+}
+
+/// The live tree of paths of a provider.
+///
+/// It's updated in the background till dropped.
+pub struct Discovery {
+    registry: Arc<Mutex<Registry>>,
+    handle: JoinHandle<()>,
+}
+
+impl Discovery {
+    fn registry(&self) -> MutexGuard<'_, Registry> {
+        lock(&self.registry)
+    }
+
+    /// The `Description` of the provider.
+    pub fn provider(&self) -> Description {
+        self.registry().description.clone()
+    }
+
+    /// Lists all paths or paths of the specific `StreamType` only.
+    pub fn list(&self, stream_type: Option<StreamType>) -> Vec<Description> {
+        self.registry().collect(&stream_type)
+    }
+
+    /// Finds the `Description` of a full path.
+    pub fn find(&self, path: &Path) -> Option<Description> {
+        self.registry().find(path).cloned()
+    }
+
+    /// Watches for changes of paths.
+    ///
+    /// The existing paths are sent as `DiscoveryEvent::Added` first.
+    pub fn watch(
+        &self,
+        stream_type: Option<StreamType>,
+    ) -> mpsc::UnboundedReceiver<DiscoveryEvent> {
+        let (tx, rx) = mpsc::unbounded();
+        let mut registry = self.registry();
+        for description in registry.collect(&stream_type) {
+            let event = DiscoveryEvent::Added(description);
+            tx.unbounded_send(event).ok();
+        }
+        let watcher = Watcher {
+            stream_type,
+            sender: tx,
+        };
+        registry.watchers.push(watcher);
+        rx
+    }
+}
+
+impl Drop for Discovery {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+/// Locks the registry. The registry is never left half-updated,
+/// so it's still usable if the lock is poisoned.
+fn lock(registry: &Mutex<Registry>) -> MutexGuard<'_, Registry> {
+    registry.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+async fn track(registry: Arc<Mutex<Registry>>, mut subscription: Subscription<PathState>) {
+    while let Some(update) = subscription.next().await {
+        let mut registry = lock(&registry);
+        match update {
+            FlowUpdate::Event { event, .. } => {
+                registry.apply(event);
+            }
+            FlowUpdate::Resynced { state } => {
+                registry.update(state);
+            }
+            FlowUpdate::Failed { reason } => {
+                log::error!("Discovery stream failed: {}", reason);
+            }
+        }
+    }
+    log::debug!("Discovery stream closed.");
+}
+
+impl RillClientLink {
+    /// Subscribes to paths of the provider and keeps them updated.
+    pub async fn discover(&mut self, provider: EntryId) -> Result<Discovery, Error> {
+        let path = PATHS.of(Path::single(provider.clone()));
+        let subscription = self.subscribe::<PathState>(path).await?;
+        let state = subscription.state().clone();
+        let registry = Arc::new(Mutex::new(Registry::new(provider, state)));
+        let handle = tokio::spawn(track(registry.clone(), subscription));
+        Ok(Discovery { registry, handle })
+    }
+}
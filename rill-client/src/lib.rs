@@ -1,3 +1,8 @@
 mod actors;
+pub mod config;
+pub mod discovery;
 
-pub use actors::client::{RillClient, RillClientLink};
+pub use actors::client::{
+    FlowUpdate, QueryError, RillClient, RillClientLink, Subscription, TreeSubscription,
+};
+pub use config::ClientConfig;
@@ -0,0 +1,106 @@
+use anyhow::Error;
+use meio::System;
+use rill_client::{ClientConfig, RillClient, RillClientLink};
+use rill_protocol::encoding;
+use rill_protocol::io::client::{
+    AccessLevel, ClientProtocol, ClientRequest, ClientResponse, ClientServiceRequest,
+    ClientServiceResponse,
+};
+use rill_protocol::io::codec;
+use rill_protocol::io::handshake::{Handshake, FEATURE_HEARTBEAT};
+use rill_protocol::io::transport::ServiceEnvelope;
+use std::convert::TryFrom;
+use std::io::ErrorKind;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::timeout;
+
+const WAIT: Duration = Duration::from_secs(5);
+
+type NodeMessage = ServiceEnvelope<ClientProtocol, ClientResponse, ClientServiceRequest>;
+type ClientMessage = ServiceEnvelope<ClientProtocol, ClientRequest, ClientServiceResponse>;
+
+async fn send(stream: &mut TcpStream, request: ClientServiceRequest) -> Result<(), Error> {
+    let msg: NodeMessage = ServiceEnvelope::Service(request);
+    let data = encoding::to_vec(&msg)?;
+    stream.write_u32(u32::try_from(data.len())?).await?;
+    stream.write_all(&data).await?;
+    Ok(())
+}
+
+/// Reads the next service message of the client or `None` if the connection is closed.
+async fn recv(stream: &mut TcpStream) -> Result<Option<ClientServiceResponse>, Error> {
+    loop {
+        let len = match stream.read_u32().await {
+            Ok(len) => len,
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let mut data = vec![0; len as usize];
+        stream.read_exact(&mut data).await?;
+        if let ServiceEnvelope::Service(msg) = codec::decode::<ClientMessage>(&data)? {
+            return Ok(Some(msg));
+        }
+    }
+}
+
+/// Waits for a ping of the client.
+async fn ping(stream: &mut TcpStream) -> Result<Option<Instant>, Error> {
+    while let Some(msg) = recv(stream).await? {
+        if let ClientServiceResponse::Ping = msg {
+            return Ok(Some(Instant::now()));
+        }
+    }
+    Ok(None)
+}
+
+#[tokio::test]
+async fn missed_pongs_drop_the_connection() -> Result<(), Error> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let config = ClientConfig {
+        url: Some(format!("tcp://{}", listener.local_addr()?)),
+        ping_interval: Some(Duration::from_millis(200)),
+        ping_deadline: Some(Duration::from_millis(500)),
+        ..ClientConfig::default()
+    };
+    let client = System::spawn(RillClient::from_config(config));
+    let mut link = RillClientLink::from(client.clone());
+
+    let (mut stream, _) = timeout(WAIT, listener.accept()).await??;
+    let handshake = match timeout(WAIT, recv(&mut stream)).await?? {
+        Some(ClientServiceResponse::Hello(handshake)) => handshake,
+        other => panic!("Unexpected message: {:?}", other),
+    };
+    let negotiated = Handshake::default().negotiate(&handshake)?;
+    assert!(negotiated.has_feature(FEATURE_HEARTBEAT));
+    send(&mut stream, ClientServiceRequest::Accepted(negotiated)).await?;
+    let level = ClientServiceRequest::AccessLevel(AccessLevel::ReadyToWork);
+    send(&mut stream, level).await?;
+    timeout(WAIT, link.wait_ready().await.recv()).await??;
+
+    // Pings are sent every interval while pongs are received
+    let mut last = timeout(WAIT, ping(&mut stream)).await??.expect("no ping");
+    for _ in 0..2 {
+        send(&mut stream, ClientServiceRequest::Pong).await?;
+        let next = timeout(WAIT, ping(&mut stream)).await??.expect("no ping");
+        let interval = next.duration_since(last);
+        assert!(interval >= Duration::from_millis(150), "{:?}", interval);
+        assert!(interval < Duration::from_millis(400), "{:?}", interval);
+        last = next;
+    }
+    let latency = link.latency().await?;
+    assert!(latency.borrow().is_some());
+
+    // The connection without pongs is dropped after the deadline
+    let result = timeout(WAIT, ping(&mut stream)).await??;
+    assert!(result.is_none(), "A ping sent before the pong.");
+    let dropped = last.elapsed();
+    assert!(dropped >= Duration::from_millis(500), "{:?}", dropped);
+    assert!(dropped < Duration::from_millis(1_500), "{:?}", dropped);
+    // And the client reconnects
+    timeout(WAIT, listener.accept()).await??;
+
+    System::interrupt(&client)?;
+    Ok(())
+}
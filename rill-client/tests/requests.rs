@@ -0,0 +1,203 @@
+use anyhow::Error;
+use futures::StreamExt;
+use meio::{Address, System};
+use rill_client::{ClientConfig, FlowUpdate, RillClient, RillClientLink};
+use rill_protocol::encoding;
+use rill_protocol::flow::core::Flow;
+use rill_protocol::flow::meta::alert::AlertState;
+use rill_protocol::io::client::{
+    AccessLevel, ClientProtocol, ClientReqId, ClientRequest, ClientResponse, ClientServiceRequest,
+    ClientServiceResponse,
+};
+use rill_protocol::io::codec;
+use rill_protocol::io::handshake::Handshake;
+use rill_protocol::io::provider::{
+    Description, FlowControl, RecorderAction, RecorderRequest, StreamType,
+};
+use rill_protocol::io::transport::{Envelope, ServiceEnvelope};
+use rill_transport::ReconnectPolicy;
+use std::convert::TryFrom;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::timeout;
+
+const WAIT: Duration = Duration::from_secs(5);
+
+type NodeMessage = ServiceEnvelope<ClientProtocol, ClientResponse, ClientServiceRequest>;
+type ClientMessage = ServiceEnvelope<ClientProtocol, ClientRequest, ClientServiceResponse>;
+
+async fn send(stream: &mut TcpStream, msg: NodeMessage) -> Result<(), Error> {
+    let data = encoding::to_vec(&msg)?;
+    stream.write_u32(u32::try_from(data.len())?).await?;
+    stream.write_all(&data).await?;
+    Ok(())
+}
+
+async fn respond(
+    stream: &mut TcpStream,
+    direct_id: ClientReqId,
+    response: ClientResponse,
+) -> Result<(), Error> {
+    let envelope = Envelope {
+        direct_id,
+        data: response,
+    };
+    send(stream, ServiceEnvelope::Envelope(envelope)).await
+}
+
+async fn recv(stream: &mut TcpStream) -> Result<ClientMessage, Error> {
+    let len = stream.read_u32().await?;
+    let mut data = vec![0; len as usize];
+    stream.read_exact(&mut data).await?;
+    codec::decode(&data)
+}
+
+/// Reads the next request of the client and skips service messages.
+async fn next_request(stream: &mut TcpStream) -> Result<(ClientReqId, ClientRequest), Error> {
+    loop {
+        if let ServiceEnvelope::Envelope(envelope) = recv(stream).await? {
+            return Ok((envelope.direct_id, envelope.data));
+        }
+    }
+}
+
+/// Accepts a connection of the client and makes the session ready to work.
+async fn accept(listener: &TcpListener) -> Result<TcpStream, Error> {
+    let (mut stream, _) = listener.accept().await?;
+    let handshake = match recv(&mut stream).await? {
+        ServiceEnvelope::Service(ClientServiceResponse::Hello(handshake)) => handshake,
+        other => return Err(Error::msg(format!("Unexpected message: {:?}", other))),
+    };
+    let negotiated = Handshake::default().negotiate(&handshake)?;
+    let accepted = ClientServiceRequest::Accepted(negotiated);
+    send(&mut stream, ServiceEnvelope::Service(accepted)).await?;
+    let level = ClientServiceRequest::AccessLevel(AccessLevel::ReadyToWork);
+    send(&mut stream, ServiceEnvelope::Service(level)).await?;
+    Ok(stream)
+}
+
+fn spawn_client(listener: &TcpListener) -> Result<Address<RillClient>, Error> {
+    let config = ClientConfig {
+        url: Some(format!("tcp://{}", listener.local_addr()?)),
+        reconnect: ReconnectPolicy {
+            initial_delay: Duration::from_millis(50),
+            ..ReconnectPolicy::default()
+        },
+        ..ClientConfig::default()
+    };
+    Ok(System::spawn(RillClient::from_config(config)))
+}
+
+#[tokio::test]
+async fn responses_are_routed_by_ids() -> Result<(), Error> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let client = spawn_client(&listener)?;
+    let mut link = RillClientLink::from(client.clone());
+    let mut access_level = link.access_level().await?;
+    assert_eq!(*access_level.borrow(), None);
+    let mut stream = timeout(WAIT, accept(&listener)).await??;
+    timeout(WAIT, link.wait_ready().await.recv()).await??;
+    timeout(WAIT, async {
+        while *access_level.borrow() != Some(AccessLevel::ReadyToWork) {
+            access_level.changed().await?;
+        }
+        Ok::<_, Error>(())
+    })
+    .await??;
+
+    let mut first_link = RillClientLink::from(client.clone());
+    let path = "a.first".parse()?;
+    let first = tokio::spawn(async move { first_link.describe(path, WAIT).await });
+    let mut second_link = RillClientLink::from(client.clone());
+    let path = "a.second".parse()?;
+    let second = tokio::spawn(async move { second_link.act::<AlertState>(path, (), WAIT).await });
+    let mut requests = Vec::new();
+    for _ in 0..2 {
+        requests.push(timeout(WAIT, next_request(&mut stream)).await??);
+    }
+    // Responses are sent in the reverse order
+    for (direct_id, request) in requests.into_iter().rev() {
+        let response = match request.request {
+            RecorderRequest::Action(RecorderAction::GetFlow) => ClientResponse::Flow(Description {
+                path: request.path,
+                info: "".into(),
+                stream_type: StreamType::from("test"),
+            }),
+            RecorderRequest::Action(RecorderAction::DoAction(_)) => ClientResponse::Delivered,
+            other => return Err(Error::msg(format!("Unexpected request: {:?}", other))),
+        };
+        respond(&mut stream, direct_id, response).await?;
+    }
+    let description = timeout(WAIT, first).await???;
+    assert_eq!(description.path, "a.first".parse()?);
+    timeout(WAIT, second).await???;
+
+    // The access level is reset when the connection is lost
+    drop(stream);
+    timeout(WAIT, async {
+        while access_level.borrow().is_some() {
+            access_level.changed().await?;
+        }
+        Ok::<_, Error>(())
+    })
+    .await??;
+
+    System::interrupt(&client)?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn streams_are_resubscribed_after_reconnection() -> Result<(), Error> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let client = spawn_client(&listener)?;
+    let mut link = RillClientLink::from(client.clone());
+    let mut stream = timeout(WAIT, accept(&listener)).await??;
+    timeout(WAIT, link.wait_ready().await.recv()).await??;
+
+    let mut stream_link = RillClientLink::from(client.clone());
+    let subscription = tokio::spawn(async move {
+        stream_link
+            .subscribe::<AlertState>("a.alerts".parse()?)
+            .await
+    });
+    let (direct_id, request) = timeout(WAIT, next_request(&mut stream)).await??;
+    assert_eq!(request.path, "a.alerts".parse()?);
+    assert!(matches!(
+        request.request,
+        RecorderRequest::ControlStream(FlowControl::StartStream)
+    ));
+    let state = AlertState::new().pack_state()?;
+    respond(&mut stream, direct_id, ClientResponse::State(state.clone())).await?;
+    let mut subscription = timeout(WAIT, subscription).await???;
+
+    // The stream is restored with the same id
+    drop(stream);
+    let mut stream = timeout(WAIT, accept(&listener)).await??;
+    let (restored_id, request) = timeout(WAIT, next_request(&mut stream)).await??;
+    assert_eq!(restored_id, direct_id);
+    assert_eq!(request.path, "a.alerts".parse()?);
+    assert!(matches!(
+        request.request,
+        RecorderRequest::ControlStream(FlowControl::StartStream)
+    ));
+    respond(&mut stream, direct_id, ClientResponse::State(state)).await?;
+    let update = timeout(WAIT, subscription.next()).await?;
+    assert!(
+        matches!(update, Some(FlowUpdate::Resynced { .. })),
+        "{:?}",
+        update
+    );
+
+    // Dropping of the subscription stops the stream
+    drop(subscription);
+    let (stopped_id, request) = timeout(WAIT, next_request(&mut stream)).await??;
+    assert_eq!(stopped_id, direct_id);
+    assert!(matches!(
+        request.request,
+        RecorderRequest::ControlStream(FlowControl::StopStream)
+    ));
+
+    System::interrupt(&client)?;
+    Ok(())
+}
@@ -0,0 +1,91 @@
+use anyhow::Error;
+use meio::System;
+use rill_client::{ClientConfig, QueryError, RillClient, RillClientLink};
+use rill_protocol::encoding;
+use rill_protocol::flow::meta::alert::AlertState;
+use rill_protocol::io::client::{
+    AccessLevel, ClientProtocol, ClientRequest, ClientResponse, ClientServiceRequest,
+    ClientServiceResponse,
+};
+use rill_protocol::io::provider::{RecorderAction, RecorderRequest};
+use rill_protocol::io::transport::ServiceEnvelope;
+use std::convert::TryFrom;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::time::timeout;
+
+const WAIT: Duration = Duration::from_secs(5);
+
+type NodeMessage = ServiceEnvelope<ClientProtocol, ClientResponse, ClientServiceRequest>;
+type ClientMessage = ServiceEnvelope<ClientProtocol, ClientRequest, ClientServiceResponse>;
+
+/// Writes a frame as a node of the first version: with the default codec.
+async fn send(stream: &mut TcpStream, request: ClientServiceRequest) -> Result<(), Error> {
+    let msg: NodeMessage = ServiceEnvelope::Service(request);
+    let data = encoding::to_vec(&msg)?;
+    stream.write_u32(u32::try_from(data.len())?).await?;
+    stream.write_all(&data).await?;
+    Ok(())
+}
+
+/// Reads a frame encoded with the default codec.
+async fn recv(stream: &mut TcpStream) -> Result<ClientMessage, Error> {
+    let len = stream.read_u32().await?;
+    let mut data = vec![0; len as usize];
+    stream.read_exact(&mut data).await?;
+    encoding::from_slice(&data)
+}
+
+#[tokio::test]
+async fn nodes_without_handshakes_are_ready() -> Result<(), Error> {
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let config = ClientConfig {
+        url: Some(format!("tcp://{}", listener.local_addr()?)),
+        ping_interval: Some(Duration::from_millis(100)),
+        ping_deadline: Some(Duration::from_secs(1)),
+        ..ClientConfig::default()
+    };
+    let client = System::spawn(RillClient::from_config(config));
+    let mut link = RillClientLink::from(client.clone());
+
+    let (mut stream, _) = timeout(WAIT, listener.accept()).await??;
+    send(
+        &mut stream,
+        ClientServiceRequest::AccessLevel(AccessLevel::SessionCreated),
+    )
+    .await?;
+    send(
+        &mut stream,
+        ClientServiceRequest::AccessLevel(AccessLevel::ReadyToWork),
+    )
+    .await?;
+    // The node reads the handshake, but doesn't answer it
+    let hello = timeout(WAIT, recv(&mut stream)).await??;
+    assert!(matches!(
+        hello,
+        ServiceEnvelope::Service(ClientServiceResponse::Hello(_))
+    ));
+
+    timeout(WAIT, link.wait_ready().await.recv()).await??;
+
+    // The node doesn't confirm actions
+    let result = link
+        .act::<AlertState>("test.alerts".parse()?, (), WAIT)
+        .await;
+    assert!(
+        matches!(result, Err(QueryError::Unconfirmed)),
+        "{:?}",
+        result
+    );
+    match timeout(WAIT, recv(&mut stream)).await?? {
+        ServiceEnvelope::Envelope(envelope) => assert!(matches!(
+            envelope.data.request,
+            RecorderRequest::Action(RecorderAction::DoAction(_))
+        )),
+        other => panic!("Unexpected message: {:?}", other),
+    }
+
+    System::interrupt(&client)?;
+    Ok(())
+}
@@ -1,44 +1,97 @@
+mod loopback;
 pub mod parcel;
+mod reconnect;
+mod subtree;
 
 use crate::actors::engine::RillEngine;
 use crate::actors::recorder::{Recorder, RecorderLink};
 use crate::config::EngineConfig;
-use crate::tracers::meta::PathTracer;
+use crate::loopback::{NodeMessage, ProviderMessage};
+use crate::tracers::meta::{LatencyTracer, PathTracer};
+use crate::tracers::tracer::TracerError;
 use anyhow::Error;
 use async_trait::async_trait;
+use loopback::LoopbackClient;
+use meio::task::{HeartBeat, OnTick, Tick};
 use meio::{
     ActionHandler, Actor, Context, Eliminated, Id, IdOf, InstantActionHandler, InterruptedBy,
-    StartedBy, TaskEliminated, TaskError,
+    StartedBy, TaskAddress, TaskEliminated, TaskError,
 };
 use meio_connect::{
-    client::{WsClient, WsClientStatus, WsSender},
+    client::{WsClient, WsClientStatus},
     WsIncoming,
 };
 use rill_protocol::flow::core;
+use rill_protocol::flow::meta::latency::LATENCY;
 use rill_protocol::flow::meta::path::PATHS;
+use rill_protocol::io::client::AccessLevel;
+use rill_protocol::io::codec::{self, Frame, Settings};
+use rill_protocol::io::handshake::{Handshake, Negotiated, FEATURE_HEARTBEAT};
+use rill_protocol::io::heartbeat::{Heartbeat, Pulse};
 use rill_protocol::io::provider::{
-    Description, ProviderProtocol, ProviderToServer, ServerToProvider,
+    Description, FlowControl, PathPattern, ProviderProtocol, ProviderReqId, ProviderServiceRequest,
+    ProviderToServer, RecorderRequest, ServerToProvider,
 };
-use rill_protocol::io::transport::{Direction, Envelope, WideEnvelope};
+use rill_protocol::io::transport::{Direction, Envelope, ServiceEnvelope, WideEnvelope};
 use rill_protocol::pathfinder::{Pathfinder, Record};
+use rill_transport::{
+    Backoff, ConnectionStatus, Endpoint, TlsConnector, TransportClient, TransportSender,
+    TransportStatus,
+};
 use std::collections::HashMap;
-use std::time::Duration;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::Instant;
+use tokio::sync::watch;
+
+/// The state of a connection agreed in the handshake.
+struct Session {
+    settings: Settings,
+    negotiated: Negotiated,
+}
+
+/// Every connection starts as a session of the first version.
+impl Default for Session {
+    fn default() -> Self {
+        Self {
+            settings: Settings::default(),
+            negotiated: Negotiated::v1(),
+        }
+    }
+}
 
 /// Wrapper for WebSocket connection for sending responses (notifications) to a server.
 #[derive(Default, Clone)]
 pub(crate) struct RillSender {
-    sender: Option<WsSender<WideEnvelope<ProviderProtocol, ProviderToServer>>>,
+    sender: Option<TransportSender<Frame<ProviderMessage>>>,
+    /// The session of the connection shared by all clones of the sender.
+    session: Arc<RwLock<Session>>,
 }
 
 impl RillSender {
-    /*
     pub fn is_connected(&self) -> bool {
         self.sender.is_some()
     }
-    */
 
-    fn set(&mut self, sender: WsSender<WideEnvelope<ProviderProtocol, ProviderToServer>>) {
+    fn set(&mut self, sender: TransportSender<Frame<ProviderMessage>>) {
         self.sender = Some(sender);
+        self.session = Arc::default();
+    }
+
+    /// Applies the session agreed in the handshake to all clones of the sender.
+    fn negotiated(&self, settings: Settings, negotiated: Negotiated) {
+        *self.session.write().unwrap_or_else(PoisonError::into_inner) = Session {
+            settings,
+            negotiated,
+        };
+    }
+
+    /// Returns `true` if the node of the connection supports the feature.
+    pub fn has_feature(&self, feature: &str) -> bool {
+        self.session
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .negotiated
+            .has_feature(feature)
     }
 
     pub fn reset(&mut self) {
@@ -48,15 +101,38 @@ impl RillSender {
     pub fn response(&mut self, direction: Direction<ProviderProtocol>, data: ProviderToServer) {
         if let Some(sender) = self.sender.as_ref() {
             let envelope = WideEnvelope { direction, data };
-            sender.send(envelope);
+            let settings = self
+                .session
+                .read()
+                .unwrap_or_else(PoisonError::into_inner)
+                .settings;
+            match settings.frame(envelope) {
+                Ok(frame) => sender.send(frame),
+                Err(err) => log::error!("Can't encode a response: {}", err),
+            }
         } else {
             log::error!("Can't send a response. Not connected.");
         }
     }
 }
 
+/// Decodes a message of a node.
+///
+/// Nodes of the first version send requests without service envelopes.
+fn decode_node_message(frame: Frame<NodeMessage>) -> Result<NodeMessage, Error> {
+    match frame {
+        Frame::Message(msg) => Ok(msg),
+        Frame::Encoded(data) => codec::decode(&data).or_else(|err| {
+            codec::decode::<Envelope<ProviderProtocol, ServerToProvider>>(&data)
+                .map(ServiceEnvelope::Envelope)
+                .map_err(|_| err)
+        }),
+    }
+}
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum Group {
+    HeartBeat,
     WsConnection,
     /// Interactions
     ActiveRequests,
@@ -68,14 +144,29 @@ pub struct RillConnector {
     url: String,
     config: EngineConfig,
     sender: RillSender,
+    ws_client: Option<TaskAddress<WsClient<ProviderProtocol, Self>>>,
+    transport_client: Option<TaskAddress<TransportClient<ProviderProtocol, Self>>>,
+    loopback_client: Option<TaskAddress<LoopbackClient>>,
+    tls_connector: Option<TlsConnector>,
+    backoff: Backoff,
+    status: watch::Sender<ConnectionStatus>,
+    heartbeat: Heartbeat,
+    /// The time the provider was declared if the node hasn't answered the handshake.
+    handshake: Option<Instant>,
     recorders: Pathfinder<RecorderLink>,
-    registered: HashMap<Id, Description>,
+    registered: HashMap<Id, Arc<Description>>,
+    /// Subscriptions to subtrees of flows.
+    subtrees: HashMap<ProviderReqId, PathPattern>,
     path_flow: PathTracer,
+    latency_flow: LatencyTracer,
     description: Description,
 }
 
 impl RillConnector {
-    pub fn new(config: EngineConfig) -> Self {
+    pub fn new(
+        config: EngineConfig,
+        status: watch::Sender<ConnectionStatus>,
+    ) -> Result<Self, TracerError> {
         let entry_id = config.provider_name();
         let provider_type = config.provider_type();
         let description = Description {
@@ -84,20 +175,145 @@ impl RillConnector {
             stream_type: provider_type,
         };
         let paths = PATHS.root();
-        Self {
+        let heartbeat = Heartbeat::new(config.ping_deadline());
+        let backoff = Backoff::new(config.reconnect.clone());
+        Ok(Self {
             url: config.node_url(),
             config,
             sender: RillSender::default(),
+            ws_client: None,
+            transport_client: None,
+            loopback_client: None,
+            tls_connector: None,
+            backoff,
+            status,
+            heartbeat,
+            handshake: None,
             recorders: Pathfinder::default(),
             registered: HashMap::new(),
-            path_flow: PathTracer::new(paths, description.clone()),
+            subtrees: HashMap::new(),
+            path_flow: PathTracer::new(paths, description.clone())?,
+            latency_flow: LatencyTracer::new(LATENCY.root())?,
             description,
-        }
+        })
     }
 
     fn send_global(&mut self, msg: ProviderToServer) {
         self.sender.response(Direction::broadcast(), msg);
     }
+
+    /// TLS settings loaded once for all attempts to connect.
+    fn tls_connector(&mut self) -> Result<TlsConnector, Error> {
+        match &self.tls_connector {
+            Some(tls) => Ok(tls.clone()),
+            None => {
+                let tls = self.config.tls().load()?;
+                self.tls_connector = Some(tls.clone());
+                Ok(tls)
+            }
+        }
+    }
+
+    fn spawn_client(&mut self, ctx: &mut Context<Self>) -> Result<(), Error> {
+        self.set_status(ConnectionStatus::Connecting);
+        if let Some(loopback) = self.config.loopback.clone() {
+            let client = LoopbackClient::new(loopback, ctx.address().clone());
+            let task = ctx.spawn_task(client, (), Group::WsConnection);
+            self.loopback_client = Some(task);
+            return Ok(());
+        }
+        // Attempts are repeated by the connector according to the policy
+        let repeat = None;
+        match self.url.parse()? {
+            Endpoint::Ws(url) => {
+                let client = WsClient::new(url, repeat, ctx.address().clone());
+                let task = ctx.spawn_task(client, (), Group::WsConnection);
+                self.ws_client = Some(task);
+            }
+            endpoint => {
+                let tls = match endpoint {
+                    Endpoint::Wss(_) => Some(self.tls_connector()?),
+                    _ => None,
+                };
+                let client = TransportClient::new(endpoint, tls, repeat, ctx.address().clone());
+                let task = ctx.spawn_task(client, (), Group::WsConnection);
+                self.transport_client = Some(task);
+            }
+        }
+        Ok(())
+    }
+
+    fn stop_client(&mut self) -> Result<(), Error> {
+        if let Some(client) = self.ws_client.take() {
+            client.stop()?;
+        }
+        if let Some(client) = self.transport_client.take() {
+            client.stop()?;
+        }
+        if let Some(client) = self.loopback_client.take() {
+            client.stop()?;
+        }
+        Ok(())
+    }
+
+    async fn established(
+        &mut self,
+        sender: TransportSender<Frame<ProviderMessage>>,
+    ) -> Result<(), Error> {
+        // The node may not support the settings of the previous connection
+        self.sender.set(sender);
+        self.heartbeat.reset();
+        self.handshake = Some(Instant::now());
+
+        // Recorders get the connection when the node verified the provider
+        let description = self.description.clone();
+        let credentials = self.config.credentials();
+        let msg = ProviderToServer::Declare {
+            description,
+            credentials,
+            handshake: Some(Handshake::default()),
+        };
+        self.send_global(msg);
+        Ok(())
+    }
+
+    /// The node verified the provider.
+    async fn ready(&mut self) {
+        self.handshake = None;
+        self.backoff.reset();
+        self.set_status(ConnectionStatus::Connected);
+        self.connected().await;
+    }
+
+    /// Nodes of the first version don't answer the handshake and
+    /// never verify the provider, so it's ready to work when such
+    /// a node sent a request or didn't answer in time.
+    async fn ready_v1(&mut self) {
+        log::info!(
+            "Node {} has no handshake. Using the first version.",
+            self.url
+        );
+        self.ready().await;
+    }
+
+    async fn connected(&mut self) {
+        for (_, link) in self.recorders.walk_mut() {
+            // TODO: Run in parallel for all links
+            link.connected(self.sender.clone()).await.ok();
+        }
+    }
+
+    async fn disconnected(&mut self) {
+        self.sender.reset();
+        self.heartbeat.reset();
+        self.handshake = None;
+        self.latency_flow.lost();
+        self.subtrees.clear();
+        for (_, link) in self.recorders.walk_mut() {
+            // TODO: Run in parallel for all links
+            link.disconnected().await.ok();
+        }
+    }
 }
 
 #[async_trait]
@@ -114,6 +330,7 @@ impl StartedBy<RillEngine> for RillConnector {
     async fn handle(&mut self, ctx: &mut Context<Self>) -> Result<(), Error> {
         // TODO: Replace with strum iter
         ctx.termination_sequence(vec![
+            Group::HeartBeat,
             Group::ActiveRequests,
             Group::WsConnection,
             Group::ParcelStream,
@@ -122,12 +339,10 @@ impl StartedBy<RillEngine> for RillConnector {
 
         self.attach_distributor(ctx).await?;
 
-        let client = WsClient::new(
-            self.config.node_url(),
-            Some(Duration::from_secs(1)),
-            ctx.address().clone(),
-        );
-        ctx.spawn_task(client, (), Group::WsConnection);
+        self.spawn_client(ctx)?;
+
+        let heartbeat = HeartBeat::new(self.config.ping_interval(), ctx.address().clone());
+        ctx.spawn_task(heartbeat, (), Group::HeartBeat);
 
         Ok(())
     }
@@ -151,37 +366,11 @@ impl InstantActionHandler<WsClientStatus<ProviderProtocol>> for RillConnector {
     ) -> Result<(), Error> {
         match status {
             WsClientStatus::Connected { sender } => {
-                // TODO: Resend new sender to all `Recorders`
-                self.sender.set(sender);
-
-                for desc in self.registered.values_mut() {
-                    // TODO: Use `Pathfinder::walk` to perform that
-                    let path = &desc.path;
-                    let link = self.recorders.find_mut(path).and_then(Record::get_link_mut);
-                    if let Some(link) = link {
-                        // TODO: Run in parallel for all links
-                        link.connected(self.sender.clone()).await.ok();
-                    }
-                }
-
-                let description = self.description.clone();
-                let msg = ProviderToServer::Declare { description };
-                self.send_global(msg);
+                self.established(sender.into()).await?;
             }
             WsClientStatus::Failed { reason } => {
                 log::error!("Connection failed: {}", reason);
-                // TODO: Try to reconnect...
-
-                // TODO: DRY!!! See above! It's the same (
-                for desc in self.registered.values_mut() {
-                    // TODO: Use `Pathfinder::walk` to perform that
-                    let path = &desc.path;
-                    let link = self.recorders.find_mut(path).and_then(Record::get_link_mut);
-                    if let Some(link) = link {
-                        // TODO: Run in parallel for all links
-                        link.disconnected().await.ok();
-                    }
-                }
+                self.disconnected().await;
             }
         }
         Ok(())
@@ -189,23 +378,97 @@ impl InstantActionHandler<WsClientStatus<ProviderProtocol>> for RillConnector {
 }
 
 #[async_trait]
-impl ActionHandler<WsIncoming<Envelope<ProviderProtocol, ServerToProvider>>> for RillConnector {
+impl InstantActionHandler<TransportStatus<ProviderProtocol>> for RillConnector {
     async fn handle(
         &mut self,
-        msg: WsIncoming<Envelope<ProviderProtocol, ServerToProvider>>,
+        status: TransportStatus<ProviderProtocol>,
         _ctx: &mut Context<Self>,
     ) -> Result<(), Error> {
-        let envelope = msg.0;
+        match status {
+            TransportStatus::Connected { sender } => {
+                self.established(sender).await?;
+            }
+            TransportStatus::Failed { reason } => {
+                log::error!("Connection failed: {}", reason);
+                self.disconnected().await;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ActionHandler<WsIncoming<Frame<NodeMessage>>> for RillConnector {
+    async fn handle(
+        &mut self,
+        msg: WsIncoming<Frame<NodeMessage>>,
+        _ctx: &mut Context<Self>,
+    ) -> Result<(), Error> {
+        let envelope = match decode_node_message(msg.0)? {
+            ServiceEnvelope::Envelope(envelope) => envelope,
+            ServiceEnvelope::Service(request) => {
+                match request {
+                    ProviderServiceRequest::Ping => {
+                        self.send_global(ProviderToServer::Pong);
+                    }
+                    ProviderServiceRequest::Pong => {
+                        if let Some(latency) = self.heartbeat.pong(Instant::now()) {
+                            self.latency_flow.measured(latency);
+                        }
+                    }
+                    ProviderServiceRequest::Accepted(negotiated) => {
+                        log::debug!("Protocol negotiated with {}: {:?}", self.url, negotiated);
+                        self.handshake = None;
+                        let settings = negotiated.settings(&self.config.settings());
+                        self.sender.negotiated(settings, negotiated);
+                    }
+                    ProviderServiceRequest::Incompatible { reason }
+                    | ProviderServiceRequest::AccessDenied { reason } => {
+                        log::error!("Node {} rejected the provider: {}", self.url, reason);
+                        self.set_status(ConnectionStatus::Rejected { reason });
+                        self.disconnected().await;
+                        self.stop_client()?;
+                    }
+                    ProviderServiceRequest::AccessLevel(level) => {
+                        log::debug!("Access level changed to {:?}", level);
+                        if level == AccessLevel::ReadyToWork {
+                            self.ready().await;
+                        }
+                    }
+                    ProviderServiceRequest::NameTaken { name } => {
+                        // The node closes the connection and the next attempt
+                        // will be made when the provider with the name is gone
+                        log::error!("Provider {} is already connected to {}", name, self.url);
+                    }
+                }
+                return Ok(());
+            }
+        };
         log::trace!("Incoming request: {:?}", envelope);
+        if self.handshake.is_some() {
+            self.ready_v1().await;
+        }
         let direct_id = envelope.direct_id;
         let path = envelope.data.path;
+        let request = envelope.data.request;
+        if self.is_subtree(&direct_id) {
+            if let RecorderRequest::ControlStream(FlowControl::StopStream) = request {
+                self.stop_subtree(direct_id).await?;
+            }
+            return Ok(());
+        }
+        let subtree = match request {
+            RecorderRequest::ControlStream(FlowControl::StartStream) => self.subtree_pattern(&path),
+            _ => None,
+        };
         let recorder_link = self
             .recorders
             .find_mut(&path)
             .and_then(Record::get_link_mut);
         if let Some(recorder) = recorder_link {
-            let request = envelope.data.request;
             recorder.do_path_request(direct_id, request).await?;
+        } else if let Some(pattern) = subtree {
+            self.start_subtree(direct_id, pattern).await?;
         } else {
             log::warn!("Path not found: {:?}", path);
             let msg = ProviderToServer::Error {
@@ -217,6 +480,37 @@ impl ActionHandler<WsIncoming<Envelope<ProviderProtocol, ServerToProvider>>> for
     }
 }
 
+#[async_trait]
+impl OnTick for RillConnector {
+    async fn tick(&mut self, tick: Tick, _ctx: &mut Context<Self>) -> Result<(), Error> {
+        if let Some(declared) = self.handshake {
+            if tick.0.duration_since(declared) >= self.config.ping_deadline() {
+                self.ready_v1().await;
+            }
+        }
+        // Nodes without heartbeats never answer pings
+        if self.sender.is_connected() && self.sender.has_feature(FEATURE_HEARTBEAT) {
+            match self.heartbeat.tick(tick.0) {
+                Pulse::Ping => {
+                    self.send_global(ProviderToServer::Ping);
+                }
+                Pulse::Wait => {}
+                Pulse::Dead => {
+                    log::warn!("No pong from {}. Reconnecting...", self.url);
+                    self.disconnected().await;
+                    // The next attempt will be scheduled when the client finished
+                    self.stop_client()?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    async fn done(&mut self, _ctx: &mut Context<Self>) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
 #[async_trait]
 impl TaskEliminated<WsClient<ProviderProtocol, Self>, ()> for RillConnector {
     async fn handle(
@@ -224,10 +518,25 @@ impl TaskEliminated<WsClient<ProviderProtocol, Self>, ()> for RillConnector {
         _id: IdOf<WsClient<ProviderProtocol, Self>>,
         _tag: (),
         _result: Result<(), TaskError>,
-        _ctx: &mut Context<Self>,
+        ctx: &mut Context<Self>,
     ) -> Result<(), Error> {
         // TODO: Drop unfinished tasks
-        Ok(())
+        self.ws_client.take();
+        self.schedule_reconnect(ctx)
+    }
+}
+
+#[async_trait]
+impl TaskEliminated<TransportClient<ProviderProtocol, Self>, ()> for RillConnector {
+    async fn handle(
+        &mut self,
+        _id: IdOf<TransportClient<ProviderProtocol, Self>>,
+        _tag: (),
+        _result: Result<(), TaskError>,
+        ctx: &mut Context<Self>,
+    ) -> Result<(), Error> {
+        self.transport_client.take();
+        self.schedule_reconnect(ctx)
     }
 }
 
@@ -240,15 +549,17 @@ impl<T: core::Flow> Eliminated<Recorder<T>> for RillConnector {
     ) -> Result<(), Error> {
         let id: Id = id.into();
         if let Some(desc) = self.registered.remove(&id) {
+            parcel::DISTRIBUTOR.release_path(&desc);
             let path = &desc.path;
-            let link = self.recorders.find_mut(&path).and_then(Record::take_link);
+            let link = self.recorders.remove_link(path);
             if link.is_some() {
                 self.path_flow.del(path.to_owned());
             } else {
                 log::error!("Recorder {:?} was registered without a link (lost).", id);
             }
         } else {
-            log::error!("Recorder {:?} wasn't registered.", id);
+            // The tracer was unregistered explicitly
+            log::debug!("Recorder {:?} was unregistered.", id);
         }
         Ok(())
     }
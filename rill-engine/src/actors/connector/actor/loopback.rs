@@ -0,0 +1,82 @@
+use super::RillConnector;
+use crate::loopback::{Loopback, ToNode};
+use anyhow::Error;
+use async_trait::async_trait;
+use futures::StreamExt;
+use meio::{
+    Address, Context, IdOf, InstantAction, InstantActionHandler, LiteTask, StopReceiver,
+    TaskEliminated, TaskError,
+};
+use meio_connect::WsIncoming;
+use rill_transport::ConnectionStatus;
+
+/// Connects the connector to an in-process node.
+pub struct LoopbackClient {
+    loopback: Loopback,
+    address: Address<RillConnector>,
+}
+
+impl LoopbackClient {
+    pub fn new(loopback: Loopback, address: Address<RillConnector>) -> Self {
+        Self { loopback, address }
+    }
+}
+
+#[async_trait]
+impl LiteTask for LoopbackClient {
+    type Output = ();
+
+    async fn routine(mut self, mut stop: StopReceiver) -> Result<Self::Output, Error> {
+        let (sender, mut receiver) = self.loopback.connect()?;
+        self.address.instant(LoopbackConnected { sender })?;
+        while let Ok(Some(msg)) = stop.or(receiver.next()).await {
+            self.address.act(WsIncoming(msg)).await?;
+        }
+        Ok(())
+    }
+}
+
+struct LoopbackConnected {
+    sender: ToNode,
+}
+
+impl InstantAction for LoopbackConnected {}
+
+#[async_trait]
+impl InstantActionHandler<LoopbackConnected> for RillConnector {
+    async fn handle(
+        &mut self,
+        msg: LoopbackConnected,
+        _ctx: &mut Context<Self>,
+    ) -> Result<(), Error> {
+        self.established(msg.sender.into()).await
+    }
+}
+
+#[async_trait]
+impl TaskEliminated<LoopbackClient, ()> for RillConnector {
+    async fn handle(
+        &mut self,
+        _id: IdOf<LoopbackClient>,
+        _tag: (),
+        result: Result<(), TaskError>,
+        ctx: &mut Context<Self>,
+    ) -> Result<(), Error> {
+        self.loopback_client.take();
+        if self.sender.is_connected() {
+            self.disconnected().await;
+        }
+        match result {
+            Ok(()) => {
+                // The node closed the session
+                self.schedule_reconnect(ctx)?;
+            }
+            Err(err) => {
+                // The acceptor is gone and can't be restored.
+                log::error!("Loopback connection failed: {}", err);
+                self.set_status(ConnectionStatus::GaveUp);
+            }
+        }
+        Ok(())
+    }
+}
@@ -7,13 +7,69 @@ use async_trait::async_trait;
 use meio::{Consumer, Context, InstantAction, InstantActionHandler, Parcel};
 use once_cell::sync::Lazy;
 use rill_protocol::flow::core;
-use rill_protocol::io::provider::Description;
-use std::sync::Arc;
+use rill_protocol::io::provider::{Description, Path};
+use std::collections::hash_map::{Entry, HashMap};
+use std::ops::Deref;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
 use thiserror::Error;
 
 /// It used by tracers to register them into the state.
-pub(crate) static DISTRIBUTOR: Lazy<ParcelDistributor<RillConnector>> =
-    Lazy::new(ParcelDistributor::new);
+pub(crate) static DISTRIBUTOR: Lazy<TracerDistributor> = Lazy::new(TracerDistributor::new);
+
+/// The distributor of the connector that keeps paths claimed by tracers.
+///
+/// A path is claimed when a tracer is created to reject duplicates immediately,
+/// because tracers are registered by the connector asynchronously. Claims
+/// are dropped when the connector stops serving tracers.
+pub(crate) struct TracerDistributor {
+    parcels: ParcelDistributor<RillConnector>,
+    claimed: Mutex<HashMap<Path, Arc<Description>>>,
+}
+
+impl Deref for TracerDistributor {
+    type Target = ParcelDistributor<RillConnector>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.parcels
+    }
+}
+
+impl TracerDistributor {
+    fn new() -> Self {
+        Self {
+            parcels: ParcelDistributor::new(),
+            claimed: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn claimed(&self) -> MutexGuard<'_, HashMap<Path, Arc<Description>>> {
+        // The map is always consistent, because it's never modified partially.
+        self.claimed.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Claims the path of the `description`. Returns `false` if the path is taken.
+    pub fn claim_path(&self, description: &Arc<Description>) -> bool {
+        match self.claimed().entry(description.path.clone()) {
+            Entry::Occupied(_) => false,
+            Entry::Vacant(entry) => {
+                entry.insert(description.clone());
+                true
+            }
+        }
+    }
+
+    /// Releases the path if it's still claimed by the `description`.
+    pub fn release_path(&self, description: &Arc<Description>) -> bool {
+        let mut paths = self.claimed();
+        match paths.get(&description.path) {
+            Some(claimed) if Arc::ptr_eq(claimed, description) => {
+                paths.remove(&description.path);
+                true
+            }
+            _ => false,
+        }
+    }
+}
 
 impl RillConnector {
     pub(super) async fn attach_distributor(
@@ -27,6 +83,7 @@ impl RillConnector {
 
     pub(super) fn detach_distributor(&mut self) {
         DISTRIBUTOR.sender.close_channel();
+        DISTRIBUTOR.claimed().clear();
         // NEVER terminate the group. The channel above has to be drained!!!
         //ctx.terminate_group(Group::ParcelStream);
     }
@@ -57,15 +114,18 @@ impl<T: core::Flow> InstantActionHandler<RegisterTracer<T>> for RillConnector {
         let record = self.recorders.dig(path.clone());
         if record.get_link().is_none() {
             let packed_desc = Description::clone(&description);
+            let description_ref = description.clone();
             let sender = self.sender.clone();
+            let spool = self.config.spool.clone();
             //let link = ctx.address().link();
-            let actor = Recorder::new(description, sender, msg.mode);
+            let actor = Recorder::new(description, sender, msg.mode, spool);
             let recorder = ctx.spawn_actor(actor, Group::Recorders);
             record.set_link(recorder.link());
             // Send a description that's new tracer added
             self.registered
-                .insert(recorder.id().into(), packed_desc.clone());
-            self.path_flow.add(path, packed_desc);
+                .insert(recorder.id().into(), description_ref);
+            self.path_flow.add(path.clone(), packed_desc);
+            self.join_subtrees(&path).await?;
         } else {
             log::error!("Provider for {} already registered.", path);
         }
@@ -80,6 +140,42 @@ pub(crate) struct RegisterTracer<T: core::Flow> {
 
 impl<T: core::Flow> InstantAction for RegisterTracer<T> {}
 
+#[async_trait]
+impl InstantActionHandler<UnregisterTracer> for RillConnector {
+    async fn handle(
+        &mut self,
+        msg: UnregisterTracer,
+        _ctx: &mut Context<Self>,
+    ) -> Result<(), Error> {
+        let path = msg.description.path.clone();
+        log::info!("Remove tracer: {}", path);
+        let id = self
+            .registered
+            .iter()
+            .find(|(_, description)| Arc::ptr_eq(description, &msg.description))
+            .map(|(id, _)| id.clone());
+        if let Some(id) = id {
+            self.registered.remove(&id);
+            if let Some(mut link) = self.recorders.remove_link(&path) {
+                if let Err(err) = link.unregister().await {
+                    log::debug!("Recorder of {} is terminated already: {}", path, err);
+                }
+            }
+            self.path_flow.del(path);
+        } else {
+            // The recorder is terminated already
+            log::debug!("Tracer for {} isn't registered.", path);
+        }
+        Ok(())
+    }
+}
+
+pub(crate) struct UnregisterTracer {
+    pub description: Arc<Description>,
+}
+
+impl InstantAction for UnregisterTracer {}
+
 #[derive(Error, Debug)]
 #[error("Tracer not registered")]
 pub struct TracerNotRegistered;
@@ -100,4 +196,15 @@ impl ParcelDistributor<RillConnector> {
             .unbounded_send(parcel)
             .map_err(|_| TracerNotRegistered)
     }
+
+    pub fn unregister_tracer(
+        &self,
+        description: Arc<Description>,
+    ) -> Result<(), TracerNotRegistered> {
+        let msg = UnregisterTracer { description };
+        let parcel = Parcel::pack(msg);
+        self.sender
+            .unbounded_send(parcel)
+            .map_err(|_| TracerNotRegistered)
+    }
 }
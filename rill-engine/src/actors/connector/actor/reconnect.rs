@@ -0,0 +1,51 @@
+use super::RillConnector;
+use anyhow::Error;
+use async_trait::async_trait;
+use meio::{Context, Scheduled};
+use rill_transport::ConnectionStatus;
+use std::time::Instant;
+
+/// The moment to make the next attempt to connect.
+pub(super) struct Reconnect;
+
+impl RillConnector {
+    pub(super) fn set_status(&mut self, status: ConnectionStatus) {
+        self.status.send_replace(status);
+    }
+
+    /// Schedules the next attempt to connect or gives up if attempts are exhausted.
+    pub(super) fn schedule_reconnect(&mut self, ctx: &mut Context<Self>) -> Result<(), Error> {
+        if ctx.is_terminating() || self.status.borrow().is_final() {
+            return Ok(());
+        }
+        if let Some(delay) = self.backoff.next_delay() {
+            log::debug!("Next attempt to connect to {} in {:?}", self.url, delay);
+            let attempt = self.backoff.attempts();
+            self.set_status(ConnectionStatus::Backoff { attempt, delay });
+            ctx.address().schedule(Reconnect, Instant::now() + delay)?;
+        } else {
+            log::error!(
+                "Gave up connecting to {} after {} attempts.",
+                self.url,
+                self.backoff.attempts()
+            );
+            self.set_status(ConnectionStatus::GaveUp);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Scheduled<Reconnect> for RillConnector {
+    async fn handle(
+        &mut self,
+        _timestamp: Instant,
+        _reconnect: Reconnect,
+        ctx: &mut Context<Self>,
+    ) -> Result<(), Error> {
+        if !ctx.is_terminating() {
+            self.spawn_client(ctx)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+use super::RillConnector;
+use crate::actors::recorder::SubtreeEnd;
+use anyhow::Error;
+use rill_protocol::io::provider::{FlowControl, Path, PathPattern, ProviderReqId};
+use rill_protocol::pathfinder::Record;
+use std::sync::Arc;
+
+impl RillConnector {
+    /// Returns a pattern if the `path` addresses a subtree of flows
+    /// instead of a single recorder.
+    ///
+    /// Entries of an existing node are matched literally. Wildcards
+    /// are used only if the `path` is not a node of the tree.
+    pub(super) fn subtree_pattern(&self, path: &Path) -> Option<PathPattern> {
+        match self.recorders.find(path) {
+            Some(record) if record.has_link() => None,
+            Some(_) => Some(PathPattern::subtree(path.clone())),
+            None => Some(PathPattern::from(path.clone())).filter(|pattern| !pattern.is_exact()),
+        }
+    }
+
+    pub(super) fn is_subtree(&self, direct_id: &ProviderReqId) -> bool {
+        self.subtrees.contains_key(direct_id)
+    }
+
+    /// Subscribes to all recorders that match the `pattern`.
+    pub(super) async fn start_subtree(
+        &mut self,
+        direct_id: ProviderReqId,
+        pattern: PathPattern,
+    ) -> Result<(), Error> {
+        log::debug!("Subscribe {:?} to the subtree {}", direct_id, pattern);
+        self.control_subtree(direct_id, &pattern, FlowControl::StartStream, None)
+            .await?;
+        self.subtrees.insert(direct_id, pattern);
+        Ok(())
+    }
+
+    /// Unsubscribes from all recorders of the subtree and ends the stream
+    /// when all recorders ended their streams.
+    pub(super) async fn stop_subtree(&mut self, direct_id: ProviderReqId) -> Result<(), Error> {
+        let end = Arc::new(SubtreeEnd::new(self.sender.clone(), direct_id));
+        if let Some(pattern) = self.subtrees.remove(&direct_id) {
+            self.control_subtree(direct_id, &pattern, FlowControl::StopStream, Some(end))
+                .await?;
+        }
+        Ok(())
+    }
+
+    /// Subscribes a new recorder to all subtrees that match its `path`.
+    pub(super) async fn join_subtrees(&mut self, path: &Path) -> Result<(), Error> {
+        let ids: Vec<_> = self
+            .subtrees
+            .iter()
+            .filter(|(_, pattern)| pattern.matches(path))
+            .map(|(direct_id, _)| *direct_id)
+            .collect();
+        if let Some(link) = self.recorders.find_mut(path).and_then(Record::get_link_mut) {
+            for direct_id in ids {
+                link.do_subtree_request(direct_id, FlowControl::StartStream, None)
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+
+    async fn control_subtree(
+        &mut self,
+        direct_id: ProviderReqId,
+        pattern: &PathPattern,
+        control: FlowControl,
+        end: Option<Arc<SubtreeEnd>>,
+    ) -> Result<(), Error> {
+        let paths: Vec<_> = self
+            .recorders
+            .find_matching(pattern)
+            .filter(|(_, record)| record.has_link())
+            .map(|(path, _)| path)
+            .collect();
+        for path in paths {
+            if let Some(link) = self
+                .recorders
+                .find_mut(&path)
+                .and_then(Record::get_link_mut)
+            {
+                link.do_subtree_request(direct_id, control.clone(), end.clone())
+                    .await?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -5,13 +5,18 @@ use anyhow::Error;
 use async_trait::async_trait;
 use meio::{Actor, Context, Eliminated, IdOf, InterruptedBy, StartedBy};
 use rill_protocol::io::provider::EntryId;
+use rill_transport::ConnectionStatus;
 use strum::{EnumIter, IntoEnumIterator};
+use tokio::sync::watch;
 
 /// The supervisor that spawns a connector.
 pub struct RillEngine {
     name: EntryId,
     /// It wrapped with `Option` to take it for a `Connector` instance later.
     config: Option<EngineConfig>,
+    /// It wrapped with `Option` to take it for a `Connector` instance later.
+    status_tx: Option<watch::Sender<ConnectionStatus>>,
+    status_rx: watch::Receiver<ConnectionStatus>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, EnumIter)]
@@ -32,11 +37,21 @@ impl RillEngine {
     /// Creates a new supervisor instance.
     pub fn new(config: EngineConfig) -> Self {
         let name = config.provider_name();
+        let (status_tx, status_rx) = watch::channel(ConnectionStatus::Connecting);
         Self {
             name,
             config: Some(config),
+            status_tx: Some(status_tx),
+            status_rx,
         }
     }
+
+    /// Watches the state of the connection to the node.
+    ///
+    /// Subscribe before spawning the engine to observe all transitions.
+    pub fn status(&self) -> watch::Receiver<ConnectionStatus> {
+        self.status_rx.clone()
+    }
 }
 
 #[async_trait]
@@ -45,7 +60,8 @@ impl<T: Actor> StartedBy<T> for RillEngine {
         ctx.termination_sequence(Group::iter().collect());
 
         let config = self.config.take().unwrap();
-        let connector = RillConnector::new(config);
+        let status = self.status_tx.take().unwrap();
+        let connector = RillConnector::new(config, status)?;
         ctx.spawn_actor(connector, Group::Connector);
 
         /*
@@ -1,6 +1,8 @@
 pub mod link;
+mod spool;
 
 use crate::actors::connector::{RillConnector, RillSender};
+use crate::config::SpoolConfig;
 use crate::tracers::tracer::{EventEnvelope, TracerMode};
 use anyhow::Error;
 use async_trait::async_trait;
@@ -8,11 +10,13 @@ use futures::stream::{self, StreamExt};
 use meio::task::{HeartBeat, OnTick, Tick};
 use meio::{ActionHandler, Actor, Consumer, Context, InterruptedBy, StartedBy};
 use rill_protocol::flow::core::{self, ActionEnvelope, Activity};
+use rill_protocol::io::handshake::FEATURE_ACTIONS;
 use rill_protocol::io::provider::{
-    Description, FlowControl, PackedState, ProviderProtocol, ProviderReqId, ProviderToServer,
-    RecorderAction, RecorderRequest,
+    Description, FlowControl, PackedAction, PackedState, ProviderProtocol, ProviderReqId,
+    ProviderToServer, RecorderAction, RecorderRequest,
 };
 use rill_protocol::io::transport::Direction;
+use spool::Spool;
 use std::collections::HashSet;
 use std::sync::{Arc, Weak};
 use tokio_stream::wrappers::UnboundedReceiverStream;
@@ -22,15 +26,27 @@ pub(crate) struct Recorder<T: core::Flow> {
     sender: RillSender,
     mode: TracerMode<T>,
     subscribers: HashSet<ProviderReqId>,
+    /// Subscribers of subtrees that get messages tagged with the path.
+    tagged: HashSet<ProviderReqId>,
+    spool_config: Option<SpoolConfig>,
+    spool: Option<Spool<T>>,
 }
 
 impl<T: core::Flow> Recorder<T> {
-    pub fn new(description: Arc<Description>, sender: RillSender, mode: TracerMode<T>) -> Self {
+    pub fn new(
+        description: Arc<Description>,
+        sender: RillSender,
+        mode: TracerMode<T>,
+        spool_config: Option<SpoolConfig>,
+    ) -> Self {
         Self {
             description,
             sender,
             mode,
             subscribers: HashSet::new(),
+            tagged: HashSet::new(),
+            spool_config,
+            spool: None,
         }
     }
 
@@ -39,10 +55,32 @@ impl<T: core::Flow> Recorder<T> {
         Direction::from(&self.subscribers)
     }
 
+    /// Sends a response tagged with the path to subscribers of subtrees.
+    fn respond(&mut self, direction: Direction<ProviderProtocol>, response: ProviderToServer) {
+        if self.tagged.is_empty() || matches!(direction, Direction::Broadcast) {
+            self.sender.response(direction, response);
+            return;
+        }
+        let (tagged, plain): (HashSet<_>, HashSet<_>) = direction
+            .into_vec()
+            .into_iter()
+            .partition(|id| self.tagged.contains(id));
+        let tagged_response = (!tagged.is_empty()).then(|| ProviderToServer::Tagged {
+            path: self.description.path.clone(),
+            data: Box::new(response.clone()),
+        });
+        if !plain.is_empty() {
+            self.sender.response(Direction::from(&plain), response);
+        }
+        if let Some(response) = tagged_response {
+            self.sender.response(Direction::from(&tagged), response);
+        }
+    }
+
     fn send_flow(&mut self, direction: Direction<ProviderProtocol>) {
         let description = Description::clone(&self.description);
         let response = ProviderToServer::Flow { description };
-        self.sender.response(direction, response);
+        self.respond(direction, response);
     }
 
     async fn pack_state(&self) -> Result<PackedState, Error> {
@@ -64,13 +102,13 @@ impl<T: core::Flow> Recorder<T> {
     async fn send_state(&mut self, direction: Direction<ProviderProtocol>) -> Result<(), Error> {
         let state = self.pack_state().await?;
         let response = ProviderToServer::State { state };
-        self.sender.response(direction, response);
+        self.respond(direction, response);
         Ok(())
     }
 
     fn send_end(&mut self, direction: Direction<ProviderProtocol>) {
         let response = ProviderToServer::EndStream;
-        self.sender.response(direction, response);
+        self.respond(direction, response);
     }
 
     fn graceful_shutdown(&mut self, ctx: &mut Context<Self>) {
@@ -78,6 +116,7 @@ impl<T: core::Flow> Recorder<T> {
         // No more events will be received after this point.
         self.send_end(self.all_subscribers());
         self.subscribers.clear();
+        self.tagged.clear();
         ctx.shutdown();
     }
 }
@@ -135,7 +174,7 @@ impl<T: core::Flow> Recorder<T> {
             let response = ProviderToServer::Data {
                 delta: T::pack_event(event)?,
             };
-            self.sender.response(direction, response);
+            self.respond(direction, response);
         }
         Ok(())
     }
@@ -188,6 +227,7 @@ impl<T: core::Flow> Consumer<Vec<EventEnvelope<T>>> for Recorder<T> {
                 }
                 // Apply even if it has no subscribers
                 if apply {
+                    self.spool_event(&event);
                     match &mut self.mode {
                         TracerMode::Push { state, .. } => {
                             T::apply(state, event);
@@ -266,39 +306,58 @@ impl<T: core::Flow> Recorder<T> {
 }
 
 impl<T: core::Flow> Recorder<T> {
-    fn send_activity(&mut self, origin: ProviderReqId, activity: Activity<T>) {
+    fn send_activity(&mut self, origin: ProviderReqId, activity: Activity<T>) -> Result<(), Error> {
         match &mut self.mode {
             TracerMode::Push {
                 control_sender: Some(sender),
                 ..
             } => {
                 let envelope = ActionEnvelope { origin, activity };
-                // TODO: Track errors and send them back to the client?
-                if let Err(err) = sender.send(envelope) {
-                    log::error!(
+                sender.send(envelope).map_err(|err| {
+                    Error::msg(format!(
                         "No activity listeners in {} watcher: {}",
-                        self.description.path,
-                        err,
-                    );
-                }
+                        self.description.path, err
+                    ))
+                })
             }
             TracerMode::Push {
                 control_sender: None,
                 ..
-            } => {
-                log::error!(
-                    "Push sender doesn't support control actions for {}",
-                    self.description.path
-                );
-            }
-            TracerMode::Pull { .. } => {
-                log::error!(
-                    "Do activity request in the pull mode of {}",
-                    self.description.path
-                );
-            }
+            } => Err(Error::msg(format!(
+                "Push sender doesn't support control actions for {}",
+                self.description.path
+            ))),
+            TracerMode::Pull { .. } => Err(Error::msg(format!(
+                "Do activity request in the pull mode of {}",
+                self.description.path
+            ))),
         }
     }
+
+    /// Notifies a watcher about changes of subscribers.
+    fn notify_activity(&mut self, origin: ProviderReqId, activity: Activity<T>) {
+        if let Err(err) = self.send_activity(origin, activity) {
+            log::error!("{}", err);
+        }
+    }
+
+    /// Delivers an action to a watcher and reports the result to the requester.
+    fn deliver_action(&mut self, origin: ProviderReqId, data: &PackedAction) {
+        let result = T::unpack_action(data)
+            .and_then(|action| self.send_activity(origin, Activity::Action(action)));
+        let response = match result {
+            Ok(()) if self.sender.has_feature(FEATURE_ACTIONS) => ProviderToServer::ActionDelivered,
+            // Nodes of the first version don't expect confirmations
+            Ok(()) => return,
+            Err(err) => {
+                log::error!("Action to {} failed: {}", self.description.path, err);
+                ProviderToServer::Error {
+                    reason: err.to_string(),
+                }
+            }
+        };
+        self.respond(origin.into(), response);
+    }
 }
 
 #[async_trait]
@@ -308,8 +367,13 @@ impl<T: core::Flow> ActionHandler<link::DoRecorderRequest> for Recorder<T> {
         msg: link::DoRecorderRequest,
         ctx: &mut Context<Self>,
     ) -> Result<(), Error> {
+        // The stream of a subtree ends after responses of the request
+        let _end = msg.end;
         if !ctx.is_terminating() {
             let id = msg.direct_id;
+            if msg.tagged {
+                self.tagged.insert(id);
+            }
             match msg.request {
                 RecorderRequest::ControlStream(control) => {
                     log::info!(
@@ -321,8 +385,8 @@ impl<T: core::Flow> ActionHandler<link::DoRecorderRequest> for Recorder<T> {
                     match control {
                         FlowControl::StartStream => {
                             if self.subscribers.insert(id) {
-                                self.send_state(id.into()).await?;
-                                self.send_activity(id, Activity::Connected);
+                                self.send_initial(id.into()).await?;
+                                self.notify_activity(id, Activity::Connected);
                             } else {
                                 log::warn!(
                                     "Attempt to subscribe twice for <path> with id: {:?}",
@@ -332,8 +396,9 @@ impl<T: core::Flow> ActionHandler<link::DoRecorderRequest> for Recorder<T> {
                         }
                         FlowControl::StopStream => {
                             if self.subscribers.remove(&id) {
-                                self.send_activity(id, Activity::Disconnected);
+                                self.notify_activity(id, Activity::Disconnected);
                                 self.send_end(id.into());
+                                self.tagged.remove(&id);
                             } else {
                                 log::warn!("Can't remove subscriber of <path> by id: {:?}", id);
                             }
@@ -355,9 +420,7 @@ impl<T: core::Flow> ActionHandler<link::DoRecorderRequest> for Recorder<T> {
                         self.send_flow(id.into());
                     }
                     RecorderAction::DoAction(data) => {
-                        let action = T::unpack_action(&data)?;
-                        let activity = Activity::Action(action);
-                        self.send_activity(id, activity);
+                        self.deliver_action(id, &data);
                     }
                 },
             }
@@ -379,12 +442,23 @@ impl<T: core::Flow> ActionHandler<link::ConnectionChanged> for Recorder<T> {
         match msg {
             Connected { sender } => {
                 self.sender = sender;
+                self.resume_spool();
             }
             Disconnected => {
                 self.sender.reset();
                 self.subscribers.clear();
+                self.tagged.clear();
+                self.start_spool();
             }
         }
         Ok(())
     }
 }
+
+#[async_trait]
+impl<T: core::Flow> ActionHandler<link::Unregister> for Recorder<T> {
+    async fn handle(&mut self, _: link::Unregister, ctx: &mut Context<Self>) -> Result<(), Error> {
+        self.graceful_shutdown(ctx);
+        Ok(())
+    }
+}
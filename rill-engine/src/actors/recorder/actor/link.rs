@@ -3,13 +3,15 @@ use crate::actors::connector::RillSender;
 use anyhow::Error;
 use meio::{Action, ActionRecipient, Address};
 use rill_protocol::flow::core;
-use rill_protocol::io::provider::{ProviderReqId, RecorderRequest};
+use rill_protocol::io::provider::{FlowControl, ProviderReqId, ProviderToServer, RecorderRequest};
+use std::sync::Arc;
 
 /// COOL SOLUTION!
 trait Recipient
 where
     Self: ActionRecipient<DoRecorderRequest>,
     Self: ActionRecipient<ConnectionChanged>,
+    Self: ActionRecipient<Unregister>,
 {
 }
 
@@ -17,6 +19,7 @@ impl<T> Recipient for T
 where
     T: ActionRecipient<DoRecorderRequest>,
     T: ActionRecipient<ConnectionChanged>,
+    T: ActionRecipient<Unregister>,
 {
 }
 
@@ -61,6 +64,32 @@ impl RecorderLink {
 pub(super) struct DoRecorderRequest {
     pub direct_id: ProviderReqId,
     pub request: RecorderRequest,
+    /// Responses are tagged with the path of the recorder.
+    pub tagged: bool,
+    /// The end of the stream of a subtree held till the request is handled.
+    pub end: Option<Arc<SubtreeEnd>>,
+}
+
+/// Ends the stream of a subscriber of a subtree.
+///
+/// Recorders that stop streams of the subtree get clones of it.
+/// The last dropped clone ends the stream after all responses of recorders.
+pub(crate) struct SubtreeEnd {
+    sender: RillSender,
+    direct_id: ProviderReqId,
+}
+
+impl SubtreeEnd {
+    pub fn new(sender: RillSender, direct_id: ProviderReqId) -> Self {
+        Self { sender, direct_id }
+    }
+}
+
+impl Drop for SubtreeEnd {
+    fn drop(&mut self) {
+        self.sender
+            .response(self.direct_id.into(), ProviderToServer::EndStream);
+    }
 }
 
 impl Action for DoRecorderRequest {}
@@ -71,7 +100,39 @@ impl RecorderLink {
         direct_id: ProviderReqId,
         request: RecorderRequest,
     ) -> Result<(), Error> {
-        let msg = DoRecorderRequest { direct_id, request };
+        let msg = DoRecorderRequest {
+            direct_id,
+            request,
+            tagged: false,
+            end: None,
+        };
+        self.recipient.act(msg).await
+    }
+
+    /// Controls a stream of a subscriber of a subtree.
+    pub async fn do_subtree_request(
+        &mut self,
+        direct_id: ProviderReqId,
+        control: FlowControl,
+        end: Option<Arc<SubtreeEnd>>,
+    ) -> Result<(), Error> {
+        let msg = DoRecorderRequest {
+            direct_id,
+            request: RecorderRequest::ControlStream(control),
+            tagged: true,
+            end,
+        };
         self.recipient.act(msg).await
     }
 }
+
+/// Stops the `Recorder` of an unregistered `Tracer`.
+pub(super) struct Unregister;
+
+impl Action for Unregister {}
+
+impl RecorderLink {
+    pub async fn unregister(&mut self) -> Result<(), Error> {
+        self.recipient.act(Unregister).await
+    }
+}
@@ -0,0 +1,424 @@
+use super::Recorder;
+use crate::config::SpoolConfig;
+use crate::tracers::tracer::{timed, TracerMode};
+use anyhow::Error;
+use rill_protocol::encoding;
+use rill_protocol::flow::core::{self, TimedEvent};
+use rill_protocol::io::provider::{
+    PackedEvent, PackedState, Path, ProviderProtocol, ProviderToServer,
+};
+use rill_protocol::io::transport::Direction;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::VecDeque;
+use std::convert::TryFrom;
+use std::fs::{self, File, OpenOptions};
+use std::hash::{Hash, Hasher};
+use std::io::{BufReader, ErrorKind, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::time::Instant;
+
+/// Events of a flow kept while the provider is disconnected.
+///
+/// The spool starts from a copy of the state and keeps all deltas
+/// applied after it. Listeners subscribed again get the copy of the state
+/// and all kept deltas instead of the current state.
+pub(super) struct Spool<T: core::Flow> {
+    config: SpoolConfig,
+    base: T,
+    storage: Storage,
+    reconnected: Option<Instant>,
+}
+
+impl<T: core::Flow> Spool<T> {
+    pub fn new(config: SpoolConfig, path: &Path, state: &T) -> Result<Self, Error> {
+        let storage = match &config.dir {
+            Some(dir) => Storage::disk(dir, path)?,
+            None => Storage::Memory(VecDeque::new()),
+        };
+        Ok(Self {
+            config,
+            base: state.clone(),
+            storage,
+            reconnected: None,
+        })
+    }
+
+    pub fn disconnected(&mut self) {
+        self.reconnected.take();
+    }
+
+    pub fn connected(&mut self) {
+        self.reconnected = Some(Instant::now());
+    }
+
+    /// Returns `true` if the replay window after the reconnection elapsed.
+    pub fn is_expired(&self) -> bool {
+        self.reconnected
+            .map(|instant| instant.elapsed() >= self.config.replay_window())
+            .unwrap_or(false)
+    }
+
+    /// Keeps a delta. The oldest deltas are applied to the base state if the spool is full.
+    pub fn push(&mut self, delta: PackedEvent) -> Result<(), Error> {
+        let event = timed(delta).ok_or_else(|| Error::msg("Can't get a timestamp."))?;
+        self.storage.push(event)?;
+        let capacity = self.config.capacity().max(1);
+        if self.storage.len() > capacity {
+            // Frees a half of the spool at once to make overflows rare
+            let outdated = self.storage.len() - capacity / 2;
+            for event in self.storage.drain_front(outdated)? {
+                T::apply(&mut self.base, T::unpack_event(&event.event)?);
+            }
+        }
+        Ok(())
+    }
+
+    /// The state and deltas to replay to a listener.
+    pub fn replay(&self) -> Result<(PackedState, Vec<PackedEvent>), Error> {
+        let state = T::pack_state(&self.base)?;
+        let deltas = self
+            .storage
+            .events()?
+            .into_iter()
+            .map(TimedEvent::into_inner)
+            .collect();
+        Ok((state, deltas))
+    }
+}
+
+impl<T: core::Flow> Recorder<T> {
+    /// Starts spooling of deltas if it's configured for the push mode.
+    pub(super) fn start_spool(&mut self) {
+        if let Some(spool) = self.spool.as_mut() {
+            spool.disconnected();
+        } else if let (Some(config), TracerMode::Push { state, .. }) =
+            (self.spool_config.as_ref(), &self.mode)
+        {
+            match Spool::new(config.clone(), &self.description.path, state) {
+                Ok(spool) => {
+                    self.spool = Some(spool);
+                }
+                Err(err) => {
+                    log::error!("Can't spool deltas of {}: {}", self.description.path, err);
+                }
+            }
+        }
+    }
+
+    pub(super) fn resume_spool(&mut self) {
+        if let Some(spool) = self.spool.as_mut() {
+            spool.connected();
+        }
+    }
+
+    /// Drops the spool if the replay window elapsed.
+    fn expire_spool(&mut self) {
+        if self.spool.as_ref().map(Spool::is_expired).unwrap_or(false) {
+            log::debug!("Spool of {} expired.", self.description.path);
+            self.spool.take();
+        }
+    }
+
+    /// Keeps a delta applied to the state while the spool is active.
+    pub(super) fn spool_event(&mut self, event: &T::Event) {
+        self.expire_spool();
+        if let Some(spool) = self.spool.as_mut() {
+            if let Err(err) = T::pack_event(event).and_then(|delta| spool.push(delta)) {
+                log::error!("Spool of {} dropped: {}", self.description.path, err);
+                self.spool.take();
+            }
+        }
+    }
+
+    /// Sends the spooled deltas to a new listener or the current state if nothing spooled.
+    pub(super) async fn send_initial(
+        &mut self,
+        direction: Direction<ProviderProtocol>,
+    ) -> Result<(), Error> {
+        self.expire_spool();
+        if let Some(spool) = self.spool.as_ref() {
+            match spool.replay() {
+                Ok((state, deltas)) => {
+                    let response = ProviderToServer::State { state };
+                    self.respond(direction.clone(), response);
+                    for delta in deltas {
+                        let response = ProviderToServer::Data { delta };
+                        self.respond(direction.clone(), response);
+                    }
+                    return Ok(());
+                }
+                Err(err) => {
+                    log::error!("Spool of {} dropped: {}", self.description.path, err);
+                    self.spool.take();
+                }
+            }
+        }
+        self.send_state(direction).await
+    }
+}
+
+enum Storage {
+    Memory(VecDeque<TimedEvent<PackedEvent>>),
+    Disk {
+        name: PathBuf,
+        file: File,
+        /// The position of the first kept event in the file.
+        offset: u64,
+        len: usize,
+    },
+}
+
+impl Storage {
+    fn disk(dir: &std::path::Path, path: &Path) -> Result<Self, Error> {
+        fs::create_dir_all(dir)?;
+        let name = dir.join(file_name(path));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(&name)?;
+        Ok(Self::Disk {
+            name,
+            file,
+            offset: 0,
+            len: 0,
+        })
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            Self::Memory(events) => events.len(),
+            Self::Disk { len, .. } => *len,
+        }
+    }
+
+    fn push(&mut self, event: TimedEvent<PackedEvent>) -> Result<(), Error> {
+        match self {
+            Self::Memory(events) => {
+                events.push_back(event);
+            }
+            Self::Disk { file, len, .. } => {
+                file.seek(SeekFrom::End(0))?;
+                write_event(file, &event)?;
+                *len += 1;
+            }
+        }
+        Ok(())
+    }
+
+    fn events(&self) -> Result<Vec<TimedEvent<PackedEvent>>, Error> {
+        match self {
+            Self::Memory(events) => Ok(events.iter().cloned().collect()),
+            Self::Disk { file, offset, .. } => {
+                let mut file: &File = file;
+                file.seek(SeekFrom::Start(*offset))?;
+                let (events, _) = read_events(file, usize::MAX)?;
+                Ok(events)
+            }
+        }
+    }
+
+    fn drain_front(&mut self, count: usize) -> Result<Vec<TimedEvent<PackedEvent>>, Error> {
+        match self {
+            Self::Memory(events) => Ok(events.drain(..count).collect()),
+            Self::Disk {
+                file, offset, len, ..
+            } => {
+                file.seek(SeekFrom::Start(*offset))?;
+                let (events, consumed) = read_events(&*file, count)?;
+                *offset += consumed;
+                *len -= events.len();
+                let size = file.metadata()?.len();
+                // Compacts the file when the drained part exceeds the kept one
+                if *offset > size - *offset {
+                    let mut rest = Vec::new();
+                    file.seek(SeekFrom::Start(*offset))?;
+                    file.read_to_end(&mut rest)?;
+                    file.set_len(0)?;
+                    file.seek(SeekFrom::Start(0))?;
+                    file.write_all(&rest)?;
+                    *offset = 0;
+                }
+                Ok(events)
+            }
+        }
+    }
+}
+
+impl Drop for Storage {
+    fn drop(&mut self) {
+        if let Self::Disk { name, .. } = self {
+            if let Err(err) = fs::remove_file(&name) {
+                log::error!("Can't remove the spool {}: {}", name.display(), err);
+            }
+        }
+    }
+}
+
+/// The name of the spool file of the `path`.
+///
+/// Entries can contain any characters. The name keeps a safe
+/// prefix of the path for readability and a hash of the path.
+fn file_name(path: &Path) -> String {
+    let path = path.to_string();
+    let prefix: String = path
+        .chars()
+        .take(64)
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let mut hasher = DefaultHasher::new();
+    path.hash(&mut hasher);
+    format!("{}-{:016x}.spool", prefix, hasher.finish())
+}
+
+fn write_event(out: &mut File, event: &TimedEvent<PackedEvent>) -> Result<(), Error> {
+    let data = encoding::to_vec(event)?;
+    let len = u32::try_from(data.len())?;
+    let mut frame = Vec::with_capacity(data.len() + 4);
+    frame.extend_from_slice(&len.to_be_bytes());
+    frame.extend_from_slice(&data);
+    out.write_all(&frame)?;
+    Ok(())
+}
+
+/// Reads up to `count` events and returns them with the number of bytes read.
+fn read_events(file: &File, count: usize) -> Result<(Vec<TimedEvent<PackedEvent>>, u64), Error> {
+    let mut reader = BufReader::new(file);
+    let mut events = Vec::new();
+    let mut consumed = 0;
+    while events.len() < count {
+        let mut len = [0; 4];
+        match reader.read_exact(&mut len) {
+            Ok(()) => {}
+            Err(err) if err.kind() == ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(err.into()),
+        }
+        let mut data = vec![0; u32::from_be_bytes(len) as usize];
+        reader.read_exact(&mut data)?;
+        events.push(encoding::from_slice(&data)?);
+        consumed += 4 + data.len() as u64;
+    }
+    Ok((events, consumed))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rill_protocol::flow::core::Flow;
+    use rill_protocol::io::provider::StreamType;
+    use serde::{Deserialize, Serialize};
+    use std::time::Duration;
+
+    #[derive(Debug, Clone, Default, Serialize, Deserialize)]
+    struct Sum(u64);
+
+    impl Flow for Sum {
+        type Action = ();
+        type Event = u64;
+
+        fn stream_type() -> StreamType {
+            StreamType::from("rill::test::sum")
+        }
+
+        fn apply(&mut self, event: Self::Event) {
+            self.0 += event;
+        }
+    }
+
+    fn spool(capacity: usize, dir: Option<PathBuf>) -> Result<Spool<Sum>, Error> {
+        let config = SpoolConfig {
+            capacity: Some(capacity),
+            replay_window: None,
+            dir,
+        };
+        Spool::new(config, &"test.sum".parse()?, &Sum(0))
+    }
+
+    fn push(spool: &mut Spool<Sum>, events: impl IntoIterator<Item = u64>) -> Result<(), Error> {
+        for event in events {
+            spool.push(Sum::pack_event(&event)?)?;
+        }
+        Ok(())
+    }
+
+    fn replay(spool: &Spool<Sum>) -> Result<(u64, Vec<u64>), Error> {
+        let (state, deltas) = spool.replay()?;
+        let deltas = deltas
+            .iter()
+            .map(Sum::unpack_event)
+            .collect::<Result<_, _>>()?;
+        Ok((Sum::unpack_state(&state)?.0, deltas))
+    }
+
+    #[test]
+    fn memory_spool_keeps_deltas() -> Result<(), Error> {
+        let mut spool = spool(8, None)?;
+        push(&mut spool, 1..=3)?;
+        assert_eq!(replay(&spool)?, (0, vec![1, 2, 3]));
+        Ok(())
+    }
+
+    #[test]
+    fn overflow_applies_the_oldest_deltas() -> Result<(), Error> {
+        let mut spool = spool(4, None)?;
+        push(&mut spool, 1..=4)?;
+        assert_eq!(replay(&spool)?, (0, vec![1, 2, 3, 4]));
+        // A half of the spool is freed
+        push(&mut spool, Some(5))?;
+        assert_eq!(replay(&spool)?, (6, vec![4, 5]));
+        Ok(())
+    }
+
+    #[test]
+    fn disk_spool_keeps_deltas_in_a_file() -> Result<(), Error> {
+        let dir = std::env::temp_dir().join(format!("rill-spool-{}", std::process::id()));
+        let mut spool = spool(4, Some(dir.clone()))?;
+        let name = dir.join(file_name(&"test.sum".parse()?));
+        assert!(name.exists());
+
+        push(&mut spool, 1..=5)?;
+        assert_eq!(replay(&spool)?, (6, vec![4, 5]));
+        // The drained part of the file is compacted
+        assert!(matches!(
+            spool.storage,
+            Storage::Disk {
+                offset: 0,
+                len: 2,
+                ..
+            }
+        ));
+        push(&mut spool, 6..=7)?;
+        assert_eq!(replay(&spool)?, (6, vec![4, 5, 6, 7]));
+        push(&mut spool, Some(8))?;
+        assert_eq!(replay(&spool)?, (21, vec![7, 8]));
+
+        drop(spool);
+        assert!(!name.exists());
+        fs::remove_dir(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn replay_window_starts_after_the_reconnection() -> Result<(), Error> {
+        let mut spool = spool(4, None)?;
+        spool.config.replay_window = Some(0);
+        assert!(!spool.is_expired());
+        spool.connected();
+        assert!(spool.is_expired());
+        spool.disconnected();
+        assert!(!spool.is_expired());
+
+        spool.config.replay_window = Some(60);
+        spool.connected();
+        assert!(!spool.is_expired());
+        assert_eq!(spool.config.replay_window(), Duration::from_secs(60));
+        Ok(())
+    }
+}
@@ -1,3 +1,3 @@
 mod actor;
-pub(crate) use actor::link::RecorderLink;
+pub(crate) use actor::link::{RecorderLink, SubtreeEnd};
 pub(crate) use actor::Recorder;
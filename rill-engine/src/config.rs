@@ -1,8 +1,17 @@
 //! Configuration structs for the provider and tracers
 
+use crate::loopback::Loopback;
+use rill_protocol::compression::{self, Compression};
 use rill_protocol::config::ConfigPatch;
+use rill_protocol::encoding::CodecKind;
+use rill_protocol::io::auth::Credentials;
+use rill_protocol::io::codec::Settings;
+use rill_protocol::io::heartbeat;
 use rill_protocol::io::provider::{EntryId, StreamType};
+use rill_transport::{ReconnectPolicy, TlsConfig};
 use serde::Deserialize;
+use std::path::PathBuf;
+use std::time::Duration;
 
 /// The external user app can set this value to override default server.
 /// If embedded server started it can put its socket address here.
@@ -11,6 +20,59 @@ pub static NODE: ConfigPatch<String> = ConfigPatch::new("RILLRATE_NODE");
 /// The external user app can set this value to override the default name.
 pub static NAME: ConfigPatch<EntryId> = ConfigPatch::new("RILLRATE_NAME");
 
+/// The token to authorize the provider on a node.
+pub static TOKEN: ConfigPatch<String> = ConfigPatch::new("RILLRATE_TOKEN");
+
+/// The path to a PEM bundle of CAs to verify a `wss://` node.
+pub static TLS_CA: ConfigPatch<PathBuf> = ConfigPatch::new("RILLRATE_TLS_CA");
+
+/// The path to a PEM certificate chain of the provider for mutual TLS.
+pub static TLS_CERT: ConfigPatch<PathBuf> = ConfigPatch::new("RILLRATE_TLS_CERT");
+
+/// The path to a PEM private key of the provider for mutual TLS.
+pub static TLS_KEY: ConfigPatch<PathBuf> = ConfigPatch::new("RILLRATE_TLS_KEY");
+
+/// The name to verify the certificate of a node instead of its host.
+pub static TLS_SERVER_NAME: ConfigPatch<String> = ConfigPatch::new("RILLRATE_TLS_SERVER_NAME");
+
+/// The codec of frames sent by the provider. Payloads are always flexbuffers.
+pub static CODEC: ConfigPatch<CodecKind> = ConfigPatch::new("RILLRATE_CODEC");
+
+/// The compression of large frames sent by the provider.
+pub static COMPRESSION: ConfigPatch<Compression> = ConfigPatch::new("RILLRATE_COMPRESSION");
+
+/// Spooling of events while the provider is disconnected
+///
+/// Every recorder of the provider keeps its own spool.
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct SpoolConfig {
+    /// Deltas kept by a recorder
+    #[serde(default)]
+    pub capacity: Option<usize>,
+    /// Seconds to replay deltas to listeners subscribed after the reconnection
+    #[serde(default)]
+    pub replay_window: Option<u64>,
+    /// Keeps deltas in files of the directory instead of the memory
+    ///
+    /// Use a separate directory for every provider.
+    #[serde(default)]
+    pub dir: Option<PathBuf>,
+}
+
+impl SpoolConfig {
+    /// Deltas kept by a recorder
+    pub fn capacity(&self) -> usize {
+        self.capacity.unwrap_or(1_024)
+    }
+
+    /// The time to replay deltas after the reconnection
+    pub fn replay_window(&self) -> Duration {
+        self.replay_window
+            .map(Duration::from_secs)
+            .unwrap_or_else(|| Duration::from_secs(30))
+    }
+}
+
 /// Provider configuration
 #[derive(Deserialize, Debug, Clone)]
 pub struct EngineConfig {
@@ -22,6 +84,45 @@ pub struct EngineConfig {
     pub name: Option<EntryId>,
     /// The type of the provider
     pub provider_type: StreamType,
+    /// The token to authorize the provider
+    #[serde(default)]
+    pub token: Option<String>,
+    /// Interval between pings in seconds (if the node supports heartbeats)
+    #[serde(default)]
+    pub ping_interval: Option<u64>,
+    /// Seconds to wait for a pong before reconnecting
+    #[serde(default)]
+    pub ping_deadline: Option<u64>,
+    /// CA bundle of `wss://` nodes
+    #[serde(default)]
+    pub tls_ca: Option<PathBuf>,
+    /// Certificate of the provider for mutual TLS
+    #[serde(default)]
+    pub tls_cert: Option<PathBuf>,
+    /// Private key of the provider for mutual TLS
+    #[serde(default)]
+    pub tls_key: Option<PathBuf>,
+    /// Overrides the server name of TLS sessions
+    #[serde(default)]
+    pub tls_server_name: Option<String>,
+    /// Delays between attempts to connect to the node
+    #[serde(default)]
+    pub reconnect: ReconnectPolicy,
+    /// The codec used if the node supports it (`flexbuffers` by default)
+    #[serde(default)]
+    pub codec: Option<CodecKind>,
+    /// Compresses large frames if the node supports the algorithm
+    #[serde(default)]
+    pub compression: Option<Compression>,
+    /// The minimal size of a frame to compress in bytes
+    #[serde(default)]
+    pub compression_threshold: Option<usize>,
+    /// Keeps events of flows while the provider is disconnected
+    #[serde(default)]
+    pub spool: Option<SpoolConfig>,
+    /// Connects the provider to an in-process node instead of the `node`
+    #[serde(skip)]
+    pub loopback: Option<Loopback>,
 }
 
 impl EngineConfig {
@@ -31,6 +132,19 @@ impl EngineConfig {
             node: None,
             name: None,
             provider_type,
+            token: None,
+            ping_interval: None,
+            ping_deadline: None,
+            tls_ca: None,
+            tls_cert: None,
+            tls_key: None,
+            tls_server_name: None,
+            reconnect: ReconnectPolicy::default(),
+            codec: None,
+            compression: None,
+            compression_threshold: None,
+            spool: None,
+            loopback: None,
         }
     }
 }
@@ -38,13 +152,20 @@ impl EngineConfig {
 impl EngineConfig {
     /// Returns `true` if node explicitly specified.
     pub fn is_node_specified(&self) -> bool {
-        NODE.env_var().transpose().is_some() || self.node.is_some()
+        NODE.env_var().transpose().is_some() || self.node.is_some() || self.loopback.is_some()
     }
 
     /// Full url of the node
+    ///
+    /// The node can be set as a url with `ws://`, `wss://`, `tcp://` or `unix://` scheme.
+    /// A node without a scheme is a host of a WebSocket server.
     pub fn node_url(&self) -> String {
-        let host = NODE.get(|| self.node.clone(), || "localhost:1636".into());
-        format!("ws://{}/live/provider", host)
+        let node = NODE.get(|| self.node.clone(), || "localhost:1636".into());
+        if node.contains("://") {
+            node
+        } else {
+            format!("ws://{}/live/provider", node)
+        }
     }
 
     /// Name of the provider
@@ -56,8 +177,9 @@ impl EngineConfig {
                     .ok()
                     .as_ref()
                     .and_then(|path| path.as_path().file_name())
-                    .and_then(|path| path.to_str().map(EntryId::from))
-                    .unwrap_or_else(|| "rillrate".into())
+                    .and_then(|path| path.to_str())
+                    .and_then(|name| EntryId::new(name).ok())
+                    .unwrap_or_else(|| EntryId::from("rillrate"))
             },
         )
     }
@@ -66,4 +188,61 @@ impl EngineConfig {
     pub fn provider_type(&self) -> StreamType {
         self.provider_type.clone()
     }
+
+    /// Interval between pings
+    pub fn ping_interval(&self) -> Duration {
+        self.ping_interval
+            .map(Duration::from_secs)
+            .unwrap_or(heartbeat::PING_INTERVAL)
+    }
+
+    /// The deadline of a pong
+    pub fn ping_deadline(&self) -> Duration {
+        self.ping_deadline
+            .map(Duration::from_secs)
+            .unwrap_or(heartbeat::PING_DEADLINE)
+    }
+
+    /// TLS settings of `wss://` connections
+    pub fn tls(&self) -> TlsConfig {
+        TlsConfig {
+            ca: TLS_CA.get_optional(|| self.tls_ca.clone()),
+            cert: TLS_CERT.get_optional(|| self.tls_cert.clone()),
+            key: TLS_KEY.get_optional(|| self.tls_key.clone()),
+            server_name: TLS_SERVER_NAME.get_optional(|| self.tls_server_name.clone()),
+        }
+    }
+
+    /// The preferred codec of the provider
+    pub fn codec(&self) -> CodecKind {
+        CODEC.get(|| self.codec, || CodecKind::Flexbuffers)
+    }
+
+    /// The preferred settings of frames sent by the provider
+    pub fn settings(&self) -> Settings {
+        Settings {
+            codec: self.codec(),
+            compression: self.compression(),
+            threshold: self.compression_threshold(),
+            blobs: true,
+        }
+    }
+
+    /// The preferred compression of large frames
+    pub fn compression(&self) -> Option<Compression> {
+        COMPRESSION.get_optional(|| self.compression)
+    }
+
+    /// The minimal size of a frame to compress
+    pub fn compression_threshold(&self) -> usize {
+        self.compression_threshold
+            .unwrap_or(compression::DEFAULT_THRESHOLD)
+    }
+
+    /// Credentials of the provider
+    pub fn credentials(&self) -> Option<Credentials> {
+        TOKEN
+            .get_optional(|| self.token.clone())
+            .map(Credentials::token)
+    }
 }
@@ -5,6 +5,7 @@
 mod actors;
 pub mod config;
 mod distributor;
+pub mod loopback;
 pub mod tracers;
 
 metacrate::meta!();
@@ -0,0 +1,39 @@
+//! In-process transport that connects a provider to a node without sockets.
+//!
+//! Put the `Loopback` to `EngineConfig::loopback` and pass the acceptor
+//! to the node, e.g. `RillHub::with_loopback`.
+
+use rill_protocol::io::provider::{
+    ProviderProtocol, ProviderServiceRequest, ProviderToServer, ServerToProvider,
+};
+use rill_protocol::io::transport::{ServiceEnvelope, WideEnvelope};
+use rill_transport::loopback as transport;
+use rill_transport::server::Connection;
+
+pub use transport::AcceptorDropped;
+
+/// A message sent by a provider to a node.
+pub type ProviderMessage = WideEnvelope<ProviderProtocol, ProviderToServer>;
+
+/// A message sent by a node to a provider.
+pub type NodeMessage = ServiceEnvelope<ProviderProtocol, ServerToProvider, ProviderServiceRequest>;
+
+/// Frames sent by a provider to a node.
+pub(crate) type ToNode = transport::ToNode<ProviderProtocol>;
+
+/// The provider side of the rendezvous.
+pub type Loopback = transport::Loopback<ProviderProtocol>;
+
+/// The node side of the rendezvous.
+pub type LoopbackAcceptor = transport::LoopbackAcceptor<ProviderProtocol>;
+
+/// The node side of a single connection.
+///
+/// Dropping of the session disconnects the provider
+/// and the provider connects again with a new session.
+pub type LoopbackSession = Connection<ProviderProtocol>;
+
+/// Creates a connected pair of a `Loopback` and its `LoopbackAcceptor`.
+pub fn loopback() -> (Loopback, LoopbackAcceptor) {
+    transport::loopback()
+}
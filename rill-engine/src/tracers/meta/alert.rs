@@ -1,4 +1,4 @@
-use crate::tracers::tracer::Tracer;
+use crate::tracers::tracer::{Tracer, TracerError};
 use derive_more::{Deref, DerefMut};
 use rill_protocol::flow::meta::alert::{AlertEvent, AlertState};
 use rill_protocol::io::provider::Path;
@@ -11,11 +11,11 @@ pub struct AlertTracer {
 
 impl AlertTracer {
     /// Create a new instance of the `Tracer`.
-    pub fn new(path: Path) -> Self {
+    pub fn new(path: Path) -> Result<Self, TracerError> {
         let state = AlertState::new();
         // TODO: Use the `Receiver`
-        let tracer = Tracer::new_push(state, path).0;
-        Self { tracer }
+        let tracer = Tracer::new_push(state, path)?.0;
+        Ok(Self { tracer })
     }
 
     /// Writes a message.
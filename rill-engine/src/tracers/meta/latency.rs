@@ -0,0 +1,33 @@
+use crate::tracers::tracer::{Tracer, TracerError};
+use derive_more::{Deref, DerefMut};
+use rill_protocol::flow::meta::latency::{LatencyEvent, LatencyState};
+use rill_protocol::io::provider::Path;
+use std::time::Duration;
+
+/// This tracer reports the latency of the connection to a node.
+#[derive(Debug, Deref, DerefMut, Clone)]
+pub struct LatencyTracer {
+    tracer: Tracer<LatencyState>,
+}
+
+impl LatencyTracer {
+    /// Create a new instance of the `Tracer`.
+    pub fn new(path: Path) -> Result<Self, TracerError> {
+        let state = LatencyState::new();
+        // TODO: Use the `Receiver`
+        let tracer = Tracer::new_meta(state, path)?;
+        Ok(Self { tracer })
+    }
+
+    /// Sets the measured latency
+    pub fn measured(&self, latency: Duration) {
+        let data = LatencyEvent::Measured { latency };
+        self.tracer.send(data, None);
+    }
+
+    /// Resets the latency when the connection lost
+    pub fn lost(&self) {
+        let data = LatencyEvent::Lost;
+        self.tracer.send(data, None);
+    }
+}
@@ -3,6 +3,9 @@
 pub(crate) mod alert;
 pub use alert::AlertTracer;
 
+pub(crate) mod latency;
+pub use latency::LatencyTracer;
+
 pub(crate) mod path;
 pub use path::PathTracer;
 
@@ -1,4 +1,4 @@
-use crate::tracers::tracer::Tracer;
+use crate::tracers::tracer::{Tracer, TracerError};
 use derive_more::{Deref, DerefMut};
 use rill_protocol::flow::meta::path::{PathEvent, PathState};
 use rill_protocol::io::provider::{Description, Path};
@@ -11,11 +11,11 @@ pub struct PathTracer {
 
 impl PathTracer {
     /// Create a new instance of the `Tracer`.
-    pub fn new(path: Path, description: Description) -> Self {
+    pub fn new(path: Path, description: Description) -> Result<Self, TracerError> {
         let state = PathState::new(description);
         // TODO: Use the receiver
-        let tracer = Tracer::new_push(state, path).0;
-        Self { tracer }
+        let tracer = Tracer::new_meta(state, path)?;
+        Ok(Self { tracer })
     }
 
     /// Add an path
@@ -1,4 +1,4 @@
-use crate::tracers::tracer::Tracer;
+use crate::tracers::tracer::{Tracer, TracerError};
 use derive_more::{Deref, DerefMut};
 use rill_protocol::flow::meta::ready_board::{Board, ReadyBoardEvent, ReadyBoardState};
 use rill_protocol::io::provider::Path;
@@ -12,11 +12,11 @@ pub struct ReadyBoardTracer {
 
 impl ReadyBoardTracer {
     /// Create a new instance of the `Tracer`.
-    pub fn new(path: Path) -> Self {
+    pub fn new(path: Path) -> Result<Self, TracerError> {
         let state = ReadyBoardState::new();
         // TODO: Use the `Receiver`
-        let tracer = Tracer::new_push(state, path).0;
-        Self { tracer }
+        let tracer = Tracer::new_push(state, path)?.0;
+        Ok(Self { tracer })
     }
 
     /// Add a board
@@ -6,10 +6,11 @@ use anyhow::Error;
 //use futures::channel::mpsc;
 use meio::Action;
 use rill_protocol::flow::core::{self, ActionEnvelope, TimedEvent};
-use rill_protocol::io::provider::{Description, Path, ProviderProtocol, Timestamp};
+use rill_protocol::io::provider::{Description, Path, PathError, ProviderProtocol, Timestamp};
 use rill_protocol::io::transport::Direction;
 use std::sync::{Arc, Mutex, Weak};
 use std::time::{Duration, SystemTime};
+use thiserror::Error;
 use tokio::sync::{mpsc, watch, Notify};
 
 #[derive(Debug)]
@@ -90,13 +91,57 @@ impl<T: core::Flow> Clone for InnerMode<T> {
     }
 }
 
+/// The reason why a `Tracer` can't be created.
+#[derive(Error, Debug)]
+pub enum TracerError {
+    /// The path is invalid or uses reserved entries.
+    #[error("Invalid path: {0}")]
+    InvalidPath(#[from] PathError),
+    /// Another `Tracer` uses the path.
+    #[error("Path {0} is already registered.")]
+    DuplicatePath(Path),
+    /// The worker that serves tracers is terminated.
+    #[error("Can't register a Tracer. The worker is terminated already.")]
+    Terminated,
+}
+
+/// The claim of the path of a `Tracer`.
+///
+/// The path is released when the last clone of the `Tracer` is dropped.
+#[derive(Debug)]
+struct PathClaim {
+    description: Arc<Description>,
+}
+
+impl PathClaim {
+    /// Releases the path and stops the `Recorder` if the path is still claimed.
+    fn release(&self) {
+        if connector::DISTRIBUTOR.release_path(&self.description) {
+            let description = self.description.clone();
+            if let Err(err) = connector::DISTRIBUTOR.unregister_tracer(description) {
+                log::debug!(
+                    "Can't unregister a Tracer. The worker can be terminated already: {}",
+                    err
+                );
+            }
+        }
+    }
+}
+
+impl Drop for PathClaim {
+    fn drop(&mut self) {
+        self.release();
+    }
+}
+
 /// The generic provider that forwards metrics to worker and keeps a flag
 /// for checking the activitiy status of the `Tracer`.
 #[derive(Debug)]
 pub struct Tracer<T: core::Flow> {
-    /// The receiver that used to activate/deactivate streams.
-    active: watch::Receiver<bool>,
+    /// The flag that used to activate/deactivate streams.
+    active: Arc<watch::Sender<bool>>,
     description: Arc<Description>,
+    claim: Arc<PathClaim>,
     mode: InnerMode<T>,
 }
 
@@ -105,6 +150,7 @@ impl<T: core::Flow> Clone for Tracer<T> {
         Self {
             active: self.active.clone(),
             description: self.description.clone(),
+            claim: self.claim.clone(),
             mode: self.mode.clone(),
         }
     }
@@ -123,7 +169,19 @@ impl<T: core::Flow> Eq for Tracer<T> {}
 
 impl<T: core::Flow> Tracer<T> {
     /// Create a `Push` mode `Tracer`
-    pub fn new_push(state: T, path: Path) -> (Self, Watcher<T>) {
+    ///
+    /// The `Recorder` lives as long as any clone of the `Tracer`.
+    /// Dropping of all clones unregisters it and releases the path.
+    pub fn new_push(state: T, path: Path) -> Result<(Self, Watcher<T>), TracerError> {
+        Self::push(state, path, false)
+    }
+
+    /// Create a `Push` mode `Tracer` of a meta flow that can use reserved entries.
+    pub(crate) fn new_meta(state: T, path: Path) -> Result<Self, TracerError> {
+        Self::push(state, path, true).map(|(tracer, _)| tracer)
+    }
+
+    fn push(state: T, path: Path, reserved: bool) -> Result<(Self, Watcher<T>), TracerError> {
         let (tx, rx) = mpsc::unbounded_channel();
         let (control_tx, control_rx) = mpsc::unbounded_channel();
         let mode = TracerMode::Push {
@@ -132,11 +190,12 @@ impl<T: core::Flow> Tracer<T> {
             control_sender: Some(control_tx),
         };
         let inner_mode = InnerMode::Push { sender: tx };
-        (Self::new_inner(path, inner_mode, mode), control_rx)
+        let tracer = Self::new_inner(path, inner_mode, mode, reserved)?;
+        Ok((tracer, control_rx))
     }
 
     /// Create a `Pull` mode `Tracer`
-    pub fn new_pull(state: T, path: Path, interval: Duration) -> Self {
+    pub fn new_pull(state: T, path: Path, interval: Duration) -> Result<Self, TracerError> {
         let state = Arc::new(Mutex::new(state));
         let notifier = Arc::new(Notify::new());
         let mode = TracerMode::Pull {
@@ -145,10 +204,25 @@ impl<T: core::Flow> Tracer<T> {
             notifier: notifier.clone(),
         };
         let inner_mode = InnerMode::Pull { state, notifier };
-        Self::new_inner(path, inner_mode, mode)
+        Self::new_inner(path, inner_mode, mode, false)
+    }
+
+    /// Reserved entries are allowed for meta flows only.
+    fn check_path(path: &Path, reserved: bool) -> Result<(), PathError> {
+        path.validate()?;
+        match path.reserved() {
+            Some(entry_id) if !reserved => Err(PathError::Reserved(entry_id.clone())),
+            _ => Ok(()),
+        }
     }
 
-    fn new_inner(path: Path, inner_mode: InnerMode<T>, mode: TracerMode<T>) -> Self {
+    fn new_inner(
+        path: Path,
+        inner_mode: InnerMode<T>,
+        mode: TracerMode<T>,
+        reserved: bool,
+    ) -> Result<Self, TracerError> {
+        Self::check_path(&path, reserved)?;
         let stream_type = T::stream_type();
         let info = format!("{} - {}", path, stream_type);
         let description = Description {
@@ -157,21 +231,40 @@ impl<T: core::Flow> Tracer<T> {
             stream_type,
         };
         // TODO: Remove this active watch channel?
-        let (_active_tx, active_rx) = watch::channel(true);
+        let (active_tx, _active_rx) = watch::channel(true);
         log::trace!("Creating Tracer with path: {}", description.path);
         let description = Arc::new(description);
-        let this = Tracer {
-            active: active_rx,
-            description: description.clone(),
-            mode: inner_mode,
-        };
-        if let Err(err) = connector::DISTRIBUTOR.register_tracer(description, mode) {
+        if !connector::DISTRIBUTOR.claim_path(&description) {
+            return Err(TracerError::DuplicatePath(description.path.clone()));
+        }
+        if let Err(err) = connector::DISTRIBUTOR.register_tracer(description.clone(), mode) {
             log::error!(
-                "Can't register a Tracer. The worker can be terminated already: {}",
+                "Can't register a Tracer with the path {}: {}",
+                description.path,
                 err
             );
+            connector::DISTRIBUTOR.release_path(&description);
+            return Err(TracerError::Terminated);
         }
-        this
+        let claim = PathClaim {
+            description: description.clone(),
+        };
+        Ok(Tracer {
+            active: Arc::new(active_tx),
+            description,
+            claim: Arc::new(claim),
+            mode: inner_mode,
+        })
+    }
+
+    /// Unregisters the `Tracer` and stops its `Recorder`.
+    ///
+    /// The path is free for a new `Tracer` right after the call.
+    /// All clones of the `Tracer` become inactive. Dropping of
+    /// all clones unregisters the `Tracer` too.
+    pub fn unregister(&self) {
+        self.active.send_replace(false);
+        self.claim.release();
     }
 
     /// Returns a reference to a `Path` of the `Tracer`.
@@ -0,0 +1,145 @@
+//! Fixtures shared by tests of the engine.
+//!
+//! Every test binary uses only a part of them.
+#![allow(dead_code)]
+
+use anyhow::Error;
+use meio::{Address, System};
+use rill_engine::loopback::{self, LoopbackAcceptor, LoopbackSession};
+use rill_engine::{EngineConfig, RillEngine};
+use rill_protocol::io::codec::Frame;
+use rill_protocol::io::handshake::Handshake;
+use rill_protocol::io::provider::{
+    EntryId, Path, ProviderProtocol, ProviderToServer, RecorderAction, RecorderRequest,
+    ServerToProvider,
+};
+use rill_protocol::io::transport::{DirectId, Envelope, ServiceEnvelope};
+use rill_transport::{ConnectionStatus, ReconnectPolicy};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::{interval, timeout};
+
+pub const WAIT: Duration = Duration::from_secs(5);
+
+/// Ids of requests made by fixtures. They don't intersect with ids of tests.
+static IDS: AtomicUsize = AtomicUsize::new(1_000_000);
+
+/// A provider connected to the in-process endpoint.
+pub struct Provider {
+    pub engine: Address<RillEngine>,
+    pub status: watch::Receiver<ConnectionStatus>,
+    pub acceptor: LoopbackAcceptor,
+}
+
+/// Spawns an engine that connects to the in-process endpoint.
+///
+/// The `configure` function adjusts the config with the fast reconnection.
+pub fn spawn(
+    name: &'static str,
+    configure: impl FnOnce(&mut EngineConfig),
+) -> Result<Provider, Error> {
+    let (loopback, acceptor) = loopback::loopback();
+    let mut config = EngineConfig::new("test".into());
+    config.name = Some(EntryId::from_static(name)?);
+    config.loopback = Some(loopback);
+    config.reconnect = ReconnectPolicy {
+        initial_delay: Duration::from_millis(50),
+        ..ReconnectPolicy::default()
+    };
+    configure(&mut config);
+    let engine = RillEngine::new(config);
+    let status = engine.status();
+    let engine = System::spawn(engine);
+    Ok(Provider {
+        engine,
+        status,
+        acceptor,
+    })
+}
+
+/// Accepts the next session of the provider and reads its declaration.
+pub async fn accept(
+    acceptor: &mut LoopbackAcceptor,
+) -> Result<(LoopbackSession, Option<Handshake>), Error> {
+    let mut session = timeout(WAIT, acceptor.accept())
+        .await?
+        .ok_or_else(|| Error::msg("The provider didn't connect."))?;
+    let msg = timeout(WAIT, session.recv())
+        .await?
+        .ok_or_else(|| Error::msg("The provider disconnected."))?;
+    match msg.into_message()?.data {
+        ProviderToServer::Declare { handshake, .. } => Ok((session, handshake)),
+        other => Err(Error::msg(format!("Unexpected message: {:?}", other))),
+    }
+}
+
+/// Waits for a status that matches the `check`.
+pub async fn wait_status(
+    status: &mut watch::Receiver<ConnectionStatus>,
+    check: impl Fn(&ConnectionStatus) -> bool,
+) -> Result<(), Error> {
+    timeout(WAIT, async {
+        while !check(&status.borrow()) {
+            status.changed().await?;
+        }
+        Ok(())
+    })
+    .await?
+}
+
+/// Sends a request to the provider.
+pub fn send(session: &LoopbackSession, id: usize, path: &Path, request: RecorderRequest) {
+    let request = Envelope::<ProviderProtocol, _> {
+        direct_id: DirectId::from(id),
+        data: ServerToProvider {
+            path: path.clone(),
+            request,
+        },
+    };
+    session.send(Frame::Message(ServiceEnvelope::Envelope(request)));
+}
+
+/// Waits for a response with the `id` and skips other messages.
+pub async fn response(session: &mut LoopbackSession, id: usize) -> Result<ProviderToServer, Error> {
+    loop {
+        let msg = session
+            .recv()
+            .await
+            .ok_or_else(|| Error::msg("The provider disconnected."))?
+            .into_message()?;
+        let ids: Vec<usize> = msg
+            .direction
+            .into_vec()
+            .into_iter()
+            .map(usize::from)
+            .collect();
+        if ids.contains(&id) {
+            return Ok(msg.data);
+        }
+    }
+}
+
+/// Requests the flow of the `path` until the recorder is `registered` or removed.
+///
+/// Tracers are registered and removed asynchronously.
+pub async fn wait_recorder(
+    session: &mut LoopbackSession,
+    path: &Path,
+    registered: bool,
+) -> Result<(), Error> {
+    let mut ticks = interval(Duration::from_millis(50));
+    timeout(WAIT, async {
+        loop {
+            ticks.tick().await;
+            let id = IDS.fetch_add(1, Ordering::Relaxed);
+            let request = RecorderRequest::Action(RecorderAction::GetFlow);
+            send(session, id, path, request);
+            let found = matches!(response(session, id).await?, ProviderToServer::Flow { .. });
+            if found == registered {
+                return Ok(());
+            }
+        }
+    })
+    .await?
+}
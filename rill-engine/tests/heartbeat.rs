@@ -0,0 +1,75 @@
+mod common;
+
+use anyhow::Error;
+use meio::System;
+use rill_engine::loopback::LoopbackSession;
+use rill_protocol::io::client::AccessLevel;
+use rill_protocol::io::codec::Frame;
+use rill_protocol::io::handshake::{Handshake, FEATURE_HEARTBEAT};
+use rill_protocol::io::provider::{ProviderServiceRequest, ProviderToServer};
+use rill_protocol::io::transport::ServiceEnvelope;
+use std::time::{Duration, Instant};
+use tokio::time::timeout;
+
+const WAIT: Duration = Duration::from_secs(10);
+
+fn send(session: &LoopbackSession, request: ProviderServiceRequest) {
+    session.send(Frame::Message(ServiceEnvelope::Service(request)));
+}
+
+/// Waits for a ping of the provider.
+async fn ping(session: &mut LoopbackSession) -> Result<Instant, Error> {
+    loop {
+        let msg = session
+            .recv()
+            .await
+            .ok_or_else(|| Error::msg("The provider disconnected."))?
+            .into_message()?;
+        if let ProviderToServer::Ping = msg.data {
+            return Ok(Instant::now());
+        }
+    }
+}
+
+#[tokio::test]
+async fn missed_pongs_drop_the_connection() -> Result<(), Error> {
+    let mut provider = common::spawn("heartbeat", |config| {
+        config.ping_interval = Some(1);
+        config.ping_deadline = Some(2);
+    })?;
+
+    let (mut session, handshake) = common::accept(&mut provider.acceptor).await?;
+    let handshake = handshake.expect("no handshake");
+    let negotiated = Handshake::default().negotiate(&handshake)?;
+    assert!(negotiated.has_feature(FEATURE_HEARTBEAT));
+    send(&session, ProviderServiceRequest::Accepted(negotiated));
+    send(
+        &session,
+        ProviderServiceRequest::AccessLevel(AccessLevel::ReadyToWork),
+    );
+
+    // Pings are sent every interval while pongs are received
+    let mut last = timeout(WAIT, ping(&mut session)).await??;
+    for _ in 0..2 {
+        send(&session, ProviderServiceRequest::Pong);
+        let next = timeout(WAIT, ping(&mut session)).await??;
+        let interval = next.duration_since(last);
+        assert!(interval >= Duration::from_millis(900), "{:?}", interval);
+        assert!(interval < Duration::from_millis(1_900), "{:?}", interval);
+        last = next;
+    }
+
+    // The connection without pongs is dropped after the deadline
+    let result = timeout(WAIT, ping(&mut session)).await?;
+    assert!(result.is_err(), "A ping sent before the pong: {:?}", result);
+    let dropped = last.elapsed();
+    assert!(dropped >= Duration::from_secs(2), "{:?}", dropped);
+    assert!(dropped < Duration::from_secs(4), "{:?}", dropped);
+    // And the provider reconnects
+    timeout(WAIT, provider.acceptor.accept())
+        .await?
+        .expect("no session");
+
+    System::interrupt(&provider.engine)?;
+    Ok(())
+}
@@ -0,0 +1,35 @@
+mod common;
+
+use anyhow::Error;
+use common::WAIT;
+use meio::System;
+use rill_transport::{ConnectionStatus, ReconnectPolicy};
+use std::time::{Duration, Instant};
+use tokio::time::timeout;
+
+#[tokio::test]
+async fn reconnect_with_backoff() -> Result<(), Error> {
+    let mut provider = common::spawn("backoff", |config| {
+        config.reconnect = ReconnectPolicy {
+            initial_delay: Duration::from_millis(200),
+            jitter: 0.0,
+            ..ReconnectPolicy::default()
+        };
+    })?;
+
+    let (session, _) = common::accept(&mut provider.acceptor).await?;
+    drop(session);
+    let closed = Instant::now();
+
+    common::wait_status(&mut provider.status, |status| {
+        matches!(status, ConnectionStatus::Backoff { attempt: 1, .. })
+    })
+    .await?;
+    let _session = timeout(WAIT, provider.acceptor.accept())
+        .await?
+        .expect("no session");
+    assert!(closed.elapsed() >= Duration::from_millis(200));
+
+    System::interrupt(&provider.engine)?;
+    Ok(())
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use anyhow::Error;
+use common::{response, send, wait_recorder, WAIT};
+use meio::System;
+use rill_engine::loopback::LoopbackSession;
+use rill_engine::tracers::meta::AlertTracer;
+use rill_protocol::io::provider::{FlowControl, Path, ProviderToServer, RecorderRequest};
+use std::time::Duration;
+use tokio::time::{sleep, timeout};
+
+/// The id of the subscription to the subtree.
+const SUBTREE: usize = 1_000;
+
+fn control(session: &LoopbackSession, path: &Path, control: FlowControl) {
+    send(
+        session,
+        SUBTREE,
+        path,
+        RecorderRequest::ControlStream(control),
+    );
+}
+
+/// Waits for a tagged state of the subtree.
+async fn tagged_state(session: &mut LoopbackSession) -> Result<Path, Error> {
+    match timeout(WAIT, response(session, SUBTREE)).await?? {
+        ProviderToServer::Tagged { path, data } => match *data {
+            ProviderToServer::State { .. } => Ok(path),
+            other => Err(Error::msg(format!("Unexpected data: {:?}", other))),
+        },
+        other => Err(Error::msg(format!("Unexpected response: {:?}", other))),
+    }
+}
+
+#[tokio::test]
+async fn subtree_subscriptions() -> Result<(), Error> {
+    let mut provider = common::spawn("subtree", |_| {})?;
+    let (mut session, _) = common::accept(&mut provider.acceptor).await?;
+
+    let subtree: Path = "svc".parse()?;
+    let first: Path = "svc.first".parse()?;
+    let _first = AlertTracer::new(first.clone())?;
+    wait_recorder(&mut session, &first, true).await?;
+
+    control(&session, &subtree, FlowControl::StartStream);
+    assert_eq!(tagged_state(&mut session).await?, first);
+
+    // Recorders added later join the subscription
+    let second: Path = "svc.second".parse()?;
+    let _second = AlertTracer::new(second.clone())?;
+    assert_eq!(tagged_state(&mut session).await?, second);
+    // Recorders out of the subtree don't
+    let other: Path = "other".parse()?;
+    let _other = AlertTracer::new(other.clone())?;
+    wait_recorder(&mut session, &other, true).await?;
+
+    // Every recorder ends its stream before the stream of the subtree ends
+    control(&session, &subtree, FlowControl::StopStream);
+    let mut ended = Vec::new();
+    loop {
+        match timeout(WAIT, response(&mut session, SUBTREE)).await?? {
+            ProviderToServer::Tagged { path, data } => {
+                assert!(matches!(*data, ProviderToServer::EndStream), "{:?}", data);
+                ended.push(path);
+            }
+            ProviderToServer::EndStream => break,
+            other => panic!("Unexpected response: {:?}", other),
+        }
+    }
+    ended.sort();
+    assert_eq!(ended, vec![first.clone(), second]);
+
+    // Subscriptions are cleared on disconnect
+    control(&session, &subtree, FlowControl::StartStream);
+    tagged_state(&mut session).await?;
+    drop(session);
+    let (mut session, _) = common::accept(&mut provider.acceptor).await?;
+    let third: Path = "svc.third".parse()?;
+    let _third = AlertTracer::new(third.clone())?;
+    wait_recorder(&mut session, &third, true).await?;
+    sleep(Duration::from_millis(100)).await;
+    // The id is free and nothing was sent to the old subscription
+    control(&session, &subtree, FlowControl::StartStream);
+    let mut paths = Vec::new();
+    for _ in 0..3 {
+        paths.push(tagged_state(&mut session).await?);
+    }
+    paths.sort();
+    assert_eq!(
+        paths,
+        vec!["svc.first".parse()?, "svc.second".parse()?, third]
+    );
+
+    System::interrupt(&provider.engine)?;
+    Ok(())
+}
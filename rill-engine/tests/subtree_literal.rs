@@ -0,0 +1,42 @@
+mod common;
+
+use anyhow::Error;
+use common::{response, send, wait_recorder, WAIT};
+use meio::System;
+use rill_engine::tracers::meta::AlertTracer;
+use rill_protocol::io::provider::{EntryId, FlowControl, Path, ProviderToServer, RecorderRequest};
+use tokio::time::timeout;
+
+fn path(entries: &[&str]) -> Result<Path, Error> {
+    let entries = entries
+        .iter()
+        .map(|entry| EntryId::new(*entry))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(entries.into())
+}
+
+#[tokio::test]
+async fn subtrees_of_escaped_entries_are_literal() -> Result<(), Error> {
+    let mut provider = common::spawn("literal", |_| {})?;
+    let (mut session, _) = common::accept(&mut provider.acceptor).await?;
+
+    // The entry `\x` is not an escaped `x`
+    let escaped = path(&["lit", "\\x", "a"])?;
+    let plain = path(&["lit", "x", "b"])?;
+    let _escaped = AlertTracer::new(escaped.clone())?;
+    let _plain = AlertTracer::new(plain.clone())?;
+
+    wait_recorder(&mut session, &escaped, true).await?;
+    wait_recorder(&mut session, &plain, true).await?;
+
+    let request = RecorderRequest::ControlStream(FlowControl::StartStream);
+    send(&session, 100, &self::path(&["lit", "\\x"])?, request);
+    let msg = timeout(WAIT, response(&mut session, 100)).await??;
+    match msg {
+        ProviderToServer::Tagged { path, .. } => assert_eq!(path, escaped),
+        other => panic!("Unexpected response: {:?}", other),
+    }
+
+    System::interrupt(&provider.engine)?;
+    Ok(())
+}
@@ -0,0 +1,49 @@
+mod common;
+
+use anyhow::Error;
+use common::wait_recorder;
+use meio::System;
+use rill_engine::tracers::tracer::{Tracer, TracerError};
+use rill_protocol::flow::meta::alert::AlertState;
+use rill_protocol::io::provider::Path;
+
+fn tracer(path: &Path) -> Result<Tracer<AlertState>, TracerError> {
+    Tracer::new_push(AlertState::new(), path.clone()).map(|(tracer, _)| tracer)
+}
+
+fn is_duplicate(path: &Path) -> bool {
+    matches!(tracer(path), Err(TracerError::DuplicatePath(taken)) if &taken == path)
+}
+
+#[tokio::test]
+async fn paths_are_released_by_tracers() -> Result<(), Error> {
+    let mut provider = common::spawn("paths", |_| {})?;
+    let (mut session, _) = common::accept(&mut provider.acceptor).await?;
+
+    let path: Path = "alerts".parse()?;
+    let first = tracer(&path)?;
+    assert!(is_duplicate(&path));
+    wait_recorder(&mut session, &path, true).await?;
+
+    // Clones keep the path
+    let clone = first.clone();
+    drop(first);
+    assert!(is_duplicate(&path));
+
+    // The path is free right after the unregistration
+    clone.unregister();
+    assert!(!clone.is_active());
+    let second = tracer(&path)?;
+    // The unregistered clone doesn't release the path of another tracer
+    drop(clone);
+    assert!(is_duplicate(&path));
+    wait_recorder(&mut session, &path, true).await?;
+
+    // Dropping the last clone removes the recorder
+    drop(second);
+    wait_recorder(&mut session, &path, false).await?;
+    let _third = tracer(&path)?;
+
+    System::interrupt(&provider.engine)?;
+    Ok(())
+}
@@ -0,0 +1,82 @@
+mod common;
+
+use anyhow::Error;
+use common::{response, WAIT};
+use meio::System;
+use rill_engine::loopback::LoopbackSession;
+use rill_engine::tracers::meta::AlertTracer;
+use rill_protocol::encoding;
+use rill_protocol::io::codec::Frame;
+use rill_protocol::io::provider::{
+    ProviderProtocol, ProviderToServer, RecorderAction, RecorderRequest, ServerToProvider,
+};
+use rill_protocol::io::transport::{DirectId, Envelope};
+use rill_transport::ConnectionStatus;
+use std::time::Duration;
+use tokio::time::{interval, timeout};
+
+/// Sends a request as a node of the first version: without a service envelope.
+fn send_v1(session: &LoopbackSession, id: usize, path: &str) -> Result<(), Error> {
+    let request = Envelope::<ProviderProtocol, _> {
+        direct_id: DirectId::from(id),
+        data: ServerToProvider {
+            path: path.parse()?,
+            request: RecorderRequest::Action(RecorderAction::GetFlow),
+        },
+    };
+    session.send(Frame::Encoded(encoding::to_vec(&request)?));
+    Ok(())
+}
+
+#[tokio::test]
+async fn requests_without_service_envelopes() -> Result<(), Error> {
+    let mut provider = common::spawn("legacy", |config| {
+        config.ping_interval = Some(1);
+        config.ping_deadline = Some(1);
+    })?;
+    let (mut session, _) = common::accept(&mut provider.acceptor).await?;
+    let _tracer = AlertTracer::new("alerts".parse()?)?;
+
+    // A node of the first version ignores the handshake and sends
+    // requests as plain envelopes
+    send_v1(&session, 1, "missing")?;
+    let msg = timeout(WAIT, response(&mut session, 1)).await??;
+    assert!(matches!(msg, ProviderToServer::Error { .. }), "{:?}", msg);
+    // The request made the provider ready
+    assert_eq!(*provider.status.borrow(), ConnectionStatus::Connected);
+
+    // The tracer is registered asynchronously
+    let mut ticks = interval(Duration::from_millis(50));
+    let mut id = 2;
+    let flow = timeout(WAIT, async {
+        loop {
+            ticks.tick().await;
+            send_v1(&session, id, "alerts")?;
+            match response(&mut session, id).await? {
+                ProviderToServer::Error { .. } => id += 1,
+                msg => return Ok::<_, Error>(msg),
+            }
+        }
+    })
+    .await??;
+    assert!(matches!(flow, ProviderToServer::Flow { .. }), "{:?}", flow);
+
+    // The next connection is ready when the node didn't answer the handshake
+    drop(session);
+    let (mut session, _) = common::accept(&mut provider.acceptor).await?;
+    common::wait_status(&mut provider.status, |status| {
+        *status != ConnectionStatus::Connected
+    })
+    .await?;
+    common::wait_status(&mut provider.status, |status| {
+        *status == ConnectionStatus::Connected
+    })
+    .await?;
+    // Recorders got the connection
+    send_v1(&session, 100, "alerts")?;
+    let flow = timeout(WAIT, response(&mut session, 100)).await??;
+    assert!(matches!(flow, ProviderToServer::Flow { .. }), "{:?}", flow);
+
+    System::interrupt(&provider.engine)?;
+    Ok(())
+}
@@ -0,0 +1,230 @@
+use super::link;
+use crate::actors::handler::SessionHandler;
+use crate::actors::hub::{HubLink, RillHub};
+use anyhow::Error;
+use async_trait::async_trait;
+use meio::{
+    ActionHandler, Actor, Context, IdOf, InterruptedBy, StartedBy, TaskEliminated, TaskError,
+};
+use meio_connect::server::WsProcessor;
+use meio_connect::{TermReason, WsIncoming};
+use rill_protocol::io::auth::Credentials;
+use rill_protocol::io::client::{
+    AccessLevel, ClientProtocol, ClientRequest, ClientResponse, ClientServiceRequest,
+    ClientServiceResponse,
+};
+use rill_protocol::io::codec::{Frame, Settings};
+use rill_protocol::io::handshake::{Handshake, HandshakeError, Negotiated, FEATURE_ACTIONS};
+use rill_protocol::io::transport::{Envelope, ServiceEnvelope};
+use rill_transport::server::ConnectionProcessor;
+
+type IncomingMessage = ServiceEnvelope<ClientProtocol, ClientRequest, ClientServiceResponse>;
+
+type OutgoingMessage = ServiceEnvelope<ClientProtocol, ClientResponse, ClientServiceRequest>;
+
+/// The session of a connected client.
+pub struct ClientSession {
+    handler: SessionHandler<ClientProtocol>,
+    hub: HubLink,
+    token: Option<String>,
+    /// The settings of frames configured for the hub.
+    preferred: Settings,
+    /// The settings of frames agreed with the client.
+    settings: Settings,
+    /// Features agreed with the client.
+    negotiated: Negotiated,
+    access_level: AccessLevel,
+}
+
+impl ClientSession {
+    pub(crate) fn new(
+        handler: SessionHandler<ClientProtocol>,
+        hub: HubLink,
+        token: Option<String>,
+        preferred: Settings,
+    ) -> Self {
+        Self {
+            handler,
+            hub,
+            token,
+            preferred,
+            settings: Settings::default(),
+            negotiated: Negotiated::v1(),
+            access_level: AccessLevel::SessionCreated,
+        }
+    }
+
+    fn send(&self, msg: OutgoingMessage) {
+        match self.settings.frame(msg) {
+            Ok(frame) => self.handler.send(frame),
+            Err(err) => log::error!("Can't encode a message to the client: {}", err),
+        }
+    }
+
+    fn send_service(&self, request: ClientServiceRequest) {
+        self.send(ServiceEnvelope::Service(request));
+    }
+
+    fn set_access_level(&mut self, level: AccessLevel) {
+        self.access_level = level;
+        self.send_service(ClientServiceRequest::AccessLevel(level));
+    }
+
+    fn authorize(&mut self, credentials: Credentials) {
+        let verified = self
+            .token
+            .as_ref()
+            .map(|token| credentials.verify(token))
+            .unwrap_or(true);
+        if verified {
+            self.set_access_level(AccessLevel::ReadyToWork);
+        } else {
+            let reason = "Invalid credentials.".into();
+            self.send_service(ClientServiceRequest::AccessDenied { reason });
+        }
+    }
+
+    /// Answers to the handshake of the client.
+    fn negotiate(&mut self, handshake: &Handshake) -> Result<(), HandshakeError> {
+        match Handshake::default().negotiate(handshake) {
+            Ok(negotiated) => {
+                log::debug!("Protocol negotiated: {:?}", negotiated);
+                // The client can decode frames of any agreed codec and compression
+                self.settings = negotiated.settings(&self.preferred);
+                self.negotiated = negotiated.clone();
+                self.send_service(ClientServiceRequest::Accepted(negotiated));
+                Ok(())
+            }
+            Err(err) => {
+                let reason = err.to_string();
+                self.send_service(ClientServiceRequest::Incompatible { reason });
+                Err(err)
+            }
+        }
+    }
+
+    fn reply(&self, envelope: Envelope<ClientProtocol, ClientResponse>) {
+        let confirmation = matches!(
+            envelope.data,
+            ClientResponse::Delivered | ClientResponse::Unconfirmed
+        );
+        if confirmation && !self.negotiated.has_feature(FEATURE_ACTIONS) {
+            // Clients of the first version don't expect confirmations
+            return;
+        }
+        self.send(ServiceEnvelope::Envelope(envelope));
+    }
+}
+
+impl Actor for ClientSession {
+    type GroupBy = ();
+
+    fn name(&self) -> String {
+        format!("ClientSession({})", self.handler.addr())
+    }
+}
+
+#[async_trait]
+impl StartedBy<RillHub> for ClientSession {
+    async fn handle(&mut self, ctx: &mut Context<Self>) -> Result<(), Error> {
+        self.handler.start(ctx);
+        self.set_access_level(AccessLevel::SessionCreated);
+        if self.token.is_some() {
+            self.set_access_level(AccessLevel::ReadyToAuth);
+        } else {
+            self.set_access_level(AccessLevel::ReadyToWork);
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl InterruptedBy<RillHub> for ClientSession {
+    async fn handle(&mut self, ctx: &mut Context<Self>) -> Result<(), Error> {
+        ctx.shutdown();
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TaskEliminated<WsProcessor<ClientProtocol, Self>, ()> for ClientSession {
+    async fn handle(
+        &mut self,
+        _id: IdOf<WsProcessor<ClientProtocol, Self>>,
+        _tag: (),
+        _result: Result<TermReason, TaskError>,
+        ctx: &mut Context<Self>,
+    ) -> Result<(), Error> {
+        ctx.shutdown();
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TaskEliminated<ConnectionProcessor<ClientProtocol, Self>, ()> for ClientSession {
+    async fn handle(
+        &mut self,
+        _id: IdOf<ConnectionProcessor<ClientProtocol, Self>>,
+        _tag: (),
+        _result: Result<(), TaskError>,
+        ctx: &mut Context<Self>,
+    ) -> Result<(), Error> {
+        ctx.shutdown();
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ActionHandler<WsIncoming<Frame<IncomingMessage>>> for ClientSession {
+    async fn handle(
+        &mut self,
+        msg: WsIncoming<Frame<IncomingMessage>>,
+        ctx: &mut Context<Self>,
+    ) -> Result<(), Error> {
+        log::trace!("Incoming from client: {:?}", msg);
+        match msg.0.into_message()? {
+            ServiceEnvelope::Envelope(envelope) => {
+                if self.access_level == AccessLevel::ReadyToWork {
+                    let id = ctx.address().id();
+                    self.hub.client_request(id, envelope).await?;
+                } else {
+                    let envelope = Envelope {
+                        direct_id: envelope.direct_id,
+                        data: ClientResponse::Error("Not authorized.".into()),
+                    };
+                    self.reply(envelope);
+                }
+            }
+            ServiceEnvelope::Service(response) => match response {
+                ClientServiceResponse::Ping => {
+                    self.send_service(ClientServiceRequest::Pong);
+                }
+                ClientServiceResponse::Pong => {
+                    // The hub doesn't ping clients.
+                }
+                ClientServiceResponse::Authorize(credentials) => {
+                    self.authorize(credentials);
+                }
+                ClientServiceResponse::Hello(handshake) => {
+                    if let Err(err) = self.negotiate(&handshake) {
+                        log::warn!("Client rejected: {}", err);
+                        ctx.shutdown();
+                    }
+                }
+            },
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ActionHandler<link::ForwardResponse> for ClientSession {
+    async fn handle(
+        &mut self,
+        msg: link::ForwardResponse,
+        _ctx: &mut Context<Self>,
+    ) -> Result<(), Error> {
+        self.reply(msg.envelope);
+        Ok(())
+    }
+}
@@ -0,0 +1,27 @@
+use super::ClientSession;
+use anyhow::Error;
+use derive_more::From;
+use meio::{Action, Address};
+use rill_protocol::io::client::{ClientProtocol, ClientResponse};
+use rill_protocol::io::transport::Envelope;
+
+#[derive(Debug, Clone, From)]
+pub(crate) struct ClientSessionLink {
+    address: Address<ClientSession>,
+}
+
+pub(super) struct ForwardResponse {
+    pub envelope: Envelope<ClientProtocol, ClientResponse>,
+}
+
+impl Action for ForwardResponse {}
+
+impl ClientSessionLink {
+    pub async fn forward(
+        &mut self,
+        envelope: Envelope<ClientProtocol, ClientResponse>,
+    ) -> Result<(), Error> {
+        let msg = ForwardResponse { envelope };
+        self.address.act(msg).await
+    }
+}
@@ -0,0 +1,5 @@
+mod actor;
+pub use actor::ClientSession;
+
+mod link;
+pub(crate) use link::ClientSessionLink;
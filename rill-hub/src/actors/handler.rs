@@ -0,0 +1,58 @@
+use meio::{ActionHandler, Actor, Context, TaskEliminated};
+use meio_connect::server::{WsHandler, WsProcessor};
+use meio_connect::WsIncoming;
+use meio_protocol::Protocol;
+use rill_transport::server::{Connection, ConnectionProcessor};
+
+/// The connection of a session over any transport.
+pub(crate) enum SessionHandler<P: Protocol> {
+    Ws(Box<WsHandler<P>>),
+    Transport(Connection<P>),
+}
+
+impl<P: Protocol> SessionHandler<P> {
+    pub fn addr(&self) -> String {
+        match self {
+            Self::Ws(handler) => handler.addr().to_string(),
+            Self::Transport(connection) => connection.addr().to_string(),
+        }
+    }
+
+    pub fn send(&self, msg: P::ToClient) {
+        match self {
+            Self::Ws(handler) => handler.send(msg),
+            Self::Transport(connection) => connection.send(msg),
+        }
+    }
+
+    /// Spawns the worker that delivers incoming messages to the session.
+    pub fn start<A>(&mut self, ctx: &mut Context<A>)
+    where
+        A: Actor<GroupBy = ()>
+            + ActionHandler<WsIncoming<P::ToServer>>
+            + TaskEliminated<WsProcessor<P, A>, ()>
+            + TaskEliminated<ConnectionProcessor<P, A>, ()>,
+    {
+        let address = ctx.address().clone();
+        match self {
+            Self::Ws(handler) => {
+                ctx.spawn_task(handler.worker(address), (), ());
+            }
+            Self::Transport(connection) => {
+                ctx.spawn_task(connection.worker(address), (), ());
+            }
+        }
+    }
+}
+
+impl<P: Protocol> From<WsHandler<P>> for SessionHandler<P> {
+    fn from(handler: WsHandler<P>) -> Self {
+        Self::Ws(Box::new(handler))
+    }
+}
+
+impl<P: Protocol> From<Connection<P>> for SessionHandler<P> {
+    fn from(connection: Connection<P>) -> Self {
+        Self::Transport(connection)
+    }
+}
@@ -0,0 +1,529 @@
+mod routes;
+
+use super::link;
+use crate::actors::client_session::{ClientSession, ClientSessionLink};
+use crate::actors::handler::SessionHandler;
+use crate::actors::provider_session::{ProviderSession, ProviderSessionLink};
+use crate::config::HubConfig;
+use anyhow::Error;
+use async_trait::async_trait;
+use meio::{
+    ActionHandler, Actor, Context, Eliminated, IdOf, InteractionHandler, InterruptedBy, StartedBy,
+    TaskEliminated, TaskError,
+};
+use meio_connect::server::{DirectPath, HttpServer, HttpServerLink, NoParameters, WsReq, WsRoute};
+use meio_protocol::Protocol;
+use rill_protocol::io::client::{ClientProtocol, ClientReqId, ClientRequest, ClientResponse};
+use rill_protocol::io::handshake::{Negotiated, FEATURE_ACTIONS};
+use rill_protocol::io::provider::{
+    EntryId, FlowControl, Path, ProviderProtocol, ProviderToServer, RecorderRequest,
+    ServerToProvider,
+};
+use rill_protocol::io::transport::Envelope;
+use rill_transport::{Accepted, Endpoint, LoopbackAcceptor, TlsAcceptor, TransportListener};
+use routes::Routes;
+use std::collections::HashMap;
+use strum::{EnumIter, IntoEnumIterator};
+
+struct ProviderLive;
+
+impl DirectPath for ProviderLive {
+    type Output = NoParameters;
+    type Parameter = ProviderProtocol;
+
+    fn paths() -> &'static [&'static str] {
+        &["/live/provider"]
+    }
+}
+
+struct ClientLive;
+
+impl DirectPath for ClientLive {
+    type Output = NoParameters;
+    type Parameter = ClientProtocol;
+
+    fn paths() -> &'static [&'static str] {
+        &["/live/client"]
+    }
+}
+
+/// A declared provider with requests forwarded to it.
+struct Provider {
+    session: ProviderSessionLink,
+    routes: Routes,
+    /// Features agreed with the provider.
+    negotiated: Negotiated,
+}
+
+/// The server that routes requests of clients to providers.
+pub struct RillHub {
+    config: HubConfig,
+    server: Option<HttpServerLink>,
+    providers: HashMap<EntryId, Provider>,
+    /// Names of declared providers by their sessions.
+    names: HashMap<IdOf<ProviderSession>, EntryId>,
+    clients: HashMap<IdOf<ClientSession>, ClientSessionLink>,
+    /// In-process connections of providers.
+    loopback: Option<LoopbackAcceptor<ProviderProtocol>>,
+    /// TLS settings shared by `wss://` endpoints.
+    tls: Option<TlsAcceptor>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, EnumIter)]
+pub enum Group {
+    Server,
+    Sessions,
+}
+
+impl Actor for RillHub {
+    type GroupBy = Group;
+
+    fn name(&self) -> String {
+        format!("RillHub({})", self.config.addr())
+    }
+}
+
+impl RillHub {
+    /// Creates a new hub instance.
+    pub fn new(config: HubConfig) -> Self {
+        Self {
+            config,
+            server: None,
+            providers: HashMap::new(),
+            names: HashMap::new(),
+            clients: HashMap::new(),
+            loopback: None,
+            tls: None,
+        }
+    }
+
+    /// Accepts providers connected in-process with a `Loopback` as well.
+    pub fn with_loopback(mut self, acceptor: LoopbackAcceptor<ProviderProtocol>) -> Self {
+        self.loopback = Some(acceptor);
+        self
+    }
+
+    /// Creates a listener of an extra endpoint.
+    ///
+    /// TLS files are read once for all `wss://` endpoints.
+    fn listener<P>(
+        &mut self,
+        url: &str,
+        ctx: &mut Context<Self>,
+    ) -> Result<TransportListener<P, Self>, Error>
+    where
+        P: Protocol,
+        Self: ActionHandler<Accepted<P>>,
+    {
+        let endpoint: Endpoint = url.parse()?;
+        let is_wss = matches!(endpoint, Endpoint::Wss(_));
+        let mut listener = TransportListener::bind(endpoint, ctx.address().clone());
+        if is_wss {
+            let tls = match &self.tls {
+                Some(tls) => tls.clone(),
+                None => {
+                    let config = self.config.tls().ok_or_else(|| {
+                        Error::msg(format!("No TLS certificate and key to listen to {}", url))
+                    })?;
+                    let tls = config.load()?;
+                    self.tls = Some(tls.clone());
+                    tls
+                }
+            };
+            listener = listener.with_tls(tls);
+        }
+        Ok(listener)
+    }
+
+    fn provider_connected(
+        &mut self,
+        handler: SessionHandler<ProviderProtocol>,
+        ctx: &mut Context<Self>,
+    ) {
+        log::info!("Provider connected from: {}", handler.addr());
+        let hub = ctx.address().clone().into();
+        let settings = self.config.settings();
+        let session = ProviderSession::new(handler, hub, self.config.token(), settings);
+        ctx.spawn_actor(session, Group::Sessions);
+    }
+
+    fn client_connected(
+        &mut self,
+        handler: SessionHandler<ClientProtocol>,
+        ctx: &mut Context<Self>,
+    ) {
+        log::info!("Client connected from: {}", handler.addr());
+        let hub = ctx.address().clone().into();
+        let settings = self.config.settings();
+        let session = ClientSession::new(handler, hub, self.config.token(), settings);
+        let address = ctx.spawn_actor(session, Group::Sessions);
+        self.clients.insert(address.id(), address.into());
+    }
+
+    async fn reply(
+        &mut self,
+        client: &IdOf<ClientSession>,
+        direct_id: ClientReqId,
+        data: ClientResponse,
+    ) {
+        if let Some(link) = self.clients.get_mut(client) {
+            let envelope = Envelope { direct_id, data };
+            if let Err(err) = link.forward(envelope).await {
+                log::debug!("Can't forward a response to {:?}: {}", client, err);
+            }
+        }
+    }
+}
+
+/// Converts a path of a provider to the path available for clients.
+fn full_path(name: &EntryId, path: Path) -> Path {
+    let entries: Vec<_> = std::iter::once(name.clone()).chain(path).collect();
+    Path::from(entries)
+}
+
+fn convert(name: &EntryId, data: ProviderToServer) -> Option<ClientResponse> {
+    match data {
+        ProviderToServer::Flow { mut description } => {
+            description.path = full_path(name, description.path);
+            Some(ClientResponse::Flow(description))
+        }
+        ProviderToServer::State { state } => Some(ClientResponse::State(state)),
+        ProviderToServer::Data { delta } => Some(ClientResponse::Delta(delta)),
+        ProviderToServer::EndStream => Some(ClientResponse::Done),
+        ProviderToServer::ActionDelivered => Some(ClientResponse::Delivered),
+        ProviderToServer::Error { reason } => Some(ClientResponse::Error(reason)),
+        ProviderToServer::Tagged { path, data } => {
+            convert(name, *data).map(|response| ClientResponse::Tagged {
+                path: full_path(name, path),
+                response: Box::new(response),
+            })
+        }
+        ProviderToServer::Declare { .. } | ProviderToServer::Ping | ProviderToServer::Pong => None,
+    }
+}
+
+#[async_trait]
+impl<T: Actor> StartedBy<T> for RillHub {
+    async fn handle(&mut self, ctx: &mut Context<Self>) -> Result<(), Error> {
+        ctx.termination_sequence(Group::iter().collect());
+
+        let server = HttpServer::new(self.config.addr());
+        let address = ctx.spawn_actor(server, Group::Server);
+        let mut server = HttpServerLink::from(address);
+        let route = WsRoute::new(ProviderLive, ctx.address().clone());
+        server.add_route(route).await?;
+        let route = WsRoute::new(ClientLive, ctx.address().clone());
+        server.add_route(route).await?;
+        self.server = Some(server);
+
+        if let Some(acceptor) = self.loopback.take() {
+            let listener = TransportListener::loopback(acceptor, ctx.address().clone());
+            ctx.spawn_task(listener, (), Group::Server);
+        }
+        if let Some(url) = self.config.providers_url() {
+            let listener = self.listener::<ProviderProtocol>(&url, ctx)?;
+            ctx.spawn_task(listener, (), Group::Server);
+        }
+        if let Some(url) = self.config.clients_url() {
+            let listener = self.listener::<ClientProtocol>(&url, ctx)?;
+            ctx.spawn_task(listener, (), Group::Server);
+        }
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl<T: Actor> InterruptedBy<T> for RillHub {
+    async fn handle(&mut self, ctx: &mut Context<Self>) -> Result<(), Error> {
+        ctx.shutdown();
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Eliminated<HttpServer> for RillHub {
+    async fn handle(
+        &mut self,
+        _id: IdOf<HttpServer>,
+        ctx: &mut Context<Self>,
+    ) -> Result<(), Error> {
+        ctx.shutdown();
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl InteractionHandler<link::GetServer> for RillHub {
+    async fn handle(
+        &mut self,
+        _msg: link::GetServer,
+        _ctx: &mut Context<Self>,
+    ) -> Result<HttpServerLink, Error> {
+        self.server
+            .clone()
+            .ok_or_else(|| Error::msg("The server is not started."))
+    }
+}
+
+#[async_trait]
+impl ActionHandler<WsReq<ProviderLive>> for RillHub {
+    async fn handle(
+        &mut self,
+        msg: WsReq<ProviderLive>,
+        ctx: &mut Context<Self>,
+    ) -> Result<(), Error> {
+        self.provider_connected(msg.stream.into(), ctx);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ActionHandler<WsReq<ClientLive>> for RillHub {
+    async fn handle(
+        &mut self,
+        msg: WsReq<ClientLive>,
+        ctx: &mut Context<Self>,
+    ) -> Result<(), Error> {
+        self.client_connected(msg.stream.into(), ctx);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ActionHandler<Accepted<ProviderProtocol>> for RillHub {
+    async fn handle(
+        &mut self,
+        msg: Accepted<ProviderProtocol>,
+        ctx: &mut Context<Self>,
+    ) -> Result<(), Error> {
+        self.provider_connected(msg.connection.into(), ctx);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ActionHandler<Accepted<ClientProtocol>> for RillHub {
+    async fn handle(
+        &mut self,
+        msg: Accepted<ClientProtocol>,
+        ctx: &mut Context<Self>,
+    ) -> Result<(), Error> {
+        self.client_connected(msg.connection.into(), ctx);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TaskEliminated<TransportListener<ProviderProtocol, Self>, ()> for RillHub {
+    async fn handle(
+        &mut self,
+        _id: IdOf<TransportListener<ProviderProtocol, Self>>,
+        _tag: (),
+        result: Result<(), TaskError>,
+        ctx: &mut Context<Self>,
+    ) -> Result<(), Error> {
+        if let Err(err) = result {
+            log::error!("Listener of providers failed: {}", err);
+            ctx.shutdown();
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TaskEliminated<TransportListener<ClientProtocol, Self>, ()> for RillHub {
+    async fn handle(
+        &mut self,
+        _id: IdOf<TransportListener<ClientProtocol, Self>>,
+        _tag: (),
+        result: Result<(), TaskError>,
+        ctx: &mut Context<Self>,
+    ) -> Result<(), Error> {
+        if let Err(err) = result {
+            log::error!("Listener of clients failed: {}", err);
+            ctx.shutdown();
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl InteractionHandler<link::ProviderDeclared> for RillHub {
+    async fn handle(
+        &mut self,
+        msg: link::ProviderDeclared,
+        _ctx: &mut Context<Self>,
+    ) -> Result<bool, Error> {
+        let address = msg.session;
+        let name = msg.name;
+        if self.providers.contains_key(&name) {
+            log::warn!("Provider {} is already connected", name);
+            Ok(false)
+        } else {
+            log::info!("Provider declared: {}", msg.description.path);
+            self.names.insert(address.id(), name.clone());
+            let provider = Provider {
+                session: address.into(),
+                routes: Routes::default(),
+                negotiated: msg.negotiated,
+            };
+            self.providers.insert(name, provider);
+            Ok(true)
+        }
+    }
+}
+
+#[async_trait]
+impl ActionHandler<link::ClientRequestAction> for RillHub {
+    async fn handle(
+        &mut self,
+        msg: link::ClientRequestAction,
+        _ctx: &mut Context<Self>,
+    ) -> Result<(), Error> {
+        let client = msg.session;
+        let direct_id = msg.envelope.direct_id;
+        let ClientRequest { path, request } = msg.envelope.data;
+        let (name, path) = path.split();
+        let provider = name.as_ref().and_then(|name| self.providers.get_mut(name));
+        let provider = match provider {
+            Some(provider) => provider,
+            None => {
+                let reason = match name {
+                    Some(name) => format!("Provider {} is not connected.", name),
+                    None => "The path is empty.".into(),
+                };
+                self.reply(&client, direct_id, ClientResponse::Error(reason))
+                    .await;
+                return Ok(());
+            }
+        };
+        let req_id = match &request {
+            RecorderRequest::ControlStream(FlowControl::StartStream) => {
+                provider
+                    .routes
+                    .add(client.clone(), direct_id, path.clone(), true)
+            }
+            RecorderRequest::ControlStream(FlowControl::StopStream) => {
+                match provider.routes.find(&client, direct_id) {
+                    Some(req_id) => req_id,
+                    None => {
+                        log::debug!("No stream {:?} to stop for {:?}", direct_id, client);
+                        return Ok(());
+                    }
+                }
+            }
+            RecorderRequest::Action(_) => {
+                provider
+                    .routes
+                    .add(client.clone(), direct_id, path.clone(), false)
+            }
+        };
+        // Providers of the first version don't confirm actions
+        let unconfirmed = matches!(request, RecorderRequest::Action(_))
+            && !provider.negotiated.has_feature(FEATURE_ACTIONS);
+        let envelope = Envelope {
+            direct_id: req_id,
+            data: ServerToProvider { path, request },
+        };
+        if let Err(err) = provider.session.forward(envelope).await {
+            log::debug!("Can't forward a request to a provider: {}", err);
+        } else if unconfirmed {
+            provider.routes.remove(req_id);
+            self.reply(&client, direct_id, ClientResponse::Unconfirmed)
+                .await;
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ActionHandler<link::ProviderResponse> for RillHub {
+    async fn handle(
+        &mut self,
+        msg: link::ProviderResponse,
+        _ctx: &mut Context<Self>,
+    ) -> Result<(), Error> {
+        let name = match self.names.get(&msg.session) {
+            Some(name) => name,
+            None => return Ok(()),
+        };
+        let provider = match self.providers.get_mut(name) {
+            Some(provider) => provider,
+            None => return Ok(()),
+        };
+        let last = matches!(
+            msg.data,
+            ProviderToServer::EndStream | ProviderToServer::Error { .. }
+        );
+        let response = match convert(name, msg.data) {
+            Some(response) => response,
+            None => return Ok(()),
+        };
+        for req_id in msg.direction.into_vec() {
+            let route = match provider.routes.get(req_id) {
+                Some(route) => route,
+                None => {
+                    log::trace!("No route for the response to {:?}", req_id);
+                    continue;
+                }
+            };
+            if let Some(link) = self.clients.get_mut(&route.client) {
+                let envelope = Envelope {
+                    direct_id: route.direct_id,
+                    data: response.clone(),
+                };
+                if let Err(err) = link.forward(envelope).await {
+                    log::debug!("Can't forward a response to {:?}: {}", route.client, err);
+                }
+            }
+            if last || !route.stream {
+                provider.routes.remove(req_id);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Eliminated<ProviderSession> for RillHub {
+    async fn handle(
+        &mut self,
+        id: IdOf<ProviderSession>,
+        _ctx: &mut Context<Self>,
+    ) -> Result<(), Error> {
+        if let Some(name) = self.names.remove(&id) {
+            log::info!("Provider disconnected: {}", name);
+            if let Some(mut provider) = self.providers.remove(&name) {
+                let routes: Vec<_> = provider.routes.drain().collect();
+                for route in routes {
+                    self.reply(&route.client, route.direct_id, ClientResponse::Done)
+                        .await;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl Eliminated<ClientSession> for RillHub {
+    async fn handle(
+        &mut self,
+        id: IdOf<ClientSession>,
+        _ctx: &mut Context<Self>,
+    ) -> Result<(), Error> {
+        self.clients.remove(&id);
+        for provider in self.providers.values_mut() {
+            for (req_id, path) in provider.routes.remove_client(&id) {
+                let request = RecorderRequest::ControlStream(FlowControl::StopStream);
+                let envelope = Envelope {
+                    direct_id: req_id,
+                    data: ServerToProvider { path, request },
+                };
+                provider.session.forward(envelope).await.ok();
+            }
+        }
+        Ok(())
+    }
+}
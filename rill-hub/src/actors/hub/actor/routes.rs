@@ -0,0 +1,88 @@
+use crate::actors::client_session::ClientSession;
+use meio::IdOf;
+use rill_protocol::io::client::ClientReqId;
+use rill_protocol::io::provider::{Path, ProviderReqId};
+use std::collections::HashMap;
+
+/// The origin of a request forwarded to a provider.
+#[derive(Debug)]
+pub struct Route {
+    pub client: IdOf<ClientSession>,
+    pub direct_id: ClientReqId,
+    /// The path of a flow of the provider.
+    pub path: Path,
+    /// Streams are kept till they end, other requests expect a single response.
+    pub stream: bool,
+}
+
+/// Maps ids of requests of a provider to ids of requests of clients.
+#[derive(Debug, Default)]
+pub struct Routes {
+    counter: usize,
+    routes: HashMap<ProviderReqId, Route>,
+    index: HashMap<(IdOf<ClientSession>, ClientReqId), ProviderReqId>,
+}
+
+impl Routes {
+    pub fn add(
+        &mut self,
+        client: IdOf<ClientSession>,
+        direct_id: ClientReqId,
+        path: Path,
+        stream: bool,
+    ) -> ProviderReqId {
+        self.counter += 1;
+        let req_id = ProviderReqId::from(self.counter);
+        self.index.insert((client.clone(), direct_id), req_id);
+        let route = Route {
+            client,
+            direct_id,
+            path,
+            stream,
+        };
+        self.routes.insert(req_id, route);
+        req_id
+    }
+
+    pub fn find(
+        &self,
+        client: &IdOf<ClientSession>,
+        direct_id: ClientReqId,
+    ) -> Option<ProviderReqId> {
+        self.index.get(&(client.clone(), direct_id)).copied()
+    }
+
+    pub fn get(&self, req_id: ProviderReqId) -> Option<&Route> {
+        self.routes.get(&req_id)
+    }
+
+    pub fn remove(&mut self, req_id: ProviderReqId) -> Option<Route> {
+        let route = self.routes.remove(&req_id)?;
+        self.index.remove(&(route.client.clone(), route.direct_id));
+        Some(route)
+    }
+
+    /// Removes all routes of a client and returns its active streams.
+    pub fn remove_client(&mut self, client: &IdOf<ClientSession>) -> Vec<(ProviderReqId, Path)> {
+        let req_ids: Vec<_> = self
+            .routes
+            .iter()
+            .filter(|(_, route)| &route.client == client)
+            .map(|(req_id, _)| *req_id)
+            .collect();
+        let mut streams = Vec::new();
+        for req_id in req_ids {
+            if let Some(route) = self.remove(req_id) {
+                if route.stream {
+                    streams.push((req_id, route.path));
+                }
+            }
+        }
+        streams
+    }
+
+    pub fn drain(&mut self) -> impl Iterator<Item = Route> + '_ {
+        self.index.clear();
+        self.routes.drain().map(|(_, route)| route)
+    }
+}
@@ -0,0 +1,110 @@
+use super::RillHub;
+use crate::actors::client_session::ClientSession;
+use crate::actors::provider_session::ProviderSession;
+use anyhow::Error;
+use derive_more::From;
+use meio::{Action, Address, IdOf, Interaction};
+use meio_connect::server::HttpServerLink;
+use rill_protocol::io::client::{ClientProtocol, ClientRequest};
+use rill_protocol::io::handshake::Negotiated;
+use rill_protocol::io::provider::{Description, EntryId, ProviderProtocol, ProviderToServer};
+use rill_protocol::io::transport::{Direction, Envelope};
+use std::net::SocketAddr;
+
+/// The public link to the hub.
+#[derive(Debug, Clone, From)]
+pub struct RillHubLink {
+    address: Address<RillHub>,
+}
+
+pub(super) struct GetServer;
+
+impl Interaction for GetServer {
+    type Output = HttpServerLink;
+}
+
+impl RillHubLink {
+    /// Waits for the address the hub listens to.
+    pub async fn wait_for_address(&self) -> Result<SocketAddr, Error> {
+        let server = self.address.interact(GetServer).recv().await?;
+        server.wait_for_address().recv().await
+    }
+}
+
+/// The link used by sessions to reach the hub.
+#[derive(Debug, Clone, From)]
+pub(crate) struct HubLink {
+    address: Address<RillHub>,
+}
+
+pub(super) struct ProviderDeclared {
+    pub session: Address<ProviderSession>,
+    pub name: EntryId,
+    pub description: Description,
+    pub negotiated: Negotiated,
+}
+
+impl Interaction for ProviderDeclared {
+    type Output = bool;
+}
+
+impl HubLink {
+    /// Registers the provider. Returns `false` if the name is taken.
+    pub async fn provider_declared(
+        &mut self,
+        session: Address<ProviderSession>,
+        name: EntryId,
+        description: Description,
+        negotiated: Negotiated,
+    ) -> Result<bool, Error> {
+        let msg = ProviderDeclared {
+            session,
+            name,
+            description,
+            negotiated,
+        };
+        self.address.interact(msg).recv().await
+    }
+}
+
+pub(super) struct ProviderResponse {
+    pub session: IdOf<ProviderSession>,
+    pub direction: Direction<ProviderProtocol>,
+    pub data: ProviderToServer,
+}
+
+impl Action for ProviderResponse {}
+
+impl HubLink {
+    pub async fn provider_response(
+        &mut self,
+        session: IdOf<ProviderSession>,
+        direction: Direction<ProviderProtocol>,
+        data: ProviderToServer,
+    ) -> Result<(), Error> {
+        let msg = ProviderResponse {
+            session,
+            direction,
+            data,
+        };
+        self.address.act(msg).await
+    }
+}
+
+pub(super) struct ClientRequestAction {
+    pub session: IdOf<ClientSession>,
+    pub envelope: Envelope<ClientProtocol, ClientRequest>,
+}
+
+impl Action for ClientRequestAction {}
+
+impl HubLink {
+    pub async fn client_request(
+        &mut self,
+        session: IdOf<ClientSession>,
+        envelope: Envelope<ClientProtocol, ClientRequest>,
+    ) -> Result<(), Error> {
+        let msg = ClientRequestAction { session, envelope };
+        self.address.act(msg).await
+    }
+}
@@ -0,0 +1,6 @@
+mod actor;
+pub use actor::RillHub;
+
+mod link;
+pub(crate) use link::HubLink;
+pub use link::RillHubLink;
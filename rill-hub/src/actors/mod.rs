@@ -0,0 +1,4 @@
+mod client_session;
+mod handler;
+pub mod hub;
+mod provider_session;
@@ -0,0 +1,249 @@
+use super::link;
+use crate::actors::handler::SessionHandler;
+use crate::actors::hub::{HubLink, RillHub};
+use anyhow::Error;
+use async_trait::async_trait;
+use meio::{
+    ActionHandler, Actor, Context, IdOf, InterruptedBy, StartedBy, TaskEliminated, TaskError,
+};
+use meio_connect::server::WsProcessor;
+use meio_connect::{TermReason, WsIncoming};
+use rill_protocol::io::auth::Credentials;
+use rill_protocol::io::client::AccessLevel;
+use rill_protocol::io::codec::{Frame, Settings};
+use rill_protocol::io::handshake::{Handshake, HandshakeError, Negotiated};
+use rill_protocol::io::provider::{
+    ProviderProtocol, ProviderServiceRequest, ProviderToServer, ServerToProvider,
+};
+use rill_protocol::io::transport::{ServiceEnvelope, WideEnvelope};
+use rill_transport::server::ConnectionProcessor;
+
+type ProviderMessage = WideEnvelope<ProviderProtocol, ProviderToServer>;
+
+type NodeMessage = ServiceEnvelope<ProviderProtocol, ServerToProvider, ProviderServiceRequest>;
+
+/// The session of a connected provider.
+pub struct ProviderSession {
+    handler: SessionHandler<ProviderProtocol>,
+    hub: HubLink,
+    token: Option<String>,
+    /// The settings of frames configured for the hub.
+    preferred: Settings,
+    /// The settings of frames agreed with the provider.
+    settings: Settings,
+    /// Features agreed with the provider.
+    negotiated: Negotiated,
+    /// `ReadyToWork` when the provider is verified and registered by the hub.
+    access_level: AccessLevel,
+}
+
+impl ProviderSession {
+    pub(crate) fn new(
+        handler: SessionHandler<ProviderProtocol>,
+        hub: HubLink,
+        token: Option<String>,
+        preferred: Settings,
+    ) -> Self {
+        Self {
+            handler,
+            hub,
+            token,
+            preferred,
+            settings: Settings::default(),
+            negotiated: Negotiated::v1(),
+            access_level: AccessLevel::SessionCreated,
+        }
+    }
+
+    fn is_authorized(&self, credentials: Option<&Credentials>) -> bool {
+        match (&self.token, credentials) {
+            (None, _) => true,
+            (Some(token), Some(credentials)) => credentials.verify(token),
+            (Some(_), None) => false,
+        }
+    }
+
+    fn send(&self, msg: NodeMessage) {
+        let frame = if self.negotiated.is_v1() {
+            // Providers of the first version get requests without service envelopes
+            match msg {
+                ServiceEnvelope::Envelope(envelope) => {
+                    self.settings.encode(envelope).map(Frame::Encoded)
+                }
+                ServiceEnvelope::Service(request) => {
+                    log::debug!("Provider can't get the service request: {:?}", request);
+                    return;
+                }
+            }
+        } else {
+            self.settings.frame(msg)
+        };
+        match frame {
+            Ok(frame) => self.handler.send(frame),
+            Err(err) => log::error!("Can't encode a message to the provider: {}", err),
+        }
+    }
+
+    fn send_service(&self, request: ProviderServiceRequest) {
+        self.send(ServiceEnvelope::Service(request));
+    }
+
+    fn set_access_level(&mut self, level: AccessLevel) {
+        self.access_level = level;
+        self.send_service(ProviderServiceRequest::AccessLevel(level));
+    }
+
+    /// Answers to the handshake of the provider.
+    ///
+    /// Providers without a handshake talk the first version of the protocol.
+    fn negotiate(&mut self, handshake: Option<&Handshake>) -> Result<(), HandshakeError> {
+        let negotiated = handshake.map(|handshake| Handshake::default().negotiate(handshake));
+        match negotiated {
+            Some(Ok(negotiated)) => {
+                log::debug!("Protocol negotiated: {:?}", negotiated);
+                // The provider can decode frames of any agreed codec and compression
+                self.settings = negotiated.settings(&self.preferred);
+                self.negotiated = negotiated.clone();
+                self.send_service(ProviderServiceRequest::Accepted(negotiated));
+            }
+            Some(Err(err)) => {
+                let reason = err.to_string();
+                self.send_service(ProviderServiceRequest::Incompatible { reason });
+                return Err(err);
+            }
+            None => {}
+        }
+        Ok(())
+    }
+}
+
+impl Actor for ProviderSession {
+    type GroupBy = ();
+
+    fn name(&self) -> String {
+        format!("ProviderSession({})", self.handler.addr())
+    }
+}
+
+#[async_trait]
+impl StartedBy<RillHub> for ProviderSession {
+    async fn handle(&mut self, ctx: &mut Context<Self>) -> Result<(), Error> {
+        self.handler.start(ctx);
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl InterruptedBy<RillHub> for ProviderSession {
+    async fn handle(&mut self, ctx: &mut Context<Self>) -> Result<(), Error> {
+        ctx.shutdown();
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TaskEliminated<WsProcessor<ProviderProtocol, Self>, ()> for ProviderSession {
+    async fn handle(
+        &mut self,
+        _id: IdOf<WsProcessor<ProviderProtocol, Self>>,
+        _tag: (),
+        _result: Result<TermReason, TaskError>,
+        ctx: &mut Context<Self>,
+    ) -> Result<(), Error> {
+        ctx.shutdown();
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl TaskEliminated<ConnectionProcessor<ProviderProtocol, Self>, ()> for ProviderSession {
+    async fn handle(
+        &mut self,
+        _id: IdOf<ConnectionProcessor<ProviderProtocol, Self>>,
+        _tag: (),
+        _result: Result<(), TaskError>,
+        ctx: &mut Context<Self>,
+    ) -> Result<(), Error> {
+        ctx.shutdown();
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ActionHandler<WsIncoming<Frame<ProviderMessage>>> for ProviderSession {
+    async fn handle(
+        &mut self,
+        msg: WsIncoming<Frame<ProviderMessage>>,
+        ctx: &mut Context<Self>,
+    ) -> Result<(), Error> {
+        log::trace!("Incoming from provider: {:?}", msg);
+        let envelope = msg.0.into_message()?;
+        match envelope.data {
+            ProviderToServer::Declare {
+                description,
+                credentials,
+                handshake,
+            } => {
+                if self.access_level != AccessLevel::SessionCreated {
+                    log::warn!("Provider {} declared twice", description.path);
+                } else if let Err(err) = self.negotiate(handshake.as_ref()) {
+                    log::warn!("Provider {} rejected: {}", description.path, err);
+                    ctx.shutdown();
+                } else if !self.is_authorized(credentials.as_ref()) {
+                    log::warn!(
+                        "Provider {} rejected: invalid credentials",
+                        description.path
+                    );
+                    let reason = "Invalid credentials.".into();
+                    self.send_service(ProviderServiceRequest::AccessDenied { reason });
+                    ctx.shutdown();
+                } else if let (Some(name), _) = description.path.split() {
+                    let address = ctx.address().clone();
+                    let negotiated = self.negotiated.clone();
+                    let declared = self
+                        .hub
+                        .provider_declared(address, name.clone(), description, negotiated)
+                        .await?;
+                    if declared {
+                        self.set_access_level(AccessLevel::ReadyToWork);
+                    } else {
+                        self.send_service(ProviderServiceRequest::NameTaken { name });
+                        ctx.shutdown();
+                    }
+                } else {
+                    log::warn!("Provider declared an empty path");
+                    ctx.shutdown();
+                }
+            }
+            ProviderToServer::Ping => {
+                self.send_service(ProviderServiceRequest::Pong);
+            }
+            ProviderToServer::Pong => {
+                // The hub doesn't ping providers.
+            }
+            data => {
+                if self.access_level == AccessLevel::ReadyToWork {
+                    let id = ctx.address().id();
+                    self.hub
+                        .provider_response(id, envelope.direction, data)
+                        .await?;
+                } else {
+                    log::warn!("Response from an undeclared provider: {:?}", data);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ActionHandler<link::ForwardRequest> for ProviderSession {
+    async fn handle(
+        &mut self,
+        msg: link::ForwardRequest,
+        _ctx: &mut Context<Self>,
+    ) -> Result<(), Error> {
+        self.send(ServiceEnvelope::Envelope(msg.envelope));
+        Ok(())
+    }
+}
@@ -0,0 +1,27 @@
+use super::ProviderSession;
+use anyhow::Error;
+use derive_more::From;
+use meio::{Action, Address};
+use rill_protocol::io::provider::{ProviderProtocol, ServerToProvider};
+use rill_protocol::io::transport::Envelope;
+
+#[derive(Debug, Clone, From)]
+pub(crate) struct ProviderSessionLink {
+    address: Address<ProviderSession>,
+}
+
+pub(super) struct ForwardRequest {
+    pub envelope: Envelope<ProviderProtocol, ServerToProvider>,
+}
+
+impl Action for ForwardRequest {}
+
+impl ProviderSessionLink {
+    pub async fn forward(
+        &mut self,
+        envelope: Envelope<ProviderProtocol, ServerToProvider>,
+    ) -> Result<(), Error> {
+        let msg = ForwardRequest { envelope };
+        self.address.act(msg).await
+    }
+}
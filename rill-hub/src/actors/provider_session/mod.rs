@@ -0,0 +1,5 @@
+mod actor;
+pub use actor::ProviderSession;
+
+mod link;
+pub(crate) use link::ProviderSessionLink;
@@ -0,0 +1,134 @@
+//! Configuration of the hub
+
+use rill_protocol::compression::{self, Compression};
+use rill_protocol::config::ConfigPatch;
+use rill_protocol::encoding::CodecKind;
+use rill_protocol::io::codec::Settings;
+use rill_transport::TlsServerConfig;
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+
+/// The address to listen to for providers and clients.
+pub static ADDR: ConfigPatch<SocketAddr> = ConfigPatch::new("RILLRATE_HUB_ADDR");
+
+/// An extra endpoint to listen to for providers.
+pub static PROVIDERS_URL: ConfigPatch<String> = ConfigPatch::new("RILLRATE_HUB_PROVIDERS_URL");
+
+/// An extra endpoint to listen to for clients.
+pub static CLIENTS_URL: ConfigPatch<String> = ConfigPatch::new("RILLRATE_HUB_CLIENTS_URL");
+
+/// The path to a PEM certificate chain of `wss://` endpoints.
+pub static TLS_CERT: ConfigPatch<PathBuf> = ConfigPatch::new("RILLRATE_HUB_TLS_CERT");
+
+/// The path to a PEM private key of `wss://` endpoints.
+pub static TLS_KEY: ConfigPatch<PathBuf> = ConfigPatch::new("RILLRATE_HUB_TLS_KEY");
+
+/// The path to a PEM bundle of CAs to authenticate peers of `wss://` endpoints.
+pub static TLS_CA: ConfigPatch<PathBuf> = ConfigPatch::new("RILLRATE_HUB_TLS_CA");
+
+/// The token that providers and clients have to send to be authorized.
+pub static TOKEN: ConfigPatch<String> = ConfigPatch::new("RILLRATE_HUB_TOKEN");
+
+/// The codec of frames sent by the hub.
+pub static CODEC: ConfigPatch<CodecKind> = ConfigPatch::new("RILLRATE_HUB_CODEC");
+
+/// The compression of large frames sent by the hub.
+pub static COMPRESSION: ConfigPatch<Compression> = ConfigPatch::new("RILLRATE_HUB_COMPRESSION");
+
+/// Hub configuration
+#[derive(Deserialize, Debug, Clone, Default)]
+pub struct HubConfig {
+    /// The address of the server
+    #[serde(default)]
+    pub addr: Option<SocketAddr>,
+    /// An extra endpoint for providers (`tcp://host:port`, `unix:///path` or `wss://host:port`)
+    #[serde(default)]
+    pub providers_url: Option<String>,
+    /// An extra endpoint for clients (`tcp://host:port`, `unix:///path` or `wss://host:port`)
+    #[serde(default)]
+    pub clients_url: Option<String>,
+    /// The path to a PEM certificate chain of `wss://` endpoints
+    #[serde(default)]
+    pub tls_cert: Option<PathBuf>,
+    /// The path to a PEM private key of `wss://` endpoints
+    #[serde(default)]
+    pub tls_key: Option<PathBuf>,
+    /// The path to a PEM bundle of CAs of peers
+    ///
+    /// If it's set, peers of `wss://` endpoints have to authenticate with certificates.
+    #[serde(default)]
+    pub tls_ca: Option<PathBuf>,
+    /// The token to authorize peers
+    #[serde(default)]
+    pub token: Option<String>,
+    /// The codec of frames sent by the hub (`flexbuffers` by default)
+    ///
+    /// Peers that can't decode the codec get frames of the default one.
+    #[serde(default)]
+    pub codec: Option<CodecKind>,
+    /// The compression of large frames sent by the hub
+    ///
+    /// Peers that can't decompress frames get them uncompressed.
+    #[serde(default)]
+    pub compression: Option<Compression>,
+    /// The minimal size of a frame to compress in bytes
+    #[serde(default)]
+    pub compression_threshold: Option<usize>,
+}
+
+impl HubConfig {
+    /// The address of the server
+    pub fn addr(&self) -> SocketAddr {
+        ADDR.get(|| self.addr, || ([127, 0, 0, 1], 1636).into())
+    }
+
+    /// An extra endpoint for providers
+    pub fn providers_url(&self) -> Option<String> {
+        PROVIDERS_URL.get_optional(|| self.providers_url.clone())
+    }
+
+    /// An extra endpoint for clients
+    pub fn clients_url(&self) -> Option<String> {
+        CLIENTS_URL.get_optional(|| self.clients_url.clone())
+    }
+
+    /// TLS settings of `wss://` endpoints if both a certificate and a key are set
+    pub fn tls(&self) -> Option<TlsServerConfig> {
+        let cert = TLS_CERT.get_optional(|| self.tls_cert.clone())?;
+        let key = TLS_KEY.get_optional(|| self.tls_key.clone())?;
+        let ca = TLS_CA.get_optional(|| self.tls_ca.clone());
+        Some(TlsServerConfig { cert, key, ca })
+    }
+
+    /// The token to authorize peers
+    pub fn token(&self) -> Option<String> {
+        TOKEN.get_optional(|| self.token.clone())
+    }
+
+    /// The codec of frames sent by the hub
+    pub fn codec(&self) -> CodecKind {
+        CODEC.get(|| self.codec, || CodecKind::Flexbuffers)
+    }
+
+    /// The preferred settings of frames sent by the hub
+    pub fn settings(&self) -> Settings {
+        Settings {
+            codec: self.codec(),
+            compression: self.compression(),
+            threshold: self.compression_threshold(),
+            blobs: true,
+        }
+    }
+
+    /// The compression of large frames sent by the hub
+    pub fn compression(&self) -> Option<Compression> {
+        COMPRESSION.get_optional(|| self.compression)
+    }
+
+    /// The minimal size of a frame to compress
+    pub fn compression_threshold(&self) -> usize {
+        self.compression_threshold
+            .unwrap_or(compression::DEFAULT_THRESHOLD)
+    }
+}
@@ -0,0 +1,9 @@
+//! Rill hub crate.
+
+#![warn(missing_docs)]
+
+mod actors;
+pub mod config;
+
+pub use actors::hub::{RillHub, RillHubLink};
+pub use config::HubConfig;
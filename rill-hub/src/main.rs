@@ -0,0 +1,11 @@
+use anyhow::Error;
+use meio::System;
+use rill_hub::{HubConfig, RillHub};
+
+#[tokio::main]
+async fn main() -> Result<(), Error> {
+    env_logger::try_init()?;
+    let hub = RillHub::new(HubConfig::default());
+    System::spawn_and_wait(hub).await;
+    Ok(())
+}
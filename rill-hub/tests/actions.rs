@@ -0,0 +1,74 @@
+mod common;
+
+use anyhow::Error;
+use common::{free_port, WAIT};
+use futures::StreamExt;
+use meio::System;
+use rill_client::{ClientConfig, QueryError, RillClient, RillClientLink};
+use rill_engine::loopback;
+use rill_hub::{HubConfig, RillHub};
+use rill_protocol::flow::meta::alert::AlertState;
+use rill_protocol::io::codec::{self, Frame};
+use rill_protocol::io::provider::{
+    ProviderProtocol, RecorderAction, RecorderRequest, ServerToProvider,
+};
+use rill_protocol::io::transport::Envelope;
+use std::time::Duration;
+use tokio::time::{interval, timeout};
+
+#[tokio::test]
+async fn actions_of_providers_without_confirmations() -> Result<(), Error> {
+    let (loopback, acceptor) = loopback::loopback();
+    let clients_url = format!("tcp://127.0.0.1:{}", free_port("127.0.0.1")?);
+    let config = HubConfig {
+        addr: Some(([127, 0, 0, 1], 0).into()),
+        clients_url: Some(clients_url.clone()),
+        ..HubConfig::default()
+    };
+    let hub = System::spawn(RillHub::new(config).with_loopback(acceptor));
+
+    // A provider of the first version declares itself without a handshake
+    let (_to_node, mut from_node) = common::declare(&loopback, "legacy", None, None)?;
+
+    let config = ClientConfig {
+        url: Some(clients_url),
+        ..ClientConfig::default()
+    };
+    let client = System::spawn(RillClient::from_config(config));
+    let mut link = RillClientLink::from(client.clone());
+    link.wait_ready().await.recv().await?;
+
+    // The hub forwards the action, but can't confirm it.
+    // The provider is registered by the hub asynchronously.
+    let mut ticks = interval(Duration::from_millis(50));
+    let result = timeout(WAIT, async {
+        loop {
+            ticks.tick().await;
+            let path = "legacy.alerts".parse()?;
+            match link.act::<AlertState>(path, (), WAIT).await {
+                Err(QueryError::Server(_)) => {}
+                result => return Ok::<_, Error>(result),
+            }
+        }
+    })
+    .await??;
+    assert!(
+        matches!(result, Err(QueryError::Unconfirmed)),
+        "{:?}",
+        result
+    );
+    // Providers of the first version get requests without service envelopes
+    let data = match timeout(WAIT, from_node.next()).await? {
+        Some(Frame::Encoded(data)) => data,
+        other => panic!("Unexpected frame: {:?}", other),
+    };
+    let request: Envelope<ProviderProtocol, ServerToProvider> = codec::decode(&data)?;
+    assert!(matches!(
+        request.data.request,
+        RecorderRequest::Action(RecorderAction::DoAction(_))
+    ));
+
+    System::interrupt(&client)?;
+    System::interrupt(&hub)?;
+    Ok(())
+}
@@ -0,0 +1,51 @@
+#![cfg(all(feature = "bincode", feature = "msgpack", feature = "json"))]
+
+mod common;
+
+use anyhow::Error;
+use common::{free_port, reconnect};
+use meio::System;
+use rill_client::ClientConfig;
+use rill_engine::EngineConfig;
+use rill_hub::{HubConfig, RillHub};
+use rill_protocol::encoding::{self, CodecKind};
+use rill_protocol::flow::core::Flow;
+use rill_protocol::flow::meta::alert::{AlertEvent, AlertState};
+use rill_protocol::io::provider::EntryId;
+
+#[tokio::test]
+async fn payloads_of_a_provider_reach_a_client_with_another_codec() -> Result<(), Error> {
+    // Payloads don't depend on codecs of frames
+    let event = AlertEvent {
+        msg: "mixed".into(),
+    };
+    let packed = AlertState::pack_event(&event)?;
+    assert_eq!(packed.as_bytes(), &encoding::to_vec(&event)?[..]);
+
+    let providers_url = format!("tcp://127.0.0.1:{}", free_port("127.0.0.1")?);
+    let clients_url = format!("tcp://127.0.0.1:{}", free_port("127.0.0.1")?);
+    let config = HubConfig {
+        addr: Some(([127, 0, 0, 1], 0).into()),
+        providers_url: Some(providers_url.clone()),
+        clients_url: Some(clients_url.clone()),
+        codec: Some(CodecKind::Json),
+        ..HubConfig::default()
+    };
+    let hub = System::spawn(RillHub::new(config));
+
+    let mut engine = EngineConfig::new("test".into());
+    engine.name = Some(EntryId::from_static("mixed")?);
+    engine.node = Some(providers_url);
+    engine.reconnect = reconnect();
+    engine.codec = Some(CodecKind::Bincode);
+    let client = ClientConfig {
+        url: Some(clients_url),
+        reconnect: reconnect(),
+        codec: Some(CodecKind::MessagePack),
+        ..ClientConfig::default()
+    };
+    common::alerts_reach_a_client(engine, client).await?;
+
+    System::interrupt(&hub)?;
+    Ok(())
+}
@@ -0,0 +1,150 @@
+//! Fixtures shared by tests of the hub.
+//!
+//! Every test binary uses only a part of them.
+#![allow(dead_code)]
+
+use anyhow::Error;
+use futures::StreamExt;
+use meio::System;
+use rill_client::{ClientConfig, FlowUpdate, RillClient, RillClientLink};
+use rill_engine::loopback::{Loopback, ProviderMessage};
+use rill_engine::tracers::meta::AlertTracer;
+use rill_engine::{EngineConfig, RillEngine};
+use rill_protocol::flow::meta::alert::AlertState;
+use rill_protocol::io::auth::Credentials;
+use rill_protocol::io::codec::Frame;
+use rill_protocol::io::handshake::Handshake;
+use rill_protocol::io::provider::{
+    Description, EntryId, ProviderProtocol, ProviderToServer, StreamType,
+};
+use rill_protocol::io::transport::{Direction, WideEnvelope};
+use rill_transport::loopback::{FromNode, ToNode};
+use rill_transport::{ConnectionStatus, ReconnectPolicy};
+use std::net::TcpListener;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::sync::watch;
+use tokio::time::{interval, timeout};
+
+pub const WAIT: Duration = Duration::from_secs(5);
+
+/// How many events a client has to receive.
+const EVENTS: usize = 3;
+
+pub fn free_port(host: &str) -> Result<u16, Error> {
+    let listener = TcpListener::bind((host, 0))?;
+    Ok(listener.local_addr()?.port())
+}
+
+pub fn reconnect() -> ReconnectPolicy {
+    ReconnectPolicy {
+        initial_delay: Duration::from_millis(50),
+        ..ReconnectPolicy::default()
+    }
+}
+
+pub fn cert(name: &str) -> Option<PathBuf> {
+    Some(
+        [env!("CARGO_MANIFEST_DIR"), "tests", "certs", name]
+            .iter()
+            .collect(),
+    )
+}
+
+/// Waits for the `Connected` status.
+pub async fn connected(status: &mut watch::Receiver<ConnectionStatus>) -> Result<(), Error> {
+    timeout(WAIT, async {
+        while *status.borrow() != ConnectionStatus::Connected {
+            status.changed().await?;
+        }
+        Ok(())
+    })
+    .await?
+}
+
+/// Declares a provider without an engine.
+pub fn declare(
+    loopback: &Loopback,
+    name: &'static str,
+    credentials: Option<Credentials>,
+    handshake: Option<Handshake>,
+) -> Result<(ToNode<ProviderProtocol>, FromNode<ProviderProtocol>), Error> {
+    let (to_node, from_node) = loopback.connect()?;
+    let msg = ProviderToServer::Declare {
+        description: Description {
+            path: EntryId::from_static(name)?.into(),
+            info: "".into(),
+            stream_type: StreamType::from("test"),
+        },
+        credentials,
+        handshake,
+    };
+    let envelope: ProviderMessage = WideEnvelope {
+        direction: Direction::broadcast(),
+        data: msg,
+    };
+    to_node.unbounded_send(Frame::Message(envelope))?;
+    Ok((to_node, from_node))
+}
+
+/// Connects a provider and a client to endpoints of a running hub
+/// and checks that alerts of the provider reach the client intact.
+///
+/// The provider takes its name from the `engine` config.
+pub async fn alerts_reach_a_client(
+    engine: EngineConfig,
+    client: ClientConfig,
+) -> Result<(), Error> {
+    let name = engine
+        .name
+        .clone()
+        .ok_or_else(|| Error::msg("The provider has no name."))?;
+    let engine = RillEngine::new(engine);
+    let mut status = engine.status();
+    let engine = System::spawn(engine);
+    connected(&mut status).await?;
+    let tracer = AlertTracer::new("alerts".parse()?)?;
+
+    let client = System::spawn(RillClient::from_config(client));
+    let mut link = RillClientLink::from(client.clone());
+    timeout(WAIT, link.wait_ready().await.recv()).await??;
+    let path = format!("{}.alerts", name).parse()?;
+    let mut subscription = timeout(WAIT, link.subscribe::<AlertState>(path)).await??;
+
+    // Events raised before the stream started are not delivered
+    let mut ticks = interval(Duration::from_millis(50));
+    let mut raised = 0;
+    let mut received = Vec::new();
+    timeout(WAIT, async {
+        while received.len() < EVENTS {
+            tokio::select! {
+                _ = ticks.tick() => {
+                    tracer.alert(format!("{}-{}", name, raised));
+                    raised += 1;
+                }
+                update = subscription.next() => {
+                    match update {
+                        Some(FlowUpdate::Event { event, .. }) => received.push(event.msg),
+                        Some(_) => {}
+                        None => return Err(Error::msg("The stream closed.")),
+                    }
+                }
+            }
+        }
+        Ok(())
+    })
+    .await??;
+    // The events are delivered in order and without gaps
+    let first: usize = received[0]
+        .strip_prefix(&format!("{}-", name))
+        .ok_or_else(|| Error::msg(format!("Unexpected event: {}", received[0])))?
+        .parse()?;
+    let expected: Vec<_> = (first..first + EVENTS)
+        .map(|n| format!("{}-{}", name, n))
+        .collect();
+    assert_eq!(received, expected);
+
+    System::interrupt(&client)?;
+    System::interrupt(&engine)?;
+    Ok(())
+}
@@ -0,0 +1,73 @@
+mod common;
+
+use anyhow::Error;
+use common::{reconnect, WAIT};
+use futures::StreamExt;
+use meio::System;
+use rill_engine::{loopback, EngineConfig, RillEngine};
+use rill_hub::{HubConfig, RillHub};
+use rill_protocol::io::client::AccessLevel;
+use rill_protocol::io::handshake::Handshake;
+use rill_protocol::io::provider::{EntryId, ProviderServiceRequest};
+use rill_protocol::io::transport::ServiceEnvelope;
+use rill_transport::{ConnectionStatus, ReconnectPolicy};
+use std::time::Duration;
+use tokio::time::{sleep, timeout};
+
+#[tokio::test]
+async fn providers_with_taken_names_wait() -> Result<(), Error> {
+    let (loopback, acceptor) = loopback::loopback();
+    let config = HubConfig {
+        addr: Some(([127, 0, 0, 1], 0).into()),
+        ..HubConfig::default()
+    };
+    let hub = System::spawn(RillHub::new(config).with_loopback(acceptor));
+
+    // Another provider took the name
+    let (to_node, mut from_node) =
+        common::declare(&loopback, "dup", None, Some(Handshake::default()))?;
+    timeout(WAIT, async {
+        while let Some(msg) = from_node.next().await {
+            if let ServiceEnvelope::Service(ProviderServiceRequest::AccessLevel(
+                AccessLevel::ReadyToWork,
+            )) = msg.into_message()?
+            {
+                return Ok(());
+            }
+        }
+        Err(Error::msg("The hub closed the connection."))
+    })
+    .await??;
+
+    let mut config = EngineConfig::new("test".into());
+    config.name = Some(EntryId::from_static("dup")?);
+    config.loopback = Some(loopback);
+    config.reconnect = ReconnectPolicy {
+        max_delay: Duration::from_millis(100),
+        ..reconnect()
+    };
+    let engine = RillEngine::new(config);
+    let mut status = engine.status();
+    let engine = System::spawn(engine);
+
+    // The provider retries until the name is released
+    timeout(WAIT, async {
+        while !matches!(*status.borrow(), ConnectionStatus::Backoff { attempt, .. } if attempt >= 2)
+        {
+            assert_ne!(*status.borrow(), ConnectionStatus::Connected);
+            status.changed().await?;
+        }
+        Ok::<_, Error>(())
+    })
+    .await??;
+    sleep(Duration::from_millis(100)).await;
+    assert_ne!(*status.borrow(), ConnectionStatus::Connected);
+
+    drop(to_node);
+    drop(from_node);
+    common::connected(&mut status).await?;
+
+    System::interrupt(&engine)?;
+    System::interrupt(&hub)?;
+    Ok(())
+}
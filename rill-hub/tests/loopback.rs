@@ -0,0 +1,31 @@
+mod common;
+
+use anyhow::Error;
+use meio::System;
+use rill_client::ClientConfig;
+use rill_engine::{loopback, EngineConfig};
+use rill_hub::{HubConfig, RillHub, RillHubLink};
+use rill_protocol::io::provider::EntryId;
+
+#[tokio::test]
+async fn provider_connected_in_process() -> Result<(), Error> {
+    let (loopback, acceptor) = loopback::loopback();
+    let config = HubConfig {
+        addr: Some(([127, 0, 0, 1], 0).into()),
+        ..HubConfig::default()
+    };
+    let hub = System::spawn(RillHub::new(config).with_loopback(acceptor));
+    let addr = RillHubLink::from(hub.clone()).wait_for_address().await?;
+
+    let mut engine = EngineConfig::new("test".into());
+    engine.name = Some(EntryId::from_static("loopback")?);
+    engine.loopback = Some(loopback);
+    let client = ClientConfig {
+        url: Some(format!("ws://{}/live/client", addr)),
+        ..ClientConfig::default()
+    };
+    common::alerts_reach_a_client(engine, client).await?;
+
+    System::interrupt(&hub)?;
+    Ok(())
+}
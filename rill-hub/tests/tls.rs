@@ -0,0 +1,50 @@
+mod common;
+
+use anyhow::Error;
+use common::{cert, free_port, reconnect};
+use meio::System;
+use rill_client::ClientConfig;
+use rill_engine::EngineConfig;
+use rill_hub::{HubConfig, RillHub};
+use rill_protocol::io::provider::EntryId;
+use rill_transport::TlsConfig;
+
+#[tokio::test]
+async fn wss_endpoints_with_client_certificates() -> Result<(), Error> {
+    let providers_url = format!("wss://127.0.0.1:{}/live/provider", free_port("127.0.0.1")?);
+    let clients_url = format!("wss://[::1]:{}/live/client", free_port("::1")?);
+    let config = HubConfig {
+        addr: Some(([127, 0, 0, 1], 0).into()),
+        providers_url: Some(providers_url.clone()),
+        clients_url: Some(clients_url.clone()),
+        tls_cert: cert("server.pem"),
+        tls_key: cert("server.key"),
+        tls_ca: cert("ca.pem"),
+        ..HubConfig::default()
+    };
+    let hub = System::spawn(RillHub::new(config));
+
+    let mut engine = EngineConfig::new("test".into());
+    engine.name = Some(EntryId::from_static("secure")?);
+    engine.node = Some(providers_url);
+    engine.reconnect = reconnect();
+    engine.tls_ca = cert("ca.pem");
+    engine.tls_cert = cert("client.pem");
+    engine.tls_key = cert("client.key");
+    engine.tls_server_name = Some("localhost".into());
+    let client = ClientConfig {
+        url: Some(clients_url),
+        tls: TlsConfig {
+            ca: cert("ca.pem"),
+            cert: cert("client.pem"),
+            key: cert("client.key"),
+            server_name: Some("localhost".into()),
+        },
+        reconnect: reconnect(),
+        ..ClientConfig::default()
+    };
+    common::alerts_reach_a_client(engine, client).await?;
+
+    System::interrupt(&hub)?;
+    Ok(())
+}
@@ -0,0 +1,95 @@
+mod common;
+
+use anyhow::Error;
+use common::WAIT;
+use futures::StreamExt;
+use meio::System;
+use rill_engine::loopback::{Loopback, NodeMessage};
+use rill_engine::{loopback, EngineConfig, RillEngine};
+use rill_hub::{HubConfig, RillHub};
+use rill_protocol::io::auth::Credentials;
+use rill_protocol::io::client::AccessLevel;
+use rill_protocol::io::handshake::Handshake;
+use rill_protocol::io::provider::{EntryId, ProviderServiceRequest};
+use rill_protocol::io::transport::ServiceEnvelope;
+use rill_transport::ConnectionStatus;
+use tokio::time::timeout;
+
+/// Declares a provider without an engine and returns the answer of the hub.
+async fn declare(
+    loopback: &Loopback,
+    credentials: Option<Credentials>,
+) -> Result<ProviderServiceRequest, Error> {
+    let (_to_node, mut from_node) =
+        common::declare(loopback, "raw", credentials, Some(Handshake::default()))?;
+    loop {
+        let msg = timeout(WAIT, from_node.next())
+            .await?
+            .ok_or_else(|| Error::msg("The hub closed the connection."))?;
+        match msg.into_message()? {
+            ServiceEnvelope::Service(ProviderServiceRequest::Accepted(_)) => {}
+            ServiceEnvelope::Service(answer) => return Ok(answer),
+            other @ NodeMessage::Envelope(_) => {
+                return Err(Error::msg(format!("Unexpected message: {:?}", other)));
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn providers_with_invalid_tokens_are_rejected() -> Result<(), Error> {
+    let (loopback, acceptor) = loopback::loopback();
+    let config = HubConfig {
+        addr: Some(([127, 0, 0, 1], 0).into()),
+        token: Some("secret".into()),
+        ..HubConfig::default()
+    };
+    let hub = System::spawn(RillHub::new(config).with_loopback(acceptor));
+
+    let answer = declare(&loopback, None).await?;
+    assert!(
+        matches!(answer, ProviderServiceRequest::AccessDenied { .. }),
+        "{:?}",
+        answer
+    );
+    let answer = declare(&loopback, Some(Credentials::token("secret"))).await?;
+    assert!(
+        matches!(
+            answer,
+            ProviderServiceRequest::AccessLevel(AccessLevel::ReadyToWork)
+        ),
+        "{:?}",
+        answer
+    );
+
+    let mut config = EngineConfig::new("test".into());
+    config.name = Some(EntryId::from_static("rejected")?);
+    config.token = Some("wrong".into());
+    config.loopback = Some(loopback);
+    let engine = RillEngine::new(config);
+    let mut status = engine.status();
+    let engine = System::spawn(engine);
+    let status = timeout(WAIT, async {
+        loop {
+            let current = status.borrow().clone();
+            match current {
+                ConnectionStatus::Connected => {
+                    return Err(Error::msg("The provider with a wrong token connected."));
+                }
+                ConnectionStatus::Rejected { .. } => return Ok(current),
+                _ => status.changed().await?,
+            }
+        }
+    })
+    .await??;
+    assert_eq!(
+        status,
+        ConnectionStatus::Rejected {
+            reason: "Invalid credentials.".into()
+        }
+    );
+
+    System::interrupt(&engine)?;
+    System::interrupt(&hub)?;
+    Ok(())
+}
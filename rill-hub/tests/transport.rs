@@ -0,0 +1,39 @@
+mod common;
+
+use anyhow::Error;
+use common::{free_port, reconnect};
+use meio::System;
+use rill_client::ClientConfig;
+use rill_engine::EngineConfig;
+use rill_hub::{HubConfig, RillHub};
+use rill_protocol::io::provider::EntryId;
+
+#[cfg(unix)]
+#[tokio::test]
+async fn framed_tcp_and_unix_endpoints() -> Result<(), Error> {
+    let providers_url = format!("tcp://127.0.0.1:{}", free_port("127.0.0.1")?);
+    let socket = std::env::temp_dir().join(format!("rill-hub-{}.sock", std::process::id()));
+    let clients_url = format!("unix://{}", socket.display());
+    let config = HubConfig {
+        addr: Some(([127, 0, 0, 1], 0).into()),
+        providers_url: Some(providers_url.clone()),
+        clients_url: Some(clients_url.clone()),
+        ..HubConfig::default()
+    };
+    let hub = System::spawn(RillHub::new(config));
+
+    let mut engine = EngineConfig::new("test".into());
+    engine.name = Some(EntryId::from_static("framed")?);
+    engine.node = Some(providers_url);
+    engine.reconnect = reconnect();
+    let client = ClientConfig {
+        url: Some(clients_url),
+        reconnect: reconnect(),
+        ..ClientConfig::default()
+    };
+    common::alerts_reach_a_client(engine, client).await?;
+
+    System::interrupt(&hub)?;
+    std::fs::remove_file(socket).ok();
+    Ok(())
+}
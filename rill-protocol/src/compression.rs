@@ -0,0 +1,232 @@
+//! Compression of frames.
+//!
+//! Compression is enabled by the `zstd` and `lz4` features.
+//!
+//! Frames larger than the threshold of a connection are compressed
+//! by the algorithm negotiated with the peer. A compressed frame has a trailing tag byte
+//! of the algorithm that never ends a frame of any codec. Frames that
+//! don't get smaller are sent as is.
+
+use anyhow::Error;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// Frames smaller than this are not compressed by default.
+pub const DEFAULT_THRESHOLD: usize = 4 * 1024;
+
+/// The limit of a decompressed frame.
+pub const MAX_DECOMPRESSED_SIZE: usize = 64 * 1024 * 1024;
+
+#[derive(Error, Debug)]
+pub enum CompressionError {
+    #[error("Unknown compression: {0}")]
+    Unknown(String),
+    #[error("Compression {0} is not enabled. Enable the feature of the compression.")]
+    NotEnabled(Compression),
+    #[error("Decompressed frame exceeds the limit.")]
+    TooLarge,
+}
+
+/// Compression algorithms.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum Compression {
+    Zstd = 1,
+    Lz4 = 2,
+}
+
+const ZSTD_TAG: u8 = 0xC1;
+const LZ4_TAG: u8 = 0xC2;
+
+impl Compression {
+    /// All compression algorithms.
+    pub const ALL: &'static [Compression] = &[Compression::Zstd, Compression::Lz4];
+
+    /// The name of the algorithm used in handshakes.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Zstd => "zstd",
+            Self::Lz4 => "lz4",
+        }
+    }
+
+    /// Returns `true` if the algorithm is enabled by features.
+    pub fn is_enabled(&self) -> bool {
+        match self {
+            Self::Zstd => cfg!(feature = "zstd"),
+            Self::Lz4 => cfg!(feature = "lz4"),
+        }
+    }
+
+    /// Algorithms enabled by features.
+    pub fn enabled() -> impl Iterator<Item = Compression> {
+        Self::ALL.iter().copied().filter(Compression::is_enabled)
+    }
+
+    fn tag(&self) -> u8 {
+        match self {
+            Self::Zstd => ZSTD_TAG,
+            Self::Lz4 => LZ4_TAG,
+        }
+    }
+
+    fn from_tag(tag: u8) -> Option<Self> {
+        match tag {
+            ZSTD_TAG => Some(Self::Zstd),
+            LZ4_TAG => Some(Self::Lz4),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for Compression {
+    type Err = CompressionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.name() == s)
+            .ok_or_else(|| CompressionError::Unknown(s.into()))
+    }
+}
+
+/// Compresses a frame if the compression is set and the frame
+/// is not smaller than the `threshold`.
+pub fn compress(
+    frame: Vec<u8>,
+    compression: Option<Compression>,
+    threshold: usize,
+) -> Result<Vec<u8>, Error> {
+    match compression {
+        Some(kind) if frame.len() >= threshold => {
+            let mut data = pack(kind, &frame)?;
+            if data.len() < frame.len() {
+                data.push(kind.tag());
+                Ok(data)
+            } else {
+                Ok(frame)
+            }
+        }
+        _ => Ok(frame),
+    }
+}
+
+/// Decompresses a frame if it has a tag of the compression.
+pub fn decompress(frame: &[u8]) -> Result<Cow<'_, [u8]>, Error> {
+    match frame.split_last() {
+        Some((tag, data)) => match Compression::from_tag(*tag) {
+            Some(kind) => unpack(kind, data).map(Cow::Owned),
+            None => Ok(Cow::Borrowed(frame)),
+        },
+        None => Ok(Cow::Borrowed(frame)),
+    }
+}
+
+#[allow(unused_variables)]
+fn pack(kind: Compression, data: &[u8]) -> Result<Vec<u8>, Error> {
+    match kind {
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => {
+            zstd::bulk::compress(data, zstd::DEFAULT_COMPRESSION_LEVEL).map_err(Error::from)
+        }
+        #[cfg(feature = "lz4")]
+        Compression::Lz4 => Ok(lz4_flex::compress_prepend_size(data)),
+        #[allow(unreachable_patterns)]
+        kind => Err(CompressionError::NotEnabled(kind).into()),
+    }
+}
+
+#[allow(unused_variables)]
+fn unpack(kind: Compression, data: &[u8]) -> Result<Vec<u8>, Error> {
+    match kind {
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => {
+            zstd::bulk::decompress(data, MAX_DECOMPRESSED_SIZE).map_err(Error::from)
+        }
+        #[cfg(feature = "lz4")]
+        Compression::Lz4 => {
+            // The size is prepended by the compressor
+            let size = data
+                .get(..4)
+                .map(|size| u32::from_le_bytes([size[0], size[1], size[2], size[3]]));
+            if size.unwrap_or(0) as usize > MAX_DECOMPRESSED_SIZE {
+                return Err(CompressionError::TooLarge.into());
+            }
+            lz4_flex::decompress_size_prepended(data).map_err(Error::from)
+        }
+        #[allow(unreachable_patterns)]
+        kind => Err(CompressionError::NotEnabled(kind).into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::encoding::{self, CodecKind};
+
+    fn large_frame() -> Vec<u8> {
+        b"rillrate "
+            .iter()
+            .copied()
+            .cycle()
+            .take(64 * 1024)
+            .collect()
+    }
+
+    #[test]
+    fn small_frames_are_not_compressed() -> Result<(), Error> {
+        let frame = vec![1, 2, 3, 4];
+        assert_eq!(compress(frame.clone(), None, 0)?, frame);
+        for kind in Compression::ALL {
+            assert_eq!(compress(frame.clone(), Some(*kind), 1024)?, frame);
+        }
+        assert!(matches!(decompress(&frame)?, Cow::Borrowed(_)));
+        assert!(matches!(decompress(&[])?, Cow::Borrowed(_)));
+        Ok(())
+    }
+
+    #[test]
+    fn compression_roundtrip() -> Result<(), Error> {
+        let frame = large_frame();
+        for kind in Compression::enabled() {
+            let compressed = compress(frame.clone(), Some(kind), DEFAULT_THRESHOLD)?;
+            assert!(compressed.len() < frame.len(), "{} is not effective", kind);
+            assert_eq!(compressed.last(), Some(&kind.tag()));
+            assert_eq!(decompress(&compressed)?.as_ref(), &frame[..]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn compressed_frames_of_all_codecs() -> Result<(), Error> {
+        let value: Vec<String> = (0..10_000).map(|idx| format!("entry-{}", idx)).collect();
+        for codec in CodecKind::enabled() {
+            let frame = encoding::encode(codec, &value)?;
+            // Tags of compression never end a frame of a codec
+            assert!(matches!(decompress(&frame)?, Cow::Borrowed(_)));
+            for kind in Compression::enabled() {
+                let compressed = compress(frame.clone(), Some(kind), 0)?;
+                let decompressed = decompress(&compressed)?;
+                assert_eq!(encoding::decode::<Vec<String>>(&decompressed)?, value);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn disabled_compressions() {
+        for kind in Compression::ALL.iter().filter(|kind| !kind.is_enabled()) {
+            assert!(compress(large_frame(), Some(*kind), 0).is_err());
+        }
+    }
+}
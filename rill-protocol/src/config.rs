@@ -67,4 +67,20 @@ impl<T> ConfigPatch<T> {
         log::debug!("{} = {:?}", self.pre, value);
         value
     }
+
+    /// The same as `get`, but for values without a default.
+    pub fn get_optional<F>(&self, value: F) -> Option<T>
+    where
+        T: FromStr + Clone,
+        F: Fn() -> Option<T>,
+    {
+        self.env_var()
+            .map_err(|err| {
+                log::error!("Value for {} will be ignored: {}", self.pre, err);
+            })
+            .ok()
+            .and_then(identity)
+            .or_else(value)
+            .or_else(|| self.post.get().cloned())
+    }
 }
@@ -1,29 +1,284 @@
+//! Codecs of messages and payloads.
+//!
+//! Flexbuffers is always available. Other codecs are enabled
+//! by the `bincode`, `msgpack` and `json` features.
+//!
+//! Frames of flexbuffers and JSON are written as is. Frames of other
+//! codecs have a trailing tag byte that never ends a flexbuffers buffer
+//! (it always ends with the byte width of the root: 1, 2, 4 or 8)
+//! or a JSON text. That's why every frame is decoded by the codec
+//! that encoded it and every connection can use its own codec.
+
 use anyhow::Error;
-use serde::{Deserialize, Serialize};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// A serialization format of messages.
+pub trait Codec {
+    /// The kind of the codec.
+    const KIND: CodecKind;
+
+    fn to_vec<T>(value: &T) -> Result<Vec<u8>, Error>
+    where
+        T: Serialize + ?Sized;
+
+    fn from_slice<T>(data: &[u8]) -> Result<T, Error>
+    where
+        T: DeserializeOwned;
+}
+
+/// The default codec compatible with all versions of the protocol.
+pub struct Flexbuffers;
+
+impl Codec for Flexbuffers {
+    const KIND: CodecKind = CodecKind::Flexbuffers;
+
+    fn to_vec<T>(value: &T) -> Result<Vec<u8>, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        flexbuffers::to_vec(value).map_err(Error::from)
+    }
+
+    fn from_slice<T>(data: &[u8]) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        flexbuffers::from_slice(data).map_err(Error::from)
+    }
+}
+
+/// The compact codec for numeric flows.
+#[cfg(feature = "bincode")]
+pub struct Bincode;
+
+#[cfg(feature = "bincode")]
+impl Codec for Bincode {
+    const KIND: CodecKind = CodecKind::Bincode;
+
+    fn to_vec<T>(value: &T) -> Result<Vec<u8>, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        bincode::serialize(value).map_err(Error::from)
+    }
+
+    fn from_slice<T>(data: &[u8]) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        bincode::deserialize(data).map_err(Error::from)
+    }
+}
+
+/// MessagePack with named fields.
+#[cfg(feature = "msgpack")]
+pub struct MessagePack;
+
+#[cfg(feature = "msgpack")]
+impl Codec for MessagePack {
+    const KIND: CodecKind = CodecKind::MessagePack;
+
+    fn to_vec<T>(value: &T) -> Result<Vec<u8>, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        rmp_serde::to_vec_named(value).map_err(Error::from)
+    }
+
+    fn from_slice<T>(data: &[u8]) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        rmp_serde::from_slice(data).map_err(Error::from)
+    }
+}
+
+/// The text codec readable by generic WebSocket tools.
+#[cfg(feature = "json")]
+pub struct Json;
+
+#[cfg(feature = "json")]
+impl Codec for Json {
+    const KIND: CodecKind = CodecKind::Json;
+
+    fn to_vec<T>(value: &T) -> Result<Vec<u8>, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        serde_json::to_vec(value).map_err(Error::from)
+    }
+
+    fn from_slice<T>(data: &[u8]) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        serde_json::from_slice(data).map_err(Error::from)
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum CodecError {
+    #[error("Unknown codec: {0}")]
+    Unknown(String),
+    #[error("Codec {0} is not enabled. Enable the feature of the codec.")]
+    NotEnabled(CodecKind),
+    #[error("Empty frame.")]
+    EmptyFrame,
+    #[error("Unknown codec tag of a frame: {0:#x}")]
+    UnknownTag(u8),
+}
+
+/// Kinds of codecs.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum CodecKind {
+    Flexbuffers = 0,
+    Bincode = 1,
+    #[serde(rename = "msgpack")]
+    MessagePack = 2,
+    Json = 3,
+}
+
+const BINCODE_TAG: u8 = 0xB1;
+const MSGPACK_TAG: u8 = 0xB2;
 
-pub fn from_slice<'a, T>(v: &'a [u8]) -> Result<T, Error>
+impl CodecKind {
+    /// All kinds of codecs.
+    pub const ALL: &'static [CodecKind] = &[
+        CodecKind::Flexbuffers,
+        CodecKind::Bincode,
+        CodecKind::MessagePack,
+        CodecKind::Json,
+    ];
+
+    /// The name of the codec used in handshakes.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Flexbuffers => "flexbuffers",
+            Self::Bincode => "bincode",
+            Self::MessagePack => "msgpack",
+            Self::Json => "json",
+        }
+    }
+
+    /// Returns `true` if the codec is enabled by features.
+    pub fn is_enabled(&self) -> bool {
+        match self {
+            Self::Flexbuffers => true,
+            Self::Bincode => cfg!(feature = "bincode"),
+            Self::MessagePack => cfg!(feature = "msgpack"),
+            Self::Json => cfg!(feature = "json"),
+        }
+    }
+
+    /// Codecs enabled by features.
+    pub fn enabled() -> impl Iterator<Item = CodecKind> {
+        Self::ALL.iter().copied().filter(CodecKind::is_enabled)
+    }
+
+    fn tag(&self) -> Option<u8> {
+        match self {
+            Self::Flexbuffers | Self::Json => None,
+            Self::Bincode => Some(BINCODE_TAG),
+            Self::MessagePack => Some(MSGPACK_TAG),
+        }
+    }
+
+    /// Detects the codec of a frame and returns the data without a tag.
+    fn detect(frame: &[u8]) -> Result<(Self, &[u8]), CodecError> {
+        let (last, data) = frame.split_last().ok_or(CodecError::EmptyFrame)?;
+        match *last {
+            1 | 2 | 4 | 8 => Ok((Self::Flexbuffers, frame)),
+            b'\t' | b'\n' | b'\r' | 0x20..=0x7E => Ok((Self::Json, frame)),
+            BINCODE_TAG => Ok((Self::Bincode, data)),
+            MSGPACK_TAG => Ok((Self::MessagePack, data)),
+            tag => Err(CodecError::UnknownTag(tag)),
+        }
+    }
+}
+
+impl fmt::Display for CodecKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for CodecKind {
+    type Err = CodecError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL
+            .iter()
+            .copied()
+            .find(|kind| kind.name() == s)
+            .ok_or_else(|| CodecError::Unknown(s.into()))
+    }
+}
+
+/// Encodes a value with the specified codec.
+pub fn encode<T>(kind: CodecKind, value: &T) -> Result<Vec<u8>, Error>
 where
-    T: Deserialize<'a>,
+    T: Serialize + ?Sized,
 {
-    //bincode::deserialize(v).map_err(Error::from)
-    flexbuffers::from_slice(v).map_err(Error::from)
-    //serde_json::from_slice(v).map_err(Error::from)
+    let mut data = match kind {
+        CodecKind::Flexbuffers => Flexbuffers::to_vec(value)?,
+        #[cfg(feature = "bincode")]
+        CodecKind::Bincode => Bincode::to_vec(value)?,
+        #[cfg(feature = "msgpack")]
+        CodecKind::MessagePack => MessagePack::to_vec(value)?,
+        #[cfg(feature = "json")]
+        CodecKind::Json => Json::to_vec(value)?,
+        #[allow(unreachable_patterns)]
+        kind => return Err(CodecError::NotEnabled(kind).into()),
+    };
+    if let Some(tag) = kind.tag() {
+        data.push(tag);
+    }
+    Ok(data)
 }
 
-pub fn to_vec<T: ?Sized>(value: &T) -> Result<Vec<u8>, Error>
+/// Decodes a frame with the codec that encoded it.
+pub fn decode<T>(frame: &[u8]) -> Result<T, Error>
 where
-    T: Serialize,
+    T: DeserializeOwned,
 {
-    //bincode::serialize(value).map_err(Error::from)
-    flexbuffers::to_vec(value).map_err(Error::from)
-    //serde_json::to_vec(value).map_err(Error::from)
+    let (kind, data) = CodecKind::detect(frame)?;
+    match kind {
+        CodecKind::Flexbuffers => Flexbuffers::from_slice(data),
+        #[cfg(feature = "bincode")]
+        CodecKind::Bincode => Bincode::from_slice(data),
+        #[cfg(feature = "msgpack")]
+        CodecKind::MessagePack => MessagePack::from_slice(data),
+        #[cfg(feature = "json")]
+        CodecKind::Json => Json::from_slice(data),
+        #[allow(unreachable_patterns)]
+        kind => Err(CodecError::NotEnabled(kind).into()),
+    }
 }
 
-pub fn pack<T: ?Sized, P: From<Vec<u8>>>(value: &T) -> Result<P, Error>
+pub fn from_slice<T>(v: &[u8]) -> Result<T, Error>
 where
-    T: Serialize,
+    T: DeserializeOwned,
 {
-    flexbuffers::to_vec(value).map_err(Error::from).map(P::from)
+    decode(v)
+}
+
+/// Encodes a value with the default codec.
+pub fn to_vec<T>(value: &T) -> Result<Vec<u8>, Error>
+where
+    T: Serialize + ?Sized,
+{
+    encode(CodecKind::Flexbuffers, value)
+}
+
+pub fn pack<T, P: From<Vec<u8>>>(value: &T) -> Result<P, Error>
+where
+    T: Serialize + ?Sized,
+{
+    to_vec(value).map(P::from)
 }
 
 pub fn unpack<T, P>(v: T) -> Result<P, Error>
@@ -31,6 +286,85 @@ where
     T: AsRef<[u8]>,
     P: for<'a> Deserialize<'a>,
 {
-    let data = v.as_ref();
-    flexbuffers::from_slice(data).map_err(Error::from)
+    decode(v.as_ref())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Sample {
+        id: u64,
+        name: String,
+        values: Vec<f64>,
+        flag: Option<bool>,
+    }
+
+    fn sample(len: usize) -> Sample {
+        Sample {
+            id: 42,
+            name: "sample".into(),
+            values: (0..len).map(|value| value as f64).collect(),
+            flag: Some(true),
+        }
+    }
+
+    #[test]
+    fn enabled_codecs_are_detected() -> Result<(), Error> {
+        for kind in CodecKind::enabled() {
+            for len in &[0, 10, 1_000] {
+                let value = sample(*len);
+                let frame = encode(kind, &value)?;
+                let (detected, _) = CodecKind::detect(&frame)?;
+                assert_eq!(detected, kind);
+                assert_eq!(decode::<Sample>(&frame)?, value);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn flexbuffers_frames_of_any_width() -> Result<(), Error> {
+        let frames = vec![
+            to_vec(&1u8)?,
+            to_vec(&u64::MAX)?,
+            to_vec("text")?,
+            to_vec(&vec![u64::MAX; 1_000])?,
+            to_vec(&sample(100_000))?,
+        ];
+        for frame in frames {
+            let (detected, data) = CodecKind::detect(&frame)?;
+            assert_eq!(detected, CodecKind::Flexbuffers);
+            assert_eq!(data, &frame[..]);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_frames() {
+        assert!(matches!(
+            CodecKind::detect(&[]),
+            Err(CodecError::EmptyFrame)
+        ));
+        assert!(matches!(
+            CodecKind::detect(&[1, 2, 0xFF]),
+            Err(CodecError::UnknownTag(0xFF))
+        ));
+    }
+
+    #[test]
+    fn disabled_codecs() {
+        for kind in CodecKind::ALL.iter().filter(|kind| !kind.is_enabled()) {
+            assert!(encode(*kind, &sample(1)).is_err());
+        }
+    }
+
+    #[test]
+    fn codec_names() {
+        for kind in CodecKind::ALL {
+            assert_eq!(kind.name().parse::<CodecKind>().unwrap(), *kind);
+        }
+        assert!("xml".parse::<CodecKind>().is_err());
+    }
 }
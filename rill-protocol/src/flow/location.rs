@@ -5,12 +5,14 @@ pub struct Location {
 }
 
 impl Location {
+    /// Creates a location. A constant with an invalid entry fails to compile.
     pub const fn new(element: &'static str) -> Self {
+        assert!(EntryId::is_valid(element), "Invalid entry of a location.");
         Self { element }
     }
 
     pub fn of(&self, mut path: Path) -> Path {
-        let entry_id: EntryId = EntryId::from(self.element);
+        let entry_id = EntryId::from(self.element);
         path.push(entry_id);
         path
     }
@@ -24,7 +26,7 @@ impl Location {
     }
 
     pub fn root(&self) -> Path {
-        Path::single(self.element)
+        Path::single(EntryId::from(self.element))
     }
 }
 
@@ -0,0 +1,46 @@
+use crate::flow::core::Flow;
+use crate::flow::location::Location;
+use crate::io::provider::StreamType;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+pub const LATENCY: Location = Location::new("meta:latency");
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencyState {
+    /// Round-trip time of the connection to a node.
+    pub latency: Option<Duration>,
+}
+
+#[allow(clippy::new_without_default)]
+impl LatencyState {
+    pub fn new() -> Self {
+        Self { latency: None }
+    }
+}
+
+impl Flow for LatencyState {
+    type Action = ();
+    type Event = LatencyEvent;
+
+    fn stream_type() -> StreamType {
+        StreamType::from("rillrate.meta.latency.v0")
+    }
+
+    fn apply(&mut self, event: Self::Event) {
+        match event {
+            LatencyEvent::Measured { latency } => {
+                self.latency = Some(latency);
+            }
+            LatencyEvent::Lost => {
+                self.latency = None;
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum LatencyEvent {
+    Measured { latency: Duration },
+    Lost,
+}
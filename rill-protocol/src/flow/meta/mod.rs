@@ -1,6 +1,9 @@
 pub mod alert;
 pub use alert::AlertState;
 
+pub mod latency;
+pub use latency::LatencyState;
+
 pub mod path;
 pub use path::PathState;
 
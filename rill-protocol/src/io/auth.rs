@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Credentials of a peer (a provider or a client).
+#[derive(Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub enum Credentials {
+    /// A bearer token shared with a server.
+    Token(String),
+}
+
+impl Credentials {
+    pub fn token(token: impl Into<String>) -> Self {
+        Self::Token(token.into())
+    }
+
+    /// Checks the credentials against the expected token.
+    ///
+    /// The comparison takes the same time for any token of the same length.
+    pub fn verify(&self, expected: &str) -> bool {
+        match self {
+            Self::Token(token) => constant_time_eq(token.as_bytes(), expected.as_bytes()),
+        }
+    }
+}
+
+impl fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Token(_) => f.write_str("Token(***)"),
+        }
+    }
+}
+
+fn constant_time_eq(left: &[u8], right: &[u8]) -> bool {
+    if left.len() != right.len() {
+        return false;
+    }
+    left.iter()
+        .zip(right.iter())
+        .fold(0, |acc, (l, r)| acc | (l ^ r))
+        == 0
+}
@@ -1,4 +1,7 @@
-use crate::io::codec::BinaryCodec;
+use crate::io::auth::Credentials;
+use crate::io::codec::{Frame, FrameCodec};
+use crate::io::handshake::{Handshake, Negotiated};
+use crate::io::payload::{Attach, Payload};
 use crate::io::provider::{Description, EntryId, PackedEvent, PackedState, Path, RecorderRequest};
 use crate::io::transport::{DirectId, Origin, ServiceEnvelope};
 use meio_protocol::Protocol;
@@ -10,9 +13,9 @@ pub type ClientReqId = DirectId<ClientProtocol>;
 pub struct ClientProtocol;
 
 impl Protocol for ClientProtocol {
-    type ToServer = ServiceEnvelope<Self, ClientRequest, ClientServiceResponse>;
-    type ToClient = ServiceEnvelope<Self, ClientResponse, ClientServiceRequest>;
-    type Codec = BinaryCodec;
+    type ToServer = Frame<ServiceEnvelope<Self, ClientRequest, ClientServiceResponse>>;
+    type ToClient = Frame<ServiceEnvelope<Self, ClientResponse, ClientServiceRequest>>;
+    type Codec = FrameCodec<Self>;
 }
 
 impl Origin for ClientProtocol {}
@@ -31,27 +34,84 @@ pub enum ClientResponse {
     Delta(PackedEvent),
     /// Stream closed/finished.
     Done,
+    /// The action reached a watcher.
+    Delivered,
     Error(String),
+    /// A response of a flow to a subscriber of a subtree of flows.
+    Tagged {
+        /// The path of the flow
+        path: Path,
+        response: Box<ClientResponse>,
+    },
+    /// The action was sent to a provider that doesn't confirm actions.
+    Unconfirmed,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ClientServiceRequest {
-    //Ping,
+    Ping,
+    /// The answer to the `Ping` of a client.
+    Pong,
     AccessLevel(AccessLevel),
+    /// Credentials were rejected by the server.
+    AccessDenied {
+        reason: String,
+    },
+    /// The answer to the `Hello` of a client.
+    Accepted(Negotiated),
+    /// No common version of the protocol. The server closes the connection.
+    Incompatible {
+        reason: String,
+    },
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum ClientServiceResponse {
-    //Pong,
+    Pong,
+    /// The client checks the connection.
+    Ping,
+    /// The answer to `AccessLevel::ReadyToAuth`.
+    Authorize(Credentials),
+    /// Versions and capabilities of the client sent when connected.
+    Hello(Handshake),
 }
 
 /// `AccessLevel` notifies about specific stages of a session:
 /// - session created (ready for pings)
 /// - client can sign in or sign up
 /// - client can work will all accessible flows
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
 pub enum AccessLevel {
     SessionCreated,
     ReadyToAuth,
     ReadyToWork,
 }
+
+impl Attach for ClientRequest {
+    fn payloads(&mut self) -> Vec<&mut Payload> {
+        self.request.payloads()
+    }
+}
+
+impl Attach for ClientResponse {
+    fn payloads(&mut self) -> Vec<&mut Payload> {
+        match self {
+            Self::State(state) => state.payloads(),
+            Self::Delta(delta) => delta.payloads(),
+            Self::Tagged { response, .. } => response.payloads(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+impl Attach for ClientServiceRequest {
+    fn payloads(&mut self) -> Vec<&mut Payload> {
+        Vec::new()
+    }
+}
+
+impl Attach for ClientServiceResponse {
+    fn payloads(&mut self) -> Vec<&mut Payload> {
+        Vec::new()
+    }
+}
@@ -1,15 +1,282 @@
-use crate::encoding;
+//! Frames of connections.
+//!
+//! A sender encodes frames with the `Settings` of its connection agreed
+//! in the handshake and `FrameCodec` writes them as is. A receiver gets
+//! frames encoded and decodes them with `Frame::into_message`. Every
+//! frame is decoded by the codec detected by its trailing tag, so decoding
+//! doesn't depend on the settings.
+
+use crate::compression::{self, Compression};
+use crate::encoding::{self, CodecKind};
+use crate::io::payload::{self, Attach};
 use anyhow::Error;
-use meio_protocol::{ProtocolCodec, ProtocolData};
+use meio_protocol::{Protocol, ProtocolCodec, ProtocolData};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+use std::any::{self, Any};
+use std::fmt;
+use std::marker::PhantomData;
+use thiserror::Error;
 
+/// Encodes values with the default codec.
 pub struct BinaryCodec;
 
 impl ProtocolCodec for BinaryCodec {
     fn decode<T: ProtocolData>(data: &[u8]) -> Result<T, Error> {
-        encoding::from_slice(data).map_err(Error::from)
+        encoding::from_slice(data)
     }
 
     fn encode<T: ProtocolData>(value: &T) -> Result<Vec<u8>, Error> {
-        encoding::to_vec(value).map_err(Error::from)
+        encoding::to_vec(value)
+    }
+}
+
+#[derive(Error, Debug)]
+#[error("The protocol has no frames of {0}.")]
+pub struct NotFrame(&'static str);
+
+/// Writes and reads `Frame`s of the protocol `P` as bytes.
+///
+/// Frames are the only messages of the protocol: `P::ToServer`
+/// is `Frame<U>` and `P::ToClient` is `Frame<D>`.
+pub struct FrameCodec<P>(PhantomData<fn() -> P>);
+
+impl<P, U, D> ProtocolCodec for FrameCodec<P>
+where
+    P: Protocol<ToServer = Frame<U>, ToClient = Frame<D>>,
+    U: ProtocolData + Attach,
+    D: ProtocolData + Attach,
+{
+    fn decode<T: ProtocolData>(data: &[u8]) -> Result<T, Error> {
+        let frame: Box<dyn Any> = if is::<T, Frame<U>>() {
+            Box::new(Frame::<U>::Encoded(data.to_vec()))
+        } else if is::<T, Frame<D>>() {
+            Box::new(Frame::<D>::Encoded(data.to_vec()))
+        } else {
+            return Err(NotFrame(any::type_name::<T>()).into());
+        };
+        frame
+            .downcast()
+            .map(|frame| *frame)
+            .map_err(|_| NotFrame(any::type_name::<T>()).into())
+    }
+
+    fn encode<T: ProtocolData>(value: &T) -> Result<Vec<u8>, Error> {
+        let value: &dyn Any = value;
+        if let Some(frame) = value.downcast_ref::<Frame<U>>() {
+            frame.to_bytes()
+        } else if let Some(frame) = value.downcast_ref::<Frame<D>>() {
+            frame.to_bytes()
+        } else {
+            Err(NotFrame(any::type_name::<T>()).into())
+        }
+    }
+}
+
+fn is<T: Any, F: Any>() -> bool {
+    any::TypeId::of::<T>() == any::TypeId::of::<F>()
+}
+
+/// Settings of outgoing frames of a connection agreed in the handshake.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Settings {
+    /// The codec of messages. Payloads are always encoded with flexbuffers.
+    pub codec: CodecKind,
+    /// The compression of large frames
+    pub compression: Option<Compression>,
+    /// The minimal size of a frame to compress
+    pub threshold: usize,
+    /// Payloads are attached to frames
+    pub blobs: bool,
+}
+
+/// The settings of the first version of the protocol.
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            codec: CodecKind::Flexbuffers,
+            compression: None,
+            threshold: compression::DEFAULT_THRESHOLD,
+            blobs: false,
+        }
+    }
+}
+
+impl Settings {
+    /// Encodes a message to a frame.
+    pub fn encode<T>(&self, msg: T) -> Result<Vec<u8>, Error>
+    where
+        T: Serialize + Attach,
+    {
+        let frame = if self.blobs {
+            payload::encode_frame(self.codec, msg)?
+        } else {
+            encoding::encode(self.codec, &msg)?
+        };
+        compression::compress(frame, self.compression, self.threshold)
+    }
+
+    /// Prepares a message to send it over the connection.
+    ///
+    /// Messages are left as is for the default settings. `FrameCodec`
+    /// encodes them and in-process connections don't encode them at all.
+    pub fn frame<T>(&self, msg: T) -> Result<Frame<T>, Error>
+    where
+        T: Serialize + Attach,
+    {
+        if *self == Self::default() {
+            Ok(Frame::Message(msg))
+        } else {
+            self.encode(msg).map(Frame::Encoded)
+        }
+    }
+}
+
+/// Decodes a frame.
+pub fn decode<T>(frame: &[u8]) -> Result<T, Error>
+where
+    T: DeserializeOwned + Attach,
+{
+    let frame = compression::decompress(frame)?;
+    payload::decode_frame(frame.into_owned())
+}
+
+/// A message of a connection.
+#[derive(Clone)]
+pub enum Frame<T> {
+    /// A message to encode with the default settings or passed in-process.
+    Message(T),
+    /// A message encoded with the settings of the connection or a received one.
+    Encoded(Vec<u8>),
+}
+
+impl<T> Frame<T>
+where
+    T: Serialize + DeserializeOwned + Attach,
+{
+    /// Returns the message. An encoded message is decoded.
+    pub fn into_message(self) -> Result<T, Error> {
+        match self {
+            Self::Message(msg) => Ok(msg),
+            Self::Encoded(data) => decode(&data),
+        }
+    }
+
+    /// Bytes of the frame. A message is encoded with the default settings.
+    pub fn to_bytes(&self) -> Result<Vec<u8>, Error> {
+        match self {
+            Self::Message(msg) => encoding::to_vec(msg),
+            Self::Encoded(data) => Ok(data.clone()),
+        }
+    }
+}
+
+impl<T> From<T> for Frame<T> {
+    fn from(msg: T) -> Self {
+        Self::Message(msg)
+    }
+}
+
+impl<T: fmt::Debug> fmt::Debug for Frame<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Message(msg) => msg.fmt(f),
+            Self::Encoded(data) => write!(f, "Encoded({} bytes)", data.len()),
+        }
+    }
+}
+
+/// `FrameCodec` writes frames as bytes and doesn't use it.
+impl<T: Serialize> Serialize for Frame<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            Self::Message(msg) => msg.serialize(serializer),
+            Self::Encoded(data) => serializer.serialize_bytes(data),
+        }
+    }
+}
+
+/// `FrameCodec` reads frames as bytes and doesn't use it.
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Frame<T> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        T::deserialize(deserializer).map(Self::Message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::provider::{
+        PackedState, Path, ProviderProtocol, ProviderToServer, ServerToProvider,
+    };
+    use crate::io::transport::{DirectId, Direction, ServiceEnvelope, WideEnvelope};
+
+    type Codec = FrameCodec<ProviderProtocol>;
+    type Up = WideEnvelope<ProviderProtocol, ProviderToServer>;
+
+    fn tagged(state: &str) -> Result<Up, Error> {
+        let state = PackedState::from(encoding::to_vec(state)?);
+        Ok(WideEnvelope {
+            direction: Direction::from(DirectId::from(3)),
+            data: ProviderToServer::Tagged {
+                path: "a.b".parse::<Path>()?,
+                data: Box::new(ProviderToServer::State { state }),
+            },
+        })
+    }
+
+    fn settings() -> impl Iterator<Item = Settings> {
+        CodecKind::enabled().flat_map(|codec| {
+            let compressions = Compression::enabled().map(Some).chain(Some(None));
+            compressions.flat_map(move |compression| {
+                [false, true].iter().map(move |blobs| Settings {
+                    codec,
+                    compression,
+                    threshold: 0,
+                    blobs: *blobs,
+                })
+            })
+        })
+    }
+
+    fn state(msg: Up) -> Result<String, Error> {
+        match msg.data {
+            ProviderToServer::Tagged { data, .. } => match *data {
+                ProviderToServer::State { state } => encoding::unpack(state),
+                other => Err(Error::msg(format!("Unexpected data: {:?}", other))),
+            },
+            other => Err(Error::msg(format!("Unexpected data: {:?}", other))),
+        }
+    }
+
+    #[test]
+    fn frames_roundtrip() -> Result<(), Error> {
+        for settings in settings() {
+            let frame = settings.frame(tagged("state")?)?;
+            let data = Codec::encode(&frame)?;
+            let frame: Frame<Up> = Codec::decode(&data)?;
+            assert!(matches!(frame, Frame::Encoded(_)), "{:?}", settings);
+            assert_eq!(state(frame.into_message()?)?, "state", "{:?}", settings);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn messages_are_encoded_with_the_default_settings() -> Result<(), Error> {
+        let data = Codec::encode(&Frame::Message(tagged("state")?))?;
+        assert_eq!(data, encoding::to_vec(&tagged("state")?)?);
+        let frame: Frame<Up> = Codec::decode(&data)?;
+        assert_eq!(state(frame.into_message()?)?, "state");
+        Ok(())
+    }
+
+    #[test]
+    fn other_types_are_rejected() -> Result<(), Error> {
+        type Down = ServiceEnvelope<ProviderProtocol, ServerToProvider, ()>;
+        assert!(Codec::encode(&tagged("state")?).is_err());
+        assert!(Codec::decode::<Frame<Down>>(&[1]).is_err());
+        Ok(())
     }
 }
@@ -0,0 +1,213 @@
+//! Negotiation of the protocol version and capabilities of peers.
+//!
+//! A provider sends its `Handshake` with the `Declare` message and
+//! a client sends it as the first service message of a session.
+//! The server answers with the `Negotiated` settings or rejects
+//! the peer if there is no common version of the protocol.
+//!
+//! Peers without a handshake are peers of the first version: they get
+//! frames with the default `Settings` and have no optional features.
+//!
+//! Every peer encodes frames of a connection with its own `Settings`.
+//! A codec is used only if the other peer reported it can decode it,
+//! otherwise the peer falls back to the default one. The same is
+//! applied to the compression of frames.
+//!
+//! Only codecs of frames are negotiated. Payloads of flows are always
+//! encoded with flexbuffers, so a node forwards payloads of a provider
+//! to clients as is whatever codecs both connections use.
+
+use crate::compression::Compression;
+use crate::encoding::CodecKind;
+use crate::io::codec::Settings;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use thiserror::Error;
+
+/// The first version of the protocol. Its peers have no handshake.
+pub const PROTOCOL_V1: u32 = 1;
+
+/// The version of the protocol implemented by the crate.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// The oldest version of the protocol the crate can talk.
+pub const MIN_PROTOCOL_VERSION: u32 = PROTOCOL_V1;
+
+/// Peer confirms delivered actions or waits for confirmations.
+pub const FEATURE_ACTIONS: &str = "actions";
+
+/// Peer checks the connection with pings.
+pub const FEATURE_HEARTBEAT: &str = "heartbeat";
+
+/// Peer reads payloads attached to frames.
+pub const FEATURE_BLOBS: &str = "blobs";
+
+/// Optional features of a peer.
+///
+/// Names unknown by a peer are ignored by it.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Capabilities {
+    /// Codecs of payloads
+    #[serde(default)]
+    pub codecs: BTreeSet<String>,
+    /// Compression algorithms of payloads
+    #[serde(default)]
+    pub compression: BTreeSet<String>,
+    /// Supported extensions of the protocol
+    #[serde(default)]
+    pub features: BTreeSet<String>,
+}
+
+impl Capabilities {
+    /// Capabilities supported by the crate.
+    pub fn supported() -> Self {
+        let features = vec![FEATURE_ACTIONS, FEATURE_HEARTBEAT, FEATURE_BLOBS];
+        Self {
+            codecs: CodecKind::enabled()
+                .map(|kind| kind.name().to_string())
+                .collect(),
+            compression: Compression::enabled()
+                .map(|kind| kind.name().to_string())
+                .collect(),
+            features: features.into_iter().map(String::from).collect(),
+        }
+    }
+
+    /// Capabilities supported by both peers.
+    pub fn intersection(&self, other: &Self) -> Self {
+        Self {
+            codecs: self.codecs.intersection(&other.codecs).cloned().collect(),
+            compression: self
+                .compression
+                .intersection(&other.compression)
+                .cloned()
+                .collect(),
+            features: self
+                .features
+                .intersection(&other.features)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /// Returns `true` if the codec is supported.
+    pub fn has_codec(&self, kind: CodecKind) -> bool {
+        self.codecs.contains(kind.name())
+    }
+
+    /// Returns `true` if the compression is supported.
+    pub fn has_compression(&self, kind: Compression) -> bool {
+        self.compression.contains(kind.name())
+    }
+
+    /// Returns `true` if the feature is supported.
+    pub fn has_feature(&self, feature: &str) -> bool {
+        self.features.contains(feature)
+    }
+}
+
+/// Versions of the protocol and capabilities offered by a peer.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Handshake {
+    /// The newest supported version
+    pub version: u32,
+    /// The oldest supported version
+    pub min_version: u32,
+    #[serde(default)]
+    pub capabilities: Capabilities,
+}
+
+impl Default for Handshake {
+    fn default() -> Self {
+        Self {
+            version: PROTOCOL_VERSION,
+            min_version: MIN_PROTOCOL_VERSION,
+            capabilities: Capabilities::supported(),
+        }
+    }
+}
+
+/// The reason to reject a peer.
+#[derive(Error, Debug, Clone)]
+pub enum HandshakeError {
+    #[error(
+        "Incompatible protocol versions: {min_version}..={version} and {peer_min_version}..={peer_version}."
+    )]
+    IncompatibleVersion {
+        version: u32,
+        min_version: u32,
+        peer_version: u32,
+        peer_min_version: u32,
+    },
+}
+
+impl Handshake {
+    /// Picks the newest common version and common capabilities.
+    pub fn negotiate(&self, peer: &Handshake) -> Result<Negotiated, HandshakeError> {
+        let version = self.version.min(peer.version);
+        if version < self.min_version || version < peer.min_version {
+            return Err(HandshakeError::IncompatibleVersion {
+                version: self.version,
+                min_version: self.min_version,
+                peer_version: peer.version,
+                peer_min_version: peer.min_version,
+            });
+        }
+        let capabilities = self.capabilities.intersection(&peer.capabilities);
+        Ok(Negotiated {
+            version,
+            capabilities,
+        })
+    }
+}
+
+/// Settings of a session agreed by both peers.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct Negotiated {
+    pub version: u32,
+    pub capabilities: Capabilities,
+}
+
+impl Negotiated {
+    /// The session with a peer of the first version.
+    pub fn v1() -> Self {
+        Self {
+            version: PROTOCOL_V1,
+            capabilities: Capabilities::default(),
+        }
+    }
+
+    /// Returns `true` if the peer talks the first version.
+    pub fn is_v1(&self) -> bool {
+        self.version == PROTOCOL_V1
+    }
+
+    /// Returns `true` if both peers support the feature.
+    pub fn has_feature(&self, feature: &str) -> bool {
+        self.capabilities.has_feature(feature)
+    }
+
+    /// The `preferred` codec if both peers support it or the default one.
+    pub fn codec(&self, preferred: CodecKind) -> CodecKind {
+        if self.capabilities.has_codec(preferred) {
+            preferred
+        } else {
+            CodecKind::Flexbuffers
+        }
+    }
+
+    /// The `preferred` compression if both peers support it.
+    pub fn compression(&self, preferred: Option<Compression>) -> Option<Compression> {
+        preferred.filter(|kind| self.capabilities.has_compression(*kind))
+    }
+
+    /// Settings of frames sent to the peer derived from the `preferred` ones.
+    pub fn settings(&self, preferred: &Settings) -> Settings {
+        Settings {
+            codec: self.codec(preferred.codec),
+            compression: self.compression(preferred.compression),
+            threshold: preferred.threshold,
+            blobs: preferred.blobs && self.capabilities.has_feature(FEATURE_BLOBS),
+        }
+    }
+}
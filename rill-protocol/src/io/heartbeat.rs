@@ -0,0 +1,66 @@
+use std::time::{Duration, Instant};
+
+/// The default interval between pings.
+pub const PING_INTERVAL: Duration = Duration::from_secs(5);
+
+/// The default time to wait for a pong before the connection considered dead.
+pub const PING_DEADLINE: Duration = Duration::from_secs(15);
+
+/// What to do on the next tick of a heartbeat.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pulse {
+    /// Send a new ping.
+    Ping,
+    /// Wait for the pong of the sent ping.
+    Wait,
+    /// No pong received in time.
+    Dead,
+}
+
+/// Tracks pings and pongs of a connection and measures the round-trip latency.
+#[derive(Debug)]
+pub struct Heartbeat {
+    deadline: Duration,
+    sent: Option<Instant>,
+    latency: Option<Duration>,
+}
+
+impl Heartbeat {
+    pub fn new(deadline: Duration) -> Self {
+        Self {
+            deadline,
+            sent: None,
+            latency: None,
+        }
+    }
+
+    /// Forgets the state of the previous connection.
+    pub fn reset(&mut self) {
+        self.sent.take();
+        self.latency.take();
+    }
+
+    pub fn tick(&mut self, now: Instant) -> Pulse {
+        match self.sent {
+            Some(sent) if now.saturating_duration_since(sent) > self.deadline => Pulse::Dead,
+            Some(_) => Pulse::Wait,
+            None => {
+                self.sent = Some(now);
+                Pulse::Ping
+            }
+        }
+    }
+
+    /// Registers a pong and returns the measured latency.
+    pub fn pong(&mut self, now: Instant) -> Option<Duration> {
+        let sent = self.sent.take()?;
+        let latency = now.saturating_duration_since(sent);
+        self.latency = Some(latency);
+        Some(latency)
+    }
+
+    /// The last measured latency.
+    pub fn latency(&self) -> Option<Duration> {
+        self.latency
+    }
+}
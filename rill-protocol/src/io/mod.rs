@@ -1,4 +1,8 @@
+pub mod auth;
 pub mod client;
 pub mod codec;
+pub mod handshake;
+pub mod heartbeat;
+pub mod payload;
 pub mod provider;
 pub mod transport;
@@ -0,0 +1,373 @@
+//! Payloads of flows carried by messages.
+//!
+//! A payload keeps a state, an event or an action of a flow encoded once
+//! by the provider or the client that packed it. Payloads are always
+//! encoded with flexbuffers, so nodes route them as is to every peer
+//! whatever codec its frames use.
+//!
+//! Peers of the first version read payloads as vectors of bytes inside
+//! messages. Other peers get payloads attached to the frame after the
+//! header that keeps the message with empty payloads and the lengths
+//! of attachments. A frame with attachments is laid out as:
+//!
+//! `[header][attachments][length of the header: u32 LE][ATTACHMENTS_TAG]`
+//!
+//! The header is encoded by the codec of the frame. Attachments follow
+//! in the order of `Attach::payloads` of the message.
+
+use crate::encoding::{self, CodecKind};
+use anyhow::Error;
+use serde::{de, de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+use std::convert::TryFrom;
+use std::fmt;
+use std::mem;
+use std::ops::Range;
+use std::sync::Arc;
+use thiserror::Error;
+
+/// Ends a frame with attachments. It never ends a frame of a codec.
+const ATTACHMENTS_TAG: u8 = 0xA1;
+
+#[derive(Error, Debug)]
+pub enum PayloadError {
+    #[error("The frame with attachments is truncated.")]
+    Truncated,
+    #[error("The attachment {0:?} is out of the frame.")]
+    OutOfBounds(Range<usize>),
+    #[error("The message has {payloads} payloads, but the frame has {attachments} attachments.")]
+    Mismatch { payloads: usize, attachments: usize },
+}
+
+/// A message that carries payloads.
+pub trait Attach {
+    /// Payloads of the message in the same order for equal messages.
+    fn payloads(&mut self) -> Vec<&mut Payload>;
+}
+
+/// The message of a frame with attachments.
+#[derive(Serialize, Deserialize)]
+struct Header<T> {
+    message: T,
+    /// Lengths of attachments.
+    attachments: Vec<u32>,
+}
+
+/// Encodes a frame with payloads of the message attached to it.
+///
+/// A message without payloads is encoded as is.
+pub(crate) fn encode_frame<T>(codec: CodecKind, mut message: T) -> Result<Vec<u8>, Error>
+where
+    T: Serialize + Attach,
+{
+    let attachments: Vec<Payload> = message.payloads().into_iter().map(mem::take).collect();
+    if attachments.is_empty() {
+        return encoding::encode(codec, &message);
+    }
+    let lengths = attachments
+        .iter()
+        .map(|payload| u32::try_from(payload.len()))
+        .collect::<Result<_, _>>()?;
+    let header = Header {
+        message,
+        attachments: lengths,
+    };
+    let mut frame = encoding::encode(codec, &header)?;
+    let len = u32::try_from(frame.len())?;
+    for payload in &attachments {
+        frame.extend_from_slice(payload.as_bytes());
+    }
+    frame.extend_from_slice(&len.to_le_bytes());
+    frame.push(ATTACHMENTS_TAG);
+    Ok(frame)
+}
+
+/// Decodes the message of a frame and attaches payloads of the frame to it.
+///
+/// Attached payloads share the buffer of the frame.
+pub(crate) fn decode_frame<T>(frame: Vec<u8>) -> Result<T, Error>
+where
+    T: DeserializeOwned + Attach,
+{
+    if frame.last() != Some(&ATTACHMENTS_TAG) {
+        return encoding::decode(&frame);
+    }
+    let end = frame.len() - 1;
+    let split = end.checked_sub(4).ok_or(PayloadError::Truncated)?;
+    let len = u32::from_le_bytes(<[u8; 4]>::try_from(&frame[split..end])?) as usize;
+    let header = frame.get(..len).ok_or(PayloadError::Truncated)?;
+    let Header {
+        mut message,
+        attachments,
+    } = encoding::decode::<Header<T>>(header)?;
+    let mut payloads = message.payloads();
+    if payloads.len() != attachments.len() {
+        return Err(PayloadError::Mismatch {
+            payloads: payloads.len(),
+            attachments: attachments.len(),
+        }
+        .into());
+    }
+    let data: Arc<[u8]> = frame.into();
+    let mut offset = len;
+    for (payload, len) in payloads.iter_mut().zip(attachments) {
+        let range = offset..offset.saturating_add(len as usize);
+        if range.end > split {
+            return Err(PayloadError::OutOfBounds(range).into());
+        }
+        offset = range.end;
+        **payload = Payload {
+            data: data.clone(),
+            range,
+        };
+    }
+    drop(payloads);
+    Ok(message)
+}
+
+/// Encoded bytes of a state, an event or an action of a flow.
+///
+/// Clones share the bytes.
+#[derive(Clone, Default)]
+pub struct Payload {
+    data: Arc<[u8]>,
+    range: Range<usize>,
+}
+
+impl Payload {
+    /// Encodes a value with the codec of payloads.
+    pub fn encode<T>(value: &T) -> Result<Self, Error>
+    where
+        T: Serialize + ?Sized,
+    {
+        encoding::to_vec(value).map(Self::from)
+    }
+
+    /// Decodes the value of the payload.
+    pub fn decode<T>(&self) -> Result<T, Error>
+    where
+        T: DeserializeOwned,
+    {
+        encoding::decode(self.as_bytes())
+    }
+
+    /// Encoded bytes of the payload.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.data[self.range.clone()]
+    }
+
+    /// The length of encoded bytes.
+    pub fn len(&self) -> usize {
+        self.range.len()
+    }
+
+    /// Returns `true` if the payload has no bytes.
+    pub fn is_empty(&self) -> bool {
+        self.range.is_empty()
+    }
+}
+
+impl From<Vec<u8>> for Payload {
+    fn from(data: Vec<u8>) -> Self {
+        let range = 0..data.len();
+        Self {
+            data: data.into(),
+            range,
+        }
+    }
+}
+
+impl From<Payload> for Vec<u8> {
+    fn from(payload: Payload) -> Self {
+        payload.as_bytes().to_vec()
+    }
+}
+
+impl AsRef<[u8]> for Payload {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl PartialEq for Payload {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl Eq for Payload {}
+
+impl fmt::Debug for Payload {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} bytes", self.len())
+    }
+}
+
+impl Serialize for Payload {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        // Peers of the first version read vectors of bytes only
+        self.as_bytes().serialize(serializer)
+    }
+}
+
+struct PayloadVisitor;
+
+impl<'de> de::Visitor<'de> for PayloadVisitor {
+    type Value = Payload;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("bytes of a payload")
+    }
+
+    fn visit_bytes<E>(self, value: &[u8]) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(Payload::from(value.to_vec()))
+    }
+
+    fn visit_byte_buf<E>(self, value: Vec<u8>) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(Payload::from(value))
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::SeqAccess<'de>,
+    {
+        let mut data = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+        while let Some(byte) = seq.next_element()? {
+            data.push(byte);
+        }
+        Ok(Payload::from(data))
+    }
+}
+
+impl<'de> Deserialize<'de> for Payload {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_bytes(PayloadVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize, Deserialize, Debug, PartialEq)]
+    struct Message {
+        name: String,
+        first: Payload,
+        second: Option<Payload>,
+    }
+
+    impl Attach for Message {
+        fn payloads(&mut self) -> Vec<&mut Payload> {
+            let mut payloads = vec![&mut self.first];
+            payloads.extend(self.second.as_mut());
+            payloads
+        }
+    }
+
+    fn message(second: Option<&str>) -> Result<Message, Error> {
+        Ok(Message {
+            name: "message".into(),
+            first: Payload::encode(&vec![1u64, 2, 3])?,
+            second: second.map(Payload::encode).transpose()?,
+        })
+    }
+
+    #[test]
+    fn attachments_roundtrip() -> Result<(), Error> {
+        for codec in CodecKind::enabled() {
+            for second in &[None, Some(""), Some("second")] {
+                let expected = message(*second)?;
+                let frame = encode_frame(codec, message(*second)?)?;
+                assert_eq!(frame.last(), Some(&ATTACHMENTS_TAG));
+                let decoded: Message = decode_frame(frame)?;
+                assert_eq!(decoded, expected, "{} frames differ", codec);
+                assert_eq!(decoded.first.decode::<Vec<u64>>()?, vec![1, 2, 3]);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn attachments_follow_the_header() -> Result<(), Error> {
+        let msg = message(Some("second"))?;
+        let mut attachments = msg.first.as_bytes().to_vec();
+        let lengths = vec![
+            attachments.len() as u32,
+            msg.second.as_ref().unwrap().len() as u32,
+        ];
+        attachments.extend_from_slice(msg.second.as_ref().unwrap().as_bytes());
+        let frame = encode_frame(CodecKind::Flexbuffers, message(Some("second"))?)?;
+
+        let (rest, len) = frame[..frame.len() - 1].split_at(frame.len() - 5);
+        let len = u32::from_le_bytes(<[u8; 4]>::try_from(len)?) as usize;
+        let header: Header<Message> = encoding::decode(&rest[..len])?;
+        assert_eq!(header.attachments, lengths);
+        assert!(header.message.first.is_empty());
+        assert_eq!(header.message.second, Some(Payload::default()));
+        assert_eq!(&rest[len..], &attachments[..]);
+
+        // Payloads of a decoded frame share its buffer
+        let decoded: Message = decode_frame(frame)?;
+        assert!(Arc::ptr_eq(
+            &decoded.first.data,
+            &decoded.second.as_ref().unwrap().data
+        ));
+        assert_eq!(decoded, msg);
+        Ok(())
+    }
+
+    #[test]
+    fn frames_without_payloads_are_plain() -> Result<(), Error> {
+        #[derive(Serialize, Deserialize, Debug, PartialEq)]
+        struct Plain(String);
+
+        impl Attach for Plain {
+            fn payloads(&mut self) -> Vec<&mut Payload> {
+                Vec::new()
+            }
+        }
+
+        let frame = encode_frame(CodecKind::Flexbuffers, Plain("plain".into()))?;
+        assert_eq!(frame, encoding::to_vec(&Plain("plain".into()))?);
+        assert_eq!(decode_frame::<Plain>(frame)?, Plain("plain".into()));
+        Ok(())
+    }
+
+    #[test]
+    fn broken_frames_are_rejected() -> Result<(), Error> {
+        let frame = encode_frame(CodecKind::Flexbuffers, message(Some("second"))?)?;
+        let end = frame.len() - 5;
+
+        // Attachments are cut off
+        let mut cut = frame[..end - 3].to_vec();
+        cut.extend_from_slice(&frame[end..]);
+        assert!(decode_frame::<Message>(cut).is_err());
+
+        // The length of the header is out of the frame
+        let mut long = frame[..end].to_vec();
+        long.extend_from_slice(&u32::MAX.to_le_bytes());
+        long.push(ATTACHMENTS_TAG);
+        assert!(decode_frame::<Message>(long).is_err());
+
+        assert!(decode_frame::<Message>(vec![0, ATTACHMENTS_TAG]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn payloads_are_bytes_inside_messages() -> Result<(), Error> {
+        let payload = Payload::encode("value")?;
+        let bytes: Vec<u8> = payload.clone().into();
+        let frame = encoding::to_vec(&payload)?;
+        assert_eq!(encoding::decode::<Vec<u8>>(&frame)?, bytes);
+        assert_eq!(encoding::decode::<Payload>(&frame)?, payload);
+        Ok(())
+    }
+}
@@ -1,11 +1,15 @@
-use crate::io::codec::BinaryCodec;
-use crate::io::transport::{DirectId, Envelope, Origin, WideEnvelope};
-use derive_more::{AsMut, AsRef, Deref, DerefMut, From, FromStr, Index, Into};
+use crate::io::auth::Credentials;
+use crate::io::client::AccessLevel;
+use crate::io::codec::{Frame, FrameCodec};
+use crate::io::handshake::{Handshake, Negotiated};
+use crate::io::payload::{Attach, Payload};
+use crate::io::transport::{DirectId, Origin, ServiceEnvelope, WideEnvelope};
+use derive_more::{AsMut, AsRef, Deref, DerefMut, From, Index, Into};
 use meio_protocol::Protocol;
 use serde::{de, Deserialize, Deserializer, Serialize};
 use std::borrow::Borrow;
 use std::convert::TryInto;
-use std::fmt;
+use std::fmt::{self, Write};
 use std::iter::FromIterator;
 use std::str::FromStr;
 use std::time::Duration;
@@ -13,10 +17,94 @@ use thiserror::Error;
 
 pub type ProviderReqId = DirectId<ProviderProtocol>;
 
+/// The prefix of entries of hidden paths.
+pub const HIDDEN_PREFIX: &str = "@";
+
+/// The prefix of entries of meta flows.
+pub const META_PREFIX: &str = "meta:";
+
+/// The separator of entries in a formatted `Path`.
+const SEPARATOR: char = '.';
+
+/// The escape character of a formatted `Path`.
+const ESCAPE: char = '\\';
+
+/// The character of wildcards that can be escaped in a formatted `PathPattern`.
+const WILDCARD: char = '*';
+
 /// An identifier in a hierarchy of the node/metadata/stream.
-#[derive(Serialize, Deserialize, FromStr, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+///
+/// An entry can contain any characters except control ones.
+/// Dots are escaped when the entry is a part of a formatted `Path`.
+///
+/// Parsed and deserialized entries are validated. Entries converted
+/// with `From` are not, use `EntryId::new` for values from outside.
+#[derive(Serialize, Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct EntryId(String);
 
+impl EntryId {
+    /// Creates a validated entry.
+    pub fn new(value: impl Into<String>) -> Result<Self, PathError> {
+        let entry_id = Self(value.into());
+        entry_id.validate()?;
+        Ok(entry_id)
+    }
+
+    /// Creates a validated entry of a constant.
+    pub fn from_static(value: &'static str) -> Result<Self, PathError> {
+        Self::new(value)
+    }
+
+    /// Returns `true` if the `value` is not empty and has no control characters.
+    ///
+    /// It's a `const fn` to check constants at compile time.
+    pub const fn is_valid(value: &str) -> bool {
+        let bytes = value.as_bytes();
+        let mut idx = 0;
+        while idx < bytes.len() {
+            // C0 controls and DEL are single bytes, C1 controls are `0xC2 0x80..=0x9F`
+            let control = match bytes[idx] {
+                0x00..=0x1F | 0x7F => true,
+                0xC2 => idx + 1 < bytes.len() && bytes[idx + 1] >= 0x80 && bytes[idx + 1] <= 0x9F,
+                _ => false,
+            };
+            if control {
+                return false;
+            }
+            idx += 1;
+        }
+        !bytes.is_empty()
+    }
+
+    /// Checks the entry is not empty and has no control characters.
+    pub fn validate(&self) -> Result<(), PathError> {
+        if self.0.is_empty() {
+            Err(PathError::EmptyEntry)
+        } else if !Self::is_valid(&self.0) {
+            Err(PathError::ControlChar(self.0.clone()))
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Returns `true` if the entry has a prefix of hidden paths
+    /// or meta flows, or if it's a wildcard.
+    pub fn is_reserved(&self) -> bool {
+        self.0.starts_with(HIDDEN_PREFIX)
+            || self.0.starts_with(META_PREFIX)
+            || self.0 == ANY_ENTRY
+            || self.0 == ANY_ENTRIES
+    }
+}
+
+impl FromStr for EntryId {
+    type Err = PathError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::new(s)
+    }
+}
+
 impl AsRef<str> for EntryId {
     fn as_ref(&self) -> &str {
         &self.0
@@ -41,25 +129,222 @@ impl From<String> for EntryId {
     }
 }
 
+impl<'de> Deserialize<'de> for EntryId {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = String::deserialize(deserializer)?;
+        Self::new(value).map_err(de::Error::custom)
+    }
+}
+
+impl From<EntryId> for String {
+    fn from(entry_id: EntryId) -> Self {
+        entry_id.0
+    }
+}
+
 impl fmt::Display for EntryId {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt::Display::fmt(&self.0, f)
     }
 }
 
+/// The entry of a pattern that matches any single entry.
+pub const ANY_ENTRY: &str = "*";
+
+/// The entry of a pattern that matches any number of entries.
+pub const ANY_ENTRIES: &str = "**";
+
+/// A `Path` with wildcards.
+///
+/// The `*` entry matches exactly one entry and `**` matches
+/// any number of entries including none. For example, `myapp.*.latency`
+/// matches `myapp.db.latency` and `myapp.**` matches all paths of `myapp`.
+///
+/// An escaped asterisk is matched literally: `myapp.\*` matches
+/// the entry `*` only. In the `path` of a pattern such an entry
+/// is prefixed with a backslash, as are entries that start with one.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct PathPattern {
     pub path: Path,
 }
 
+/// An entry of a pattern.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Segment<'a> {
+    AnyEntry,
+    AnyEntries,
+    Literal(&'a str),
+}
+
+impl<'a> From<&'a EntryId> for Segment<'a> {
+    fn from(entry: &'a EntryId) -> Self {
+        match entry.as_ref() {
+            ANY_ENTRY => Self::AnyEntry,
+            ANY_ENTRIES => Self::AnyEntries,
+            entry => match entry.strip_prefix(ESCAPE) {
+                Some(literal) if !literal.is_empty() => Self::Literal(literal),
+                _ => Self::Literal(entry),
+            },
+        }
+    }
+}
+
+impl PathPattern {
+    /// The pattern that matches the `path` only.
+    ///
+    /// Entries that look like wildcards are matched literally.
+    pub fn exact(path: Path) -> Self {
+        let path = path.into_iter().map(Self::escape).collect::<Vec<_>>();
+        Self { path: path.into() }
+    }
+
+    /// The pattern that matches the `path` and all nested paths.
+    pub fn subtree(path: Path) -> Self {
+        let mut pattern = Self::exact(path);
+        pattern.path.0.push(EntryId::from(ANY_ENTRIES));
+        pattern
+    }
+
+    /// Makes the `entry` of a pattern match itself literally.
+    pub fn escape(entry: EntryId) -> EntryId {
+        let entry_ref = entry.as_ref();
+        if entry_ref == ANY_ENTRY || entry_ref == ANY_ENTRIES || entry_ref.starts_with(ESCAPE) {
+            EntryId(format!("{}{}", ESCAPE, entry_ref))
+        } else {
+            entry
+        }
+    }
+
+    fn segments(&self) -> impl Iterator<Item = Segment<'_>> {
+        self.path.0.iter().map(Segment::from)
+    }
+
+    fn segment(&self, state: usize) -> Option<Segment<'_>> {
+        self.path.0.get(state).map(Segment::from)
+    }
+
+    /// Returns `true` if the pattern has no wildcards.
+    pub fn is_exact(&self) -> bool {
+        self.segments()
+            .all(|segment| matches!(segment, Segment::Literal(_)))
+    }
+
+    /// The only path matched by the pattern if it has no wildcards.
+    pub fn exact_path(&self) -> Option<Path> {
+        self.segments()
+            .map(|segment| match segment {
+                Segment::Literal(entry) => Some(EntryId(entry.into())),
+                Segment::AnyEntry | Segment::AnyEntries => None,
+            })
+            .collect::<Option<Vec<_>>>()
+            .map(Path::from)
+    }
+
+    /// Returns `true` if the `path` matches the pattern.
+    pub fn matches(&self, path: &Path) -> bool {
+        let states = path
+            .0
+            .iter()
+            .fold(self.start(), |states, entry| self.step(&states, entry));
+        self.accepts(&states)
+    }
+
+    /// Positions in the pattern before any entry is matched.
+    pub(crate) fn start(&self) -> Vec<usize> {
+        let mut states = Vec::new();
+        self.add_state(&mut states, 0);
+        states
+    }
+
+    /// Positions in the pattern after the `entry` is matched.
+    ///
+    /// An empty result means no path with this prefix matches.
+    pub(crate) fn step(&self, states: &[usize], entry: &EntryId) -> Vec<usize> {
+        let mut next = Vec::new();
+        for &state in states {
+            match self.segment(state) {
+                Some(Segment::AnyEntries) => self.add_state(&mut next, state),
+                Some(Segment::AnyEntry) => self.add_state(&mut next, state + 1),
+                Some(Segment::Literal(expected)) if expected == entry.as_ref() => {
+                    self.add_state(&mut next, state + 1);
+                }
+                _ => {}
+            }
+        }
+        next
+    }
+
+    /// Returns `true` if the whole pattern is matched.
+    pub(crate) fn accepts(&self, states: &[usize]) -> bool {
+        states.contains(&self.path.0.len())
+    }
+
+    fn add_state(&self, states: &mut Vec<usize>, state: usize) {
+        if !states.contains(&state) {
+            states.push(state);
+            // `**` may match no entries
+            if self.segment(state) == Some(Segment::AnyEntries) {
+                self.add_state(states, state + 1);
+            }
+        }
+    }
+}
+
+/// Parses entries like a `Path`, but asterisks can be escaped too.
+impl FromStr for PathPattern {
+    type Err = PathError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let entries = parse_entries(s, &[WILDCARD])?
+            .into_iter()
+            .map(|(entry, escaped)| {
+                let entry_id = EntryId::new(entry)?;
+                let wildcard = entry_id.as_ref() == ANY_ENTRY || entry_id.as_ref() == ANY_ENTRIES;
+                if wildcard && !escaped {
+                    Ok(entry_id)
+                } else {
+                    Ok(Self::escape(entry_id))
+                }
+            })
+            .collect::<Result<Vec<_>, PathError>>()?;
+        Ok(Self {
+            path: entries.into(),
+        })
+    }
+}
+
+impl fmt::Display for PathPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (idx, segment) in self.segments().enumerate() {
+            if idx > 0 {
+                f.write_char(SEPARATOR)?;
+            }
+            match segment {
+                Segment::AnyEntry => f.write_str(ANY_ENTRY)?,
+                Segment::AnyEntries => f.write_str(ANY_ENTRIES)?,
+                Segment::Literal(entry) => write_entry(f, entry, &[WILDCARD])?,
+            }
+        }
+        Ok(())
+    }
+}
+
 impl<'de> Deserialize<'de> for PathPattern {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
         let s = String::deserialize(deserializer)?;
-        let path: Path = FromStr::from_str(&s).map_err(de::Error::custom)?;
-        Ok(PathPattern { path })
+        s.parse().map_err(de::Error::custom)
+    }
+}
+
+impl From<Path> for PathPattern {
+    fn from(path: Path) -> Self {
+        Self { path }
     }
 }
 
@@ -98,16 +383,29 @@ impl Path {
     pub fn is_meta(&self) -> bool {
         self.0
             .iter()
-            .any(|entry_id| entry_id.as_ref().contains("meta:"))
+            .any(|entry_id| entry_id.as_ref().starts_with(META_PREFIX))
     }
 
     pub fn is_hidden(&self) -> bool {
         self.0
-            .get(0)
-            .map(|entry_id| entry_id.as_ref().starts_with('@'))
+            .first()
+            .map(|entry_id| entry_id.as_ref().starts_with(HIDDEN_PREFIX))
             .unwrap_or_default()
     }
 
+    /// Checks the path is not empty and all entries are valid.
+    pub fn validate(&self) -> Result<(), PathError> {
+        if self.0.is_empty() {
+            return Err(PathError::EmptyPath);
+        }
+        self.0.iter().try_for_each(EntryId::validate)
+    }
+
+    /// Returns the first reserved entry of the path.
+    pub fn reserved(&self) -> Option<&EntryId> {
+        self.0.iter().find(|entry_id| entry_id.is_reserved())
+    }
+
     /*
     pub fn root() -> Self {
         Self(Vec::new())
@@ -177,13 +475,13 @@ impl IntoIterator for Path {
 
 impl Path {
     pub fn of_server(self) -> Self {
-        let mut server = vec!["@server".into()];
+        let mut server = vec![EntryId::from("@server")];
         server.extend(self);
         server.into()
     }
 
     pub fn of_client(self) -> Self {
-        let mut server = vec!["@self".into()];
+        let mut server = vec![EntryId::from("@self")];
         server.extend(self);
         server.into()
     }
@@ -199,39 +497,93 @@ impl AsRef<[EntryId]> for Path {
 
 impl fmt::Display for Path {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let mut prefix = false;
-        for entry in self.0.iter() {
-            if prefix {
-                ".".fmt(f)?;
-            } else {
-                prefix = true;
+        for (idx, entry) in self.0.iter().enumerate() {
+            if idx > 0 {
+                f.write_char(SEPARATOR)?;
             }
-            entry.fmt(f)?;
+            write_entry(f, entry.as_ref(), &[])?;
         }
         Ok(())
     }
 }
 
+/// Writes an entry with escaped separators, escapes and `special` characters.
+fn write_entry(f: &mut fmt::Formatter<'_>, entry: &str, special: &[char]) -> fmt::Result {
+    for c in entry.chars() {
+        if c == SEPARATOR || c == ESCAPE || special.contains(&c) {
+            f.write_char(ESCAPE)?;
+        }
+        f.write_char(c)?;
+    }
+    Ok(())
+}
+
 impl From<EntryId> for Path {
     fn from(entry_id: EntryId) -> Self {
         Self(vec![entry_id])
     }
 }
 
-#[derive(Error, Debug)]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
 pub enum PathError {
-    // Never constructed yet, because paths never fail now.
+    #[error("Path is empty.")]
+    EmptyPath,
+    #[error("Path contains an empty entry.")]
+    EmptyEntry,
+    #[error("Entry {0:?} contains a control character.")]
+    ControlChar(String),
+    #[error("Invalid escape sequence in {0:?}. Only dots, backslashes and asterisks of patterns can be escaped.")]
+    InvalidEscape(String),
+    #[error("Entry {0} is reserved.")]
+    Reserved(EntryId),
 }
 
+/// Parses entries separated by dots. Dots and backslashes
+/// inside entries are escaped with a backslash.
 impl FromStr for Path {
     type Err = PathError;
 
     fn from_str(s: &str) -> Result<Self, Self::Err> {
-        let entries: Vec<_> = s.split('.').map(EntryId::from).collect();
+        let entries = parse_entries(s, &[])?
+            .into_iter()
+            .map(|(entry, _)| EntryId::new(entry))
+            .collect::<Result<Vec<_>, _>>()?;
         Ok(Path::from(entries))
     }
 }
 
+/// Splits a formatted path into unescaped entries.
+///
+/// Every entry is paired with `true` if it had an escaped `special` character.
+fn parse_entries(s: &str, special: &[char]) -> Result<Vec<(String, bool)>, PathError> {
+    if s.is_empty() {
+        return Err(PathError::EmptyPath);
+    }
+    let mut entries = Vec::new();
+    let mut entry = String::new();
+    let mut escaped = false;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            ESCAPE => match chars.next() {
+                Some(c) if c == SEPARATOR || c == ESCAPE => entry.push(c),
+                Some(c) if special.contains(&c) => {
+                    escaped = true;
+                    entry.push(c);
+                }
+                _ => return Err(PathError::InvalidEscape(s.into())),
+            },
+            SEPARATOR => {
+                entries.push((std::mem::take(&mut entry), escaped));
+                escaped = false;
+            }
+            c => entry.push(c),
+        }
+    }
+    entries.push((entry, escaped));
+    Ok(entries)
+}
+
 // `i64` used, becuase it's widely supported as UTC timestamp
 // and for example it's used as timestamp value in BSON format.
 #[derive(
@@ -288,9 +640,9 @@ impl Timestamp {
 pub struct ProviderProtocol;
 
 impl Protocol for ProviderProtocol {
-    type ToServer = WideEnvelope<Self, ProviderToServer>;
-    type ToClient = Envelope<Self, ServerToProvider>;
-    type Codec = BinaryCodec;
+    type ToServer = Frame<WideEnvelope<Self, ProviderToServer>>;
+    type ToClient = Frame<ServiceEnvelope<Self, ServerToProvider, ProviderServiceRequest>>;
+    type Codec = FrameCodec<Self>;
 }
 
 impl Origin for ProviderProtocol {}
@@ -301,6 +653,30 @@ pub type ServerRequest = Envelope<ProviderProtocol, ServerToProvider>;
 pub type ProviderResponse = WideEnvelope<ProviderProtocol, ProviderToServer>;
 */
 
+/// Service messages of a server.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ProviderServiceRequest {
+    Ping,
+    /// The answer to the `Ping` of a provider.
+    Pong,
+    /// The answer to the `Handshake` of a provider.
+    Accepted(Negotiated),
+    /// No common version of the protocol. The server closes the connection.
+    Incompatible {
+        reason: String,
+    },
+    /// `ReadyToWork` is sent when the provider is verified and registered.
+    AccessLevel(AccessLevel),
+    /// Credentials were rejected by the server. The server closes the connection.
+    AccessDenied {
+        reason: String,
+    },
+    /// Another connected provider has the same name. The server closes the connection.
+    NameTaken {
+        name: EntryId,
+    },
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerToProvider {
     pub path: Path,
@@ -351,7 +727,32 @@ pub struct Description {
 macro_rules! packed {
     ($name:ident) => {
         #[derive(Clone, From, Into, Serialize, Deserialize, PartialEq, Eq)]
-        pub struct $name(pub Vec<u8>);
+        pub struct $name(Payload);
+
+        impl $name {
+            /// Encoded bytes of the payload.
+            pub fn as_bytes(&self) -> &[u8] {
+                self.0.as_bytes()
+            }
+        }
+
+        impl From<Vec<u8>> for $name {
+            fn from(data: Vec<u8>) -> Self {
+                Self(Payload::from(data))
+            }
+        }
+
+        impl From<$name> for Vec<u8> {
+            fn from(packed: $name) -> Self {
+                packed.0.into()
+            }
+        }
+
+        impl AsRef<[u8]> for $name {
+            fn as_ref(&self) -> &[u8] {
+                self.0.as_ref()
+            }
+        }
 
         impl fmt::Debug for $name {
             fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -361,9 +762,9 @@ macro_rules! packed {
             }
         }
 
-        impl AsRef<[u8]> for $name {
-            fn as_ref(&self) -> &[u8] {
-                self.0.as_ref()
+        impl Attach for $name {
+            fn payloads(&mut self) -> Vec<&mut Payload> {
+                vec![&mut self.0]
             }
         }
     };
@@ -377,6 +778,12 @@ packed!(PackedAction);
 pub enum ProviderToServer {
     Declare {
         description: Description,
+        /// Servers without authentication ignore it.
+        #[serde(default)]
+        credentials: Option<Credentials>,
+        /// Providers without a handshake talk the first version of the protocol.
+        #[serde(default)]
+        handshake: Option<Handshake>,
     },
     /// The response to `ControlStream { active: true }` request
     Flow {
@@ -390,7 +797,245 @@ pub enum ProviderToServer {
         delta: PackedEvent,
     },
     EndStream,
+    /// The action was delivered to a watcher of the flow.
+    ActionDelivered,
+    /// The answer to the `Ping` of a server.
+    Pong,
+    /// The provider checks the connection.
+    Ping,
     Error {
         reason: String,
     },
+    /// A message of a flow to a subscriber of a subtree of flows.
+    Tagged {
+        /// The path of the flow
+        path: Path,
+        data: Box<ProviderToServer>,
+    },
+}
+
+impl Attach for ServerToProvider {
+    fn payloads(&mut self) -> Vec<&mut Payload> {
+        self.request.payloads()
+    }
+}
+
+impl Attach for RecorderRequest {
+    fn payloads(&mut self) -> Vec<&mut Payload> {
+        match self {
+            Self::Action(RecorderAction::DoAction(action)) => action.payloads(),
+            Self::Action(_) | Self::ControlStream(_) => Vec::new(),
+        }
+    }
+}
+
+impl Attach for ProviderServiceRequest {
+    fn payloads(&mut self) -> Vec<&mut Payload> {
+        Vec::new()
+    }
+}
+
+impl Attach for ProviderToServer {
+    fn payloads(&mut self) -> Vec<&mut Payload> {
+        match self {
+            Self::State { state } => state.payloads(),
+            Self::Data { delta } => delta.payloads(),
+            Self::Tagged { data, .. } => data.payloads(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(s: &str) -> Path {
+        s.parse().unwrap()
+    }
+
+    fn pattern(s: &str) -> PathPattern {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn escaped_entries_roundtrip() {
+        let entries = vec![
+            EntryId::new("pod-1.example.com").unwrap(),
+            EntryId::new("back\\slash").unwrap(),
+            EntryId::new("latency").unwrap(),
+        ];
+        let path = Path::from(entries.clone());
+        let formatted = path.to_string();
+        assert_eq!(formatted, "pod-1\\.example\\.com.back\\\\slash.latency");
+        let parsed: Path = formatted.parse().unwrap();
+        assert_eq!(parsed, path);
+        assert_eq!(Vec::from(parsed), entries);
+    }
+
+    #[test]
+    fn invalid_paths() {
+        assert_eq!("".parse::<Path>(), Err(PathError::EmptyPath));
+        assert_eq!("a..b".parse::<Path>(), Err(PathError::EmptyEntry));
+        assert_eq!("a.".parse::<Path>(), Err(PathError::EmptyEntry));
+        assert!(matches!(
+            "a\\b".parse::<Path>(),
+            Err(PathError::InvalidEscape(_))
+        ));
+        assert!(matches!(
+            "a.b\\".parse::<Path>(),
+            Err(PathError::InvalidEscape(_))
+        ));
+        // Asterisks are escaped in patterns only
+        assert!(matches!(
+            "a.\\*".parse::<Path>(),
+            Err(PathError::InvalidEscape(_))
+        ));
+        assert!(matches!(
+            "a\u{7}".parse::<Path>(),
+            Err(PathError::ControlChar(_))
+        ));
+    }
+
+    #[test]
+    fn entries_are_validated() {
+        for value in &[
+            "a", "é", "a.b", "a\u{a0}", "", "a\u{7}", "a\u{7f}", "a\u{85}",
+        ] {
+            let expected = !value.is_empty() && !value.chars().any(char::is_control);
+            assert_eq!(EntryId::is_valid(value), expected, "{:?}", value);
+            assert_eq!(EntryId::new(*value).is_ok(), expected, "{:?}", value);
+        }
+        let data = crate::encoding::to_vec(&EntryId::from("a\u{7}")).unwrap();
+        assert!(crate::encoding::from_slice::<EntryId>(&data).is_err());
+        assert_eq!(EntryId::from_static(""), Err(PathError::EmptyEntry),);
+    }
+
+    #[test]
+    fn reserved_entries() {
+        assert!(path("@server.a").is_hidden());
+        assert!(!path("a.@server").is_hidden());
+        assert!(path("a.meta:ready").is_meta());
+        assert_eq!(path("a.b").reserved(), None);
+        assert_eq!(
+            path("a.meta:ready.b").reserved().map(AsRef::as_ref),
+            Some("meta:ready")
+        );
+        assert_eq!(path("a.*").reserved().map(AsRef::as_ref), Some("*"));
+    }
+
+    #[test]
+    fn single_entry_wildcard() {
+        let pattern = pattern("myapp.*.latency");
+        assert!(!pattern.is_exact());
+        assert!(pattern.matches(&path("myapp.db.latency")));
+        assert!(pattern.matches(&path("myapp.cache.latency")));
+        assert!(!pattern.matches(&path("myapp.latency")));
+        assert!(!pattern.matches(&path("myapp.db.pool.latency")));
+        assert!(!pattern.matches(&path("other.db.latency")));
+    }
+
+    #[test]
+    fn any_depth_wildcard() {
+        let pattern = pattern("myapp.**");
+        assert!(pattern.matches(&path("myapp")));
+        assert!(pattern.matches(&path("myapp.db")));
+        assert!(pattern.matches(&path("myapp.db.pool.latency")));
+        assert!(!pattern.matches(&path("other.db")));
+
+        let pattern = self::pattern("**.latency");
+        assert!(pattern.matches(&path("latency")));
+        assert!(pattern.matches(&path("myapp.db.latency")));
+        assert!(!pattern.matches(&path("myapp.latency.max")));
+
+        let pattern = self::pattern("a.**.b.*.c");
+        assert!(pattern.matches(&path("a.b.x.c")));
+        assert!(pattern.matches(&path("a.b.b.x.c")));
+        assert!(pattern.matches(&path("a.x.y.b.z.c")));
+        assert!(!pattern.matches(&path("a.b.c")));
+        assert!(!pattern.matches(&path("a.x.b.z.c.d")));
+    }
+
+    #[test]
+    fn exact_pattern() {
+        let pattern = pattern("myapp.db");
+        assert!(pattern.is_exact());
+        assert_eq!(pattern.exact_path(), Some(path("myapp.db")));
+        assert!(pattern.matches(&path("myapp.db")));
+        assert!(!pattern.matches(&path("myapp")));
+        assert!(!pattern.matches(&path("myapp.db.latency")));
+    }
+
+    #[test]
+    fn subtree_pattern() {
+        let pattern = PathPattern::subtree(path("myapp"));
+        assert_eq!(pattern, self::pattern("myapp.**"));
+        assert!(pattern.matches(&path("myapp")));
+        assert!(pattern.matches(&path("myapp.db.latency")));
+        assert!(!pattern.matches(&path("other")));
+    }
+
+    #[test]
+    fn escaped_wildcards() {
+        let star = EntryId::new("*").unwrap();
+        let literal = Path::from(vec![EntryId::new("a").unwrap(), star.clone()]);
+        let other = path("a.b");
+
+        let pattern = pattern("a.\\*");
+        assert!(pattern.is_exact());
+        assert_eq!(pattern.exact_path(), Some(literal.clone()));
+        assert!(pattern.matches(&literal));
+        assert!(!pattern.matches(&other));
+        assert_eq!(pattern.to_string(), "a.\\*");
+
+        // Unescaped wildcards match the literal entry as well
+        assert!(self::pattern("a.*").matches(&literal));
+        assert!(self::pattern("a.*").matches(&other));
+
+        let pattern = self::pattern("a.\\**");
+        assert!(pattern.matches(&Path::from(vec![
+            EntryId::new("a").unwrap(),
+            EntryId::new("**").unwrap()
+        ])));
+        assert!(!pattern.matches(&other));
+        assert!(!pattern.matches(&path("a")));
+
+        let pattern = PathPattern::exact(literal.clone());
+        assert_eq!(pattern, self::pattern("a.\\*"));
+        assert!(pattern.matches(&literal));
+        assert!(!pattern.matches(&other));
+    }
+
+    #[test]
+    fn escaped_backslashes_in_patterns() {
+        let entry = EntryId::new("\\x").unwrap();
+        let literal = Path::from(vec![entry.clone()]);
+        let pattern = pattern("\\\\x");
+        assert_eq!(pattern.path, Path::from(vec![PathPattern::escape(entry)]));
+        assert_eq!(pattern.exact_path(), Some(literal.clone()));
+        assert!(pattern.matches(&literal));
+        assert_eq!(pattern.to_string(), "\\\\x");
+        assert_eq!(PathPattern::exact(literal), pattern);
+    }
+
+    #[test]
+    fn patterns_roundtrip() {
+        for s in &[
+            "a.*.b",
+            "a.**",
+            "a.\\*.b",
+            "a\\*b.c",
+            "a\\.b.*",
+            "\\\\.\\*\\*",
+        ] {
+            let pattern = pattern(s);
+            let formatted = pattern.to_string();
+            assert_eq!(&formatted, s);
+            assert_eq!(formatted.parse::<PathPattern>().unwrap(), pattern);
+            // Patterns are sent as paths
+            assert_eq!(PathPattern::from(Path::from(pattern.clone())), pattern);
+        }
+        // A single escape makes the whole entry literal
+        assert_eq!(pattern("a.\\**"), pattern("a.\\*\\*"));
+    }
 }
@@ -1,3 +1,4 @@
+use crate::io::payload::{Attach, Payload};
 use serde::{Deserialize, Serialize};
 use std::collections::HashSet;
 use std::fmt;
@@ -23,6 +24,27 @@ pub struct WideEnvelope<T: Origin, D> {
     pub data: D,
 }
 
+impl<T: Origin, D: Attach, S: Attach> Attach for ServiceEnvelope<T, D, S> {
+    fn payloads(&mut self) -> Vec<&mut Payload> {
+        match self {
+            Self::Service(service) => service.payloads(),
+            Self::Envelope(envelope) => envelope.payloads(),
+        }
+    }
+}
+
+impl<T: Origin, D: Attach> Attach for Envelope<T, D> {
+    fn payloads(&mut self) -> Vec<&mut Payload> {
+        self.data.payloads()
+    }
+}
+
+impl<T: Origin, D: Attach> Attach for WideEnvelope<T, D> {
+    fn payloads(&mut self) -> Vec<&mut Payload> {
+        self.data.payloads()
+    }
+}
+
 /// The origin of `DirectId`.
 pub trait Origin: Default + Clone + PartialEq + Eq + Hash {}
 
@@ -1,4 +1,5 @@
 pub mod calc;
+pub mod compression;
 pub mod config;
 pub mod encoding;
 pub mod flow;
@@ -1,9 +1,14 @@
-use crate::io::provider::{EntryId, Path};
+use crate::io::provider::{EntryId, Path, PathPattern};
 use derive_more::{Deref, DerefMut};
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 /// Universal storage with `EntryId` hierarchy.
-#[derive(Debug, Deref, DerefMut)]
+///
+/// It's serialized as nested maps of entries with links.
+#[derive(Debug, Deref, DerefMut, Serialize, Deserialize)]
+#[serde(transparent)]
+#[serde(bound(serialize = "T: Serialize", deserialize = "T: Deserialize<'de>"))]
 pub struct Pathfinder<T> {
     root: Record<T>,
 }
@@ -22,9 +27,12 @@ impl<T> Default for Pathfinder<T> {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(bound(serialize = "T: Serialize", deserialize = "T: Deserialize<'de>"))]
 pub struct Record<T> {
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
     subs: HashMap<EntryId, Record<T>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     link: Option<T>,
 }
 
@@ -63,7 +71,7 @@ impl<T> Record<T> {
         let mut record = self;
         let mut iter = path.as_ref().iter();
         let mut remained = Vec::new();
-        while let Some(element) = iter.next() {
+        for element in iter.by_ref() {
             if let Some(next_record) = record.subs.get(element) {
                 record = next_record;
             } else {
@@ -78,19 +86,46 @@ impl<T> Record<T> {
         }
     }
 
+    /// Iterates over nested records with paths that match the `pattern`.
+    ///
+    /// Paths are relative to this record. Records without links are
+    /// included too. The order of records is not specified.
+    pub fn find_matching<'a>(&'a self, pattern: &'a PathPattern) -> FindMatching<'a, T> {
+        FindMatching {
+            pattern,
+            stack: vec![(Path::from(Vec::new()), self, pattern.start())],
+        }
+    }
+
+    /// Removes the `Record` with all nested records.
+    ///
+    /// Parent records that become empty are removed too.
     pub fn remove(&mut self, path: &Path) -> Option<Self> {
-        let mut record = self;
-        let mut iter = path.as_ref().iter();
-        while let Some(element) = iter.next() {
-            if iter.len() == 0 {
-                return record.subs.remove(element);
-            } else if let Some(next_record) = record.subs.get_mut(element) {
-                record = next_record;
-            } else {
-                break;
+        if path.is_empty() {
+            return None;
+        }
+        self.prune_with(path, std::mem::take)
+    }
+
+    /// Takes the link of the `Record` for the `Path`.
+    ///
+    /// The record and its parents are removed if they become empty.
+    pub fn remove_link(&mut self, path: &Path) -> Option<T> {
+        self.prune_with(path, Record::take_link).flatten()
+    }
+
+    fn prune_with<R>(&mut self, entries: &[EntryId], op: impl FnOnce(&mut Self) -> R) -> Option<R> {
+        match entries.split_first() {
+            Some((element, rest)) => {
+                let record = self.subs.get_mut(element)?;
+                let result = record.prune_with(rest, op);
+                if record.is_empty() {
+                    self.subs.remove(element);
+                }
+                result
             }
+            None => Some(op(self)),
         }
-        None
     }
 
     /// Returns the `Record` for the `Path` or `None` if the `Record` not
@@ -147,4 +182,304 @@ impl<T> Record<T> {
     pub fn has_link(&self) -> bool {
         self.link.is_some()
     }
+
+    /// Returns `true` if the record has no link and no nested records.
+    pub fn is_empty(&self) -> bool {
+        self.link.is_none() && self.subs.is_empty()
+    }
+
+    /// Iterates over links of nested records depth-first.
+    ///
+    /// Paths are relative to this record.
+    pub fn walk(&self) -> Walk<'_, T> {
+        Walk {
+            stack: vec![(Path::from(Vec::new()), self)],
+        }
+    }
+
+    /// Iterates over mutable links of nested records depth-first.
+    ///
+    /// Paths are relative to this record.
+    pub fn walk_mut(&mut self) -> WalkMut<'_, T> {
+        WalkMut {
+            stack: vec![(Path::from(Vec::new()), self)],
+        }
+    }
+}
+
+/// The depth-first iterator over links of records.
+pub struct Walk<'a, T> {
+    stack: Vec<(Path, &'a Record<T>)>,
+}
+
+impl<'a, T> Iterator for Walk<'a, T> {
+    type Item = (Path, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((path, record)) = self.stack.pop() {
+            for (entry_id, sub) in record.subs.iter() {
+                let mut sub_path = path.clone();
+                sub_path.extend(Some(entry_id.clone()));
+                self.stack.push((sub_path, sub));
+            }
+            if let Some(link) = record.link.as_ref() {
+                return Some((path, link));
+            }
+        }
+        None
+    }
+}
+
+/// The depth-first iterator over mutable links of records.
+pub struct WalkMut<'a, T> {
+    stack: Vec<(Path, &'a mut Record<T>)>,
+}
+
+impl<'a, T> Iterator for WalkMut<'a, T> {
+    type Item = (Path, &'a mut T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((path, record)) = self.stack.pop() {
+            let Record { subs, link } = record;
+            for (entry_id, sub) in subs.iter_mut() {
+                let mut sub_path = path.clone();
+                sub_path.extend(Some(entry_id.clone()));
+                self.stack.push((sub_path, sub));
+            }
+            if let Some(link) = link.as_mut() {
+                return Some((path, link));
+            }
+        }
+        None
+    }
+}
+
+/// The iterator over records that match a pattern.
+///
+/// Branches that can't match the pattern are not visited.
+pub struct FindMatching<'a, T> {
+    pattern: &'a PathPattern,
+    stack: Vec<(Path, &'a Record<T>, Vec<usize>)>,
+}
+
+impl<'a, T> Iterator for FindMatching<'a, T> {
+    type Item = (Path, &'a Record<T>);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((path, record, states)) = self.stack.pop() {
+            for (entry_id, sub) in record.subs.iter() {
+                let next = self.pattern.step(&states, entry_id);
+                if !next.is_empty() {
+                    let mut sub_path = path.clone();
+                    sub_path.extend(Some(entry_id.clone()));
+                    self.stack.push((sub_path, sub, next));
+                }
+            }
+            if self.pattern.accepts(&states) {
+                return Some((path, record));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pathfinder(paths: &[&str]) -> Pathfinder<String> {
+        let mut pathfinder = Pathfinder::new();
+        for path in paths {
+            pathfinder
+                .dig(path.parse().unwrap())
+                .set_link(path.to_string());
+        }
+        pathfinder
+    }
+
+    fn matching(pathfinder: &Pathfinder<String>, pattern: &str) -> Vec<String> {
+        let pattern: PathPattern = pattern.parse().unwrap();
+        let mut found: Vec<_> = pathfinder
+            .find_matching(&pattern)
+            .map(|(path, record)| {
+                let link = record.get_link().cloned();
+                // Paths are relative to the root
+                assert_eq!(Some(path.to_string()), link);
+                path.to_string()
+            })
+            .collect();
+        found.sort();
+        found
+    }
+
+    #[test]
+    fn find_matching_records() {
+        let pathfinder = pathfinder(&[
+            "myapp",
+            "myapp.db.latency",
+            "myapp.cache.latency",
+            "myapp.db.pool.latency",
+            "other.db.latency",
+        ]);
+        assert_eq!(
+            matching(&pathfinder, "myapp.*.latency"),
+            vec!["myapp.cache.latency", "myapp.db.latency"]
+        );
+        assert_eq!(
+            matching(&pathfinder, "**.latency"),
+            vec![
+                "myapp.cache.latency",
+                "myapp.db.latency",
+                "myapp.db.pool.latency",
+                "other.db.latency",
+            ]
+        );
+        assert_eq!(
+            matching(&pathfinder, "*.db.latency"),
+            vec!["myapp.db.latency", "other.db.latency"]
+        );
+        assert_eq!(matching(&pathfinder, "myapp"), vec!["myapp"]);
+        assert!(matching(&pathfinder, "myapp.*.*.*.latency").is_empty());
+        assert!(matching(&pathfinder, "unknown.**").is_empty());
+    }
+
+    #[test]
+    fn find_matching_includes_records_without_links() {
+        let pathfinder = pathfinder(&["a.b.c"]);
+        let pattern: PathPattern = "a.**".parse().unwrap();
+        let mut found: Vec<_> = pathfinder
+            .find_matching(&pattern)
+            .map(|(path, record)| (path.to_string(), record.has_link()))
+            .collect();
+        found.sort();
+        assert_eq!(
+            found,
+            vec![
+                ("a".to_string(), false),
+                ("a.b".to_string(), false),
+                ("a.b.c".to_string(), true),
+            ]
+        );
+    }
+
+    #[test]
+    fn find_matching_literal_wildcards() {
+        let mut pathfinder = pathfinder(&["a.b"]);
+        let literal = Path::from(vec![EntryId::new("a").unwrap(), EntryId::new("*").unwrap()]);
+        pathfinder
+            .dig(literal.clone())
+            .set_link(literal.to_string());
+        assert_eq!(matching(&pathfinder, "a.\\*"), vec!["a.*"]);
+        assert_eq!(matching(&pathfinder, "a.*"), vec!["a.*", "a.b"]);
+    }
+
+    fn walked(record: &Record<String>) -> Vec<(String, String)> {
+        let mut walked: Vec<_> = record
+            .walk()
+            .map(|(path, link)| (path.to_string(), link.clone()))
+            .collect();
+        walked.sort();
+        walked
+    }
+
+    #[test]
+    fn walk_yields_full_paths() {
+        let pathfinder = pathfinder(&["a", "a.b.c", "a.d", "e.f"]);
+        assert_eq!(
+            walked(&pathfinder),
+            vec![
+                ("a".to_string(), "a".to_string()),
+                ("a.b.c".to_string(), "a.b.c".to_string()),
+                ("a.d".to_string(), "a.d".to_string()),
+                ("e.f".to_string(), "e.f".to_string()),
+            ]
+        );
+        // Paths are relative to the record
+        let record = pathfinder.find(&"a.b".parse().unwrap()).unwrap();
+        assert_eq!(walked(record), vec![("c".to_string(), "a.b.c".to_string())]);
+    }
+
+    #[test]
+    fn walk_mut_changes_links() {
+        let mut pathfinder = pathfinder(&["a", "a.b.c", "e.f"]);
+        for (path, link) in pathfinder.walk_mut() {
+            assert_eq!(&path.to_string(), link);
+            link.push('!');
+        }
+        assert_eq!(
+            walked(&pathfinder),
+            vec![
+                ("a".to_string(), "a!".to_string()),
+                ("a.b.c".to_string(), "a.b.c!".to_string()),
+                ("e.f".to_string(), "e.f!".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn remove_link_prunes_empty_parents() {
+        let mut pathfinder = pathfinder(&["a.b.c", "a.d", "e.f.g"]);
+        assert_eq!(
+            pathfinder.remove_link(&"e.f.g".parse().unwrap()),
+            Some("e.f.g".to_string())
+        );
+        assert!(pathfinder.find(&"e".parse().unwrap()).is_none());
+
+        assert_eq!(
+            pathfinder.remove_link(&"a.b.c".parse().unwrap()),
+            Some("a.b.c".to_string())
+        );
+        assert!(pathfinder.find(&"a.b".parse().unwrap()).is_none());
+        // The parent with another child is kept
+        assert!(pathfinder.find(&"a".parse().unwrap()).is_some());
+        assert_eq!(
+            walked(&pathfinder),
+            vec![("a.d".to_string(), "a.d".to_string())]
+        );
+
+        // Records with nested records are kept without links
+        let mut pathfinder = self::pathfinder(&["a", "a.b"]);
+        assert_eq!(
+            pathfinder.remove_link(&"a".parse().unwrap()),
+            Some("a".to_string())
+        );
+        assert_eq!(
+            walked(&pathfinder),
+            vec![("a.b".to_string(), "a.b".to_string())]
+        );
+        assert_eq!(pathfinder.remove_link(&"x.y".parse().unwrap()), None);
+    }
+
+    #[test]
+    fn remove_subtrees() {
+        let mut pathfinder = pathfinder(&["a.b.c", "a.b.d", "a.e"]);
+        assert!(pathfinder.remove(&Path::from(Vec::new())).is_none());
+        assert_eq!(walked(&pathfinder).len(), 3);
+
+        let removed = pathfinder.remove(&"a.b".parse().unwrap()).unwrap();
+        assert_eq!(walked(&removed).len(), 2);
+        assert_eq!(
+            walked(&pathfinder),
+            vec![("a.e".to_string(), "a.e".to_string())]
+        );
+        assert!(pathfinder.remove(&"a.b".parse().unwrap()).is_none());
+    }
+
+    #[test]
+    fn serde_roundtrip() {
+        let pathfinder = pathfinder(&["a.b", "c"]);
+        let value = serde_json::to_value(&pathfinder).unwrap();
+        // The root is not wrapped and empty fields are skipped
+        assert_eq!(
+            value,
+            serde_json::json!({
+                "subs": {
+                    "a": { "subs": { "b": { "link": "a.b" } } },
+                    "c": { "link": "c" },
+                }
+            })
+        );
+        let restored: Pathfinder<String> = serde_json::from_value(value).unwrap();
+        assert_eq!(walked(&restored), walked(&pathfinder));
+    }
 }
@@ -60,6 +60,7 @@ impl Default for Pct {
 }
 
 impl Pct {
+    #[allow(clippy::manual_clamp)]
     pub fn from_value(mut value: f64) -> Self {
         // TODO: Use `clamp` here.
         if value < 0.0 {
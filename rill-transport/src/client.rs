@@ -0,0 +1,183 @@
+//! The client of `tcp://`, `unix://` and `wss://` endpoints.
+
+use crate::endpoint::{self, Endpoint};
+use crate::sender::TransportSender;
+use crate::tls::TlsConnector;
+use crate::{framed, ws};
+use anyhow::Error;
+use async_trait::async_trait;
+use futures::channel::mpsc;
+use meio::{
+    ActionHandler, Actor, Address, InstantAction, InstantActionHandler, LiteTask, StopReceiver,
+};
+use meio_connect::client::WsFailReason;
+use meio_connect::WsIncoming;
+use meio_protocol::Protocol;
+use std::marker::PhantomData;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+use tokio::net::TcpStream;
+use tokio::time::sleep;
+
+/// The status of a connection.
+#[derive(Debug)]
+pub enum TransportStatus<P: Protocol> {
+    /// The connection established.
+    Connected {
+        /// The sender of outgoing messages.
+        sender: TransportSender<P::ToServer>,
+    },
+    /// The connection failed or closed.
+    Failed {
+        /// The reason of the failure.
+        reason: WsFailReason,
+    },
+}
+
+impl<P: Protocol> InstantAction for TransportStatus<P> {}
+
+#[derive(Error, Debug)]
+enum ClientError {
+    #[error("Endpoint {0} is served by WsClient.")]
+    PlainWs(Endpoint),
+    #[error("No TLS settings to connect to {0}")]
+    NoTls(Endpoint),
+    #[cfg(not(unix))]
+    #[error("Unix domain sockets are not supported on this platform.")]
+    UnixNotSupported,
+}
+
+/// Connects an actor to a node.
+///
+/// It reconnects to the node till stopped and reports
+/// the status of the connection as `TransportStatus`.
+/// Incoming messages are delivered as `WsIncoming`.
+pub struct TransportClient<P, A>
+where
+    A: Actor,
+{
+    endpoint: Endpoint,
+    tls: Option<TlsConnector>,
+    repeat_interval: Option<Duration>,
+    address: Address<A>,
+    _protocol: PhantomData<P>,
+}
+
+impl<P, A> TransportClient<P, A>
+where
+    P: Protocol,
+    A: Actor + InstantActionHandler<TransportStatus<P>> + ActionHandler<WsIncoming<P::ToClient>>,
+{
+    /// Creates a client for a `tcp://`, `unix://` or `wss://` endpoint.
+    ///
+    /// `TlsConnector` is required by `wss://` endpoints only.
+    pub fn new(
+        endpoint: Endpoint,
+        tls: Option<TlsConnector>,
+        repeat_interval: Option<Duration>,
+        address: Address<A>,
+    ) -> Self {
+        Self {
+            endpoint,
+            tls,
+            repeat_interval,
+            address,
+            _protocol: PhantomData,
+        }
+    }
+
+    /// Returns the result of a session if the connection was established.
+    async fn connect_and_talk(&mut self) -> Result<Result<(), Error>, Error> {
+        let (tx, rx) = mpsc::unbounded();
+        let address = self.address.clone();
+        match self.endpoint.clone() {
+            Endpoint::Tcp(addr) => {
+                let stream = TcpStream::connect(addr).await?;
+                stream.set_nodelay(true)?;
+                self.connected(tx)?;
+                Ok(framed::talk::<P, _, _>(address, stream, rx).await)
+            }
+            #[cfg(unix)]
+            Endpoint::Unix(path) => {
+                let stream = tokio::net::UnixStream::connect(path).await?;
+                self.connected(tx)?;
+                Ok(framed::talk::<P, _, _>(address, stream, rx).await)
+            }
+            #[cfg(not(unix))]
+            Endpoint::Unix(_) => Err(ClientError::UnixNotSupported.into()),
+            Endpoint::Wss(url) => {
+                let tls = self
+                    .tls
+                    .clone()
+                    .ok_or_else(|| ClientError::NoTls(self.endpoint.clone()))?;
+                let (host, port) = endpoint::host_and_port(&url)?;
+                let stream = TcpStream::connect((host.as_str(), port)).await?;
+                stream.set_nodelay(true)?;
+                let stream = tls.connect(&host, stream).await?;
+                let (connection, _resp) = tokio_tungstenite::client_async(&url, stream).await?;
+                self.connected(tx)?;
+                Ok(ws::talk::<P, _, _>(address, connection, rx).await)
+            }
+            endpoint @ Endpoint::Ws(_) => Err(ClientError::PlainWs(endpoint).into()),
+        }
+    }
+
+    fn connected(&mut self, tx: mpsc::UnboundedSender<P::ToServer>) -> Result<(), Error> {
+        log::debug!("Client connected successfully to: {}", self.endpoint);
+        let sender = TransportSender::from(tx);
+        self.address
+            .instant(TransportStatus::<P>::Connected { sender })
+    }
+}
+
+#[async_trait]
+impl<P, A> LiteTask for TransportClient<P, A>
+where
+    P: Protocol,
+    A: Actor + InstantActionHandler<TransportStatus<P>> + ActionHandler<WsIncoming<P::ToClient>>,
+{
+    type Output = ();
+
+    fn name(&self) -> String {
+        format!("TransportClient({})", self.endpoint)
+    }
+
+    async fn routine(mut self, mut stop: StopReceiver) -> Result<Self::Output, Error> {
+        while stop.is_alive() {
+            log::trace!("Client connecting to: {}", self.endpoint);
+            let last_attempt = Instant::now();
+            let res = stop.or(self.connect_and_talk()).await;
+            let (reason, original_err) = match res {
+                Err(_stopped) => {
+                    log::info!("Interrupted by a user");
+                    return Ok(());
+                }
+                Ok(Ok(Ok(()))) => {
+                    log::error!("Server closed a connection");
+                    let reason = WsFailReason::ClosedByServer;
+                    (reason.clone(), reason.into())
+                }
+                Ok(Ok(Err(err))) => {
+                    log::error!("Connection to {} failed: {}", self.endpoint, err);
+                    (WsFailReason::ConnectionFailed, err)
+                }
+                Ok(Err(err)) => {
+                    log::error!("Can't connect to {}: {}", self.endpoint, err);
+                    (WsFailReason::ServerNotAvailable, err)
+                }
+            };
+            self.address
+                .instant(TransportStatus::<P>::Failed { reason })?;
+            if let Some(dur) = self.repeat_interval {
+                let elapsed = last_attempt.elapsed();
+                if elapsed < dur {
+                    stop.or(sleep(dur - elapsed)).await?;
+                }
+                log::debug!("Next attempt to connect to: {}", self.endpoint);
+            } else {
+                return Err(original_err);
+            }
+        }
+        Ok(())
+    }
+}
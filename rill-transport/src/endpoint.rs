@@ -0,0 +1,82 @@
+//! Addresses of nodes.
+
+use anyhow::Error;
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+use thiserror::Error;
+use tungstenite::http::Uri;
+
+/// The address of a node with the transport to reach it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Endpoint {
+    /// WebSocket url (`ws://host:port/path`).
+    Ws(String),
+    /// WebSocket over TLS url (`wss://host:port/path`).
+    Wss(String),
+    /// Framed TCP (`tcp://host:port`).
+    Tcp(String),
+    /// Framed Unix domain socket (`unix:///path/to/socket`).
+    Unix(PathBuf),
+}
+
+/// The url of an endpoint can't be parsed.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum EndpointError {
+    /// The url has no scheme.
+    #[error("No scheme in the url: {0}")]
+    NoScheme(String),
+    /// The scheme is not supported.
+    #[error("Unsupported scheme: {0}")]
+    UnsupportedScheme(String),
+    /// The url has no address after the scheme.
+    #[error("No address in the url: {0}")]
+    NoAddress(String),
+    /// The url has no host.
+    #[error("No host in the url: {0}")]
+    NoHost(String),
+}
+
+impl FromStr for Endpoint {
+    type Err = EndpointError;
+
+    fn from_str(url: &str) -> Result<Self, Self::Err> {
+        let (scheme, rest) = url
+            .split_once("://")
+            .ok_or_else(|| EndpointError::NoScheme(url.into()))?;
+        if rest.is_empty() {
+            return Err(EndpointError::NoAddress(url.into()));
+        }
+        match scheme {
+            "ws" => Ok(Self::Ws(url.into())),
+            "wss" => Ok(Self::Wss(url.into())),
+            "tcp" => Ok(Self::Tcp(rest.into())),
+            "unix" => Ok(Self::Unix(rest.into())),
+            other => Err(EndpointError::UnsupportedScheme(other.into())),
+        }
+    }
+}
+
+impl fmt::Display for Endpoint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Ws(url) | Self::Wss(url) => url.fmt(f),
+            Self::Tcp(addr) => write!(f, "tcp://{}", addr),
+            Self::Unix(path) => write!(f, "unix://{}", path.display()),
+        }
+    }
+}
+
+/// The host and the port of a `wss://` url.
+///
+/// Brackets of an IPv6 host are removed to resolve the address.
+pub(crate) fn host_and_port(url: &str) -> Result<(String, u16), Error> {
+    let uri: Uri = url.parse()?;
+    let host = uri
+        .host()
+        .ok_or_else(|| EndpointError::NoHost(url.into()))?
+        .trim_start_matches('[')
+        .trim_end_matches(']');
+    let port = uri.port_u16().unwrap_or(443);
+    Ok((host.into(), port))
+}
@@ -0,0 +1,131 @@
+//! Length-prefixed frames over TCP and Unix domain sockets.
+//!
+//! Every frame is a big-endian `u32` length followed by
+//! a message encoded with the codec of the protocol.
+
+use anyhow::Error;
+use futures::channel::mpsc;
+use futures::future::{self, Either};
+use futures::{Future, StreamExt};
+use meio::{ActionHandler, Actor, Address};
+use meio_connect::WsIncoming;
+use meio_protocol::{Protocol, ProtocolCodec, ProtocolData};
+use std::convert::TryFrom;
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// The limit of a single frame.
+pub const MAX_FRAME_SIZE: u32 = 64 * 1024 * 1024;
+
+#[derive(Error, Debug)]
+#[error("Frame size {0} exceeds the limit.")]
+struct TooLarge(usize);
+
+/// The connection was dropped by the node.
+#[derive(Error, Debug)]
+#[error("Connection dropped.")]
+pub(crate) struct ConnectionDropped;
+
+/// Exchanges frames of a client till one of the sides closes the connection.
+pub(crate) async fn talk<P, A, S>(
+    address: Address<A>,
+    stream: S,
+    rx: mpsc::UnboundedReceiver<P::ToServer>,
+) -> Result<(), Error>
+where
+    P: Protocol,
+    A: Actor + ActionHandler<WsIncoming<P::ToClient>>,
+    S: AsyncRead + AsyncWrite + Send,
+{
+    exchange::<P::Codec, _, _, _, _, _>(stream, rx, move |msg| {
+        let mut address = address.clone();
+        async move { address.act(WsIncoming(msg)).await }
+    })
+    .await
+}
+
+/// Exchanges frames of an accepted connection till one of the sides closes it.
+pub(crate) async fn serve<P, S>(
+    stream: S,
+    tx: mpsc::UnboundedSender<P::ToServer>,
+    rx: mpsc::UnboundedReceiver<P::ToClient>,
+) -> Result<(), Error>
+where
+    P: Protocol,
+    S: AsyncRead + AsyncWrite + Send,
+{
+    exchange::<P::Codec, _, _, _, _, _>(stream, rx, move |msg| {
+        future::ready(tx.unbounded_send(msg).map_err(|_| ConnectionDropped.into()))
+    })
+    .await
+}
+
+async fn exchange<C, I, O, S, F, Fut>(
+    stream: S,
+    mut rx: mpsc::UnboundedReceiver<O>,
+    mut deliver: F,
+) -> Result<(), Error>
+where
+    C: ProtocolCodec,
+    I: ProtocolData,
+    O: ProtocolData,
+    S: AsyncRead + AsyncWrite + Send,
+    F: FnMut(I) -> Fut,
+    Fut: Future<Output = Result<(), Error>>,
+{
+    let (mut reader, mut writer) = tokio::io::split(stream);
+    let reading = async move {
+        while let Some(msg) = read_frame::<_, C, I>(&mut reader).await? {
+            deliver(msg).await?;
+        }
+        Ok::<_, Error>(())
+    };
+    let writing = async move {
+        while let Some(msg) = rx.next().await {
+            write_frame::<_, C, O>(&mut writer, msg).await?;
+        }
+        Ok::<_, Error>(())
+    };
+    futures::pin_mut!(reading, writing);
+    match future::select(reading, writing).await {
+        Either::Left((res, _)) | Either::Right((res, _)) => res,
+    }
+}
+
+/// Reads a frame. Returns `None` if the stream closed between frames.
+pub async fn read_frame<R, C, T>(reader: &mut R) -> Result<Option<T>, Error>
+where
+    R: AsyncRead + Unpin,
+    C: ProtocolCodec,
+    T: ProtocolData,
+{
+    let len = match reader.read_u32().await {
+        Ok(len) => len,
+        Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    if len > MAX_FRAME_SIZE {
+        return Err(TooLarge(len as usize).into());
+    }
+    let mut data = vec![0; len as usize];
+    reader.read_exact(&mut data).await?;
+    C::decode(&data).map(Some)
+}
+
+/// Writes a frame.
+pub async fn write_frame<W, C, T>(writer: &mut W, msg: T) -> Result<(), Error>
+where
+    W: AsyncWrite + Unpin,
+    C: ProtocolCodec,
+    T: ProtocolData,
+{
+    let data = C::encode(&msg)?;
+    let len = u32::try_from(data.len())
+        .ok()
+        .filter(|len| *len <= MAX_FRAME_SIZE)
+        .ok_or(TooLarge(data.len()))?;
+    writer.write_u32(len).await?;
+    writer.write_all(&data).await?;
+    writer.flush().await?;
+    Ok(())
+}
@@ -0,0 +1,23 @@
+//! Transports of providers and clients.
+
+#![warn(missing_docs)]
+
+pub mod client;
+pub mod endpoint;
+pub mod framed;
+pub mod listener;
+pub mod loopback;
+pub mod reconnect;
+pub mod sender;
+pub mod server;
+pub mod tls;
+mod ws;
+
+pub use client::{TransportClient, TransportStatus};
+pub use endpoint::Endpoint;
+pub use listener::{Accepted, TransportListener};
+pub use loopback::{loopback, Loopback, LoopbackAcceptor};
+pub use reconnect::{Backoff, ConnectionStatus, ReconnectPolicy};
+pub use sender::TransportSender;
+pub use server::Connection;
+pub use tls::{TlsAcceptor, TlsConfig, TlsConnector, TlsServerConfig};
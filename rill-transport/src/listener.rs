@@ -0,0 +1,251 @@
+//! The listener of connections of peers.
+
+use crate::endpoint::{self, Endpoint};
+use crate::loopback::LoopbackAcceptor;
+use crate::server::Connection;
+use crate::tls::TlsAcceptor;
+use crate::{framed, ws};
+use anyhow::Error;
+use async_trait::async_trait;
+use futures::Future;
+use meio::{Action, ActionHandler, Actor, Address, LiteTask, StopReceiver};
+use meio_protocol::Protocol;
+use thiserror::Error;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::{TcpListener, TcpStream};
+
+/// A connection accepted by a `TransportListener`.
+pub struct Accepted<P: Protocol> {
+    /// The accepted connection.
+    pub connection: Connection<P>,
+}
+
+impl<P: Protocol> Action for Accepted<P> {}
+
+#[derive(Error, Debug)]
+enum ListenerError {
+    #[error("Endpoint {0} is served by HttpServer.")]
+    PlainWs(Endpoint),
+    #[error("No TLS settings to listen to {0}")]
+    NoTls(Endpoint),
+    #[cfg(not(unix))]
+    #[error("Unix domain sockets are not supported on this platform.")]
+    UnixNotSupported,
+}
+
+enum Source<P: Protocol> {
+    Endpoint(Endpoint, Option<TlsAcceptor>),
+    Loopback(LoopbackAcceptor<P>),
+}
+
+/// Accepts connections of peers till stopped.
+///
+/// Every connection is delivered to an actor as `Accepted`.
+pub struct TransportListener<P, A>
+where
+    P: Protocol,
+    A: Actor,
+{
+    source: Source<P>,
+    address: Address<A>,
+}
+
+impl<P, A> TransportListener<P, A>
+where
+    P: Protocol,
+    A: Actor + ActionHandler<Accepted<P>>,
+{
+    /// Creates a listener of a `tcp://`, `unix://` or `wss://` endpoint.
+    ///
+    /// The endpoint is bound when the listener is started.
+    /// A `wss://` endpoint accepts WebSocket connections with any path
+    /// and requires TLS settings set by `with_tls`.
+    pub fn bind(endpoint: Endpoint, address: Address<A>) -> Self {
+        Self {
+            source: Source::Endpoint(endpoint, None),
+            address,
+        }
+    }
+
+    /// Sets TLS settings of a `wss://` endpoint.
+    pub fn with_tls(mut self, tls: TlsAcceptor) -> Self {
+        if let Source::Endpoint(_, acceptor) = &mut self.source {
+            *acceptor = Some(tls);
+        }
+        self
+    }
+
+    /// Accepts in-process connections of a `Loopback`.
+    pub fn loopback(acceptor: LoopbackAcceptor<P>, address: Address<A>) -> Self {
+        Self {
+            source: Source::Loopback(acceptor),
+            address,
+        }
+    }
+
+    async fn listen(&mut self, stop: &mut StopReceiver) -> Result<(), Error> {
+        let (endpoint, tls) = match &self.source {
+            Source::Endpoint(endpoint, tls) => (endpoint, tls),
+            Source::Loopback(_) => return Ok(()),
+        };
+        match endpoint {
+            Endpoint::Tcp(addr) => {
+                let listener = TcpListener::bind(addr).await?;
+                log::info!("Listening on tcp://{}", listener.local_addr()?);
+                while let Ok(res) = stop.or(listener.accept()).await {
+                    match res {
+                        Ok((stream, peer)) => {
+                            set_nodelay(&stream);
+                            let session =
+                                serve_framed(self.address.clone(), stream, peer.to_string());
+                            spawn(peer.to_string(), session);
+                        }
+                        Err(err) => {
+                            log::error!("Can't accept a connection on {}: {}", endpoint, err);
+                        }
+                    }
+                }
+            }
+            #[cfg(unix)]
+            Endpoint::Unix(path) => {
+                use std::os::unix::fs::FileTypeExt;
+                // A socket of a previous run prevents binding
+                if let Ok(metadata) = std::fs::symlink_metadata(path) {
+                    if metadata.file_type().is_socket() {
+                        log::debug!("Removing the stale socket: {}", path.display());
+                        std::fs::remove_file(path)?;
+                    }
+                }
+                let listener = tokio::net::UnixListener::bind(path)?;
+                log::info!("Listening on {}", endpoint);
+                while let Ok(res) = stop.or(listener.accept()).await {
+                    match res {
+                        Ok((stream, _peer)) => {
+                            let session =
+                                serve_framed(self.address.clone(), stream, endpoint.to_string());
+                            spawn(endpoint.to_string(), session);
+                        }
+                        Err(err) => {
+                            log::error!("Can't accept a connection on {}: {}", endpoint, err);
+                        }
+                    }
+                }
+            }
+            #[cfg(not(unix))]
+            Endpoint::Unix(_) => {
+                return Err(ListenerError::UnixNotSupported.into());
+            }
+            Endpoint::Wss(url) => {
+                let tls = tls
+                    .clone()
+                    .ok_or_else(|| ListenerError::NoTls(endpoint.clone()))?;
+                let (host, port) = endpoint::host_and_port(url)?;
+                let listener = TcpListener::bind((host.as_str(), port)).await?;
+                log::info!("Listening on wss://{}", listener.local_addr()?);
+                while let Ok(res) = stop.or(listener.accept()).await {
+                    match res {
+                        Ok((stream, peer)) => {
+                            set_nodelay(&stream);
+                            let session = serve_wss(
+                                self.address.clone(),
+                                tls.clone(),
+                                stream,
+                                peer.to_string(),
+                            );
+                            spawn(peer.to_string(), session);
+                        }
+                        Err(err) => {
+                            log::error!("Can't accept a connection on {}: {}", endpoint, err);
+                        }
+                    }
+                }
+            }
+            Endpoint::Ws(_) => {
+                return Err(ListenerError::PlainWs(endpoint.clone()).into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Disables delays of small frames. The stream is served even if it fails.
+fn set_nodelay(stream: &TcpStream) {
+    if let Err(err) = stream.set_nodelay(true) {
+        log::warn!("Can't set nodelay of an accepted stream: {}", err);
+    }
+}
+
+/// Runs the session of an accepted stream in the background.
+fn spawn<F>(addr: String, session: F)
+where
+    F: Future<Output = Result<(), Error>> + Send + 'static,
+{
+    tokio::spawn(async move {
+        if let Err(err) = session.await {
+            log::debug!("Connection with {} failed: {}", addr, err);
+        }
+    });
+}
+
+/// Delivers the connection and exchanges frames of it.
+async fn serve_framed<P, A, S>(
+    mut address: Address<A>,
+    stream: S,
+    addr: String,
+) -> Result<(), Error>
+where
+    P: Protocol,
+    A: Actor + ActionHandler<Accepted<P>>,
+    S: AsyncRead + AsyncWrite + Send,
+{
+    log::debug!("Connection accepted from: {}", addr);
+    let (connection, tx, rx) = Connection::new(addr);
+    address.act(Accepted { connection }).await?;
+    framed::serve::<P, _>(stream, tx, rx).await
+}
+
+/// Completes TLS and WebSocket handshakes, delivers the connection and exchanges messages of it.
+async fn serve_wss<P, A>(
+    mut address: Address<A>,
+    tls: TlsAcceptor,
+    stream: TcpStream,
+    addr: String,
+) -> Result<(), Error>
+where
+    P: Protocol,
+    A: Actor + ActionHandler<Accepted<P>>,
+{
+    let stream = tls.accept(stream).await?;
+    let websocket = tokio_tungstenite::accept_async(stream).await?;
+    log::debug!("Connection accepted from: {}", addr);
+    let (connection, tx, rx) = Connection::new(addr);
+    address.act(Accepted { connection }).await?;
+    ws::serve::<P, _>(websocket, tx, rx).await
+}
+
+#[async_trait]
+impl<P, A> LiteTask for TransportListener<P, A>
+where
+    P: Protocol,
+    A: Actor + ActionHandler<Accepted<P>>,
+{
+    type Output = ();
+
+    fn name(&self) -> String {
+        match &self.source {
+            Source::Endpoint(endpoint, _) => format!("TransportListener({})", endpoint),
+            Source::Loopback(_) => "TransportListener(loopback)".into(),
+        }
+    }
+
+    async fn routine(mut self, mut stop: StopReceiver) -> Result<Self::Output, Error> {
+        if let Source::Loopback(acceptor) = &mut self.source {
+            while let Ok(Some(connection)) = stop.or(acceptor.accept()).await {
+                self.address.act(Accepted { connection }).await?;
+            }
+        } else {
+            self.listen(&mut stop).await?;
+        }
+        Ok(())
+    }
+}
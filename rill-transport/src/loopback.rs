@@ -0,0 +1,72 @@
+//! In-process transport that connects a peer to a node without sockets.
+
+pub use crate::server::{FromNode, ToNode};
+
+use crate::server::Connection;
+use futures::channel::mpsc;
+use futures::StreamExt;
+use meio_protocol::Protocol;
+use std::fmt;
+use thiserror::Error;
+
+/// The node side of the rendezvous doesn't exist anymore.
+#[derive(Error, Debug)]
+#[error("Loopback acceptor dropped.")]
+pub struct AcceptorDropped;
+
+/// Creates a connected pair of a `Loopback` and its `LoopbackAcceptor`.
+///
+/// The peer connects with the `Loopback` and the node accepts
+/// connections with the acceptor.
+pub fn loopback<P: Protocol>() -> (Loopback<P>, LoopbackAcceptor<P>) {
+    let (tx, rx) = mpsc::unbounded();
+    let loopback = Loopback { sessions: tx };
+    let acceptor = LoopbackAcceptor { sessions: rx };
+    (loopback, acceptor)
+}
+
+/// The peer side of the rendezvous.
+///
+/// Every connection of the peer creates a new `Connection` for the node.
+pub struct Loopback<P: Protocol> {
+    sessions: mpsc::UnboundedSender<Connection<P>>,
+}
+
+impl<P: Protocol> Clone for Loopback<P> {
+    fn clone(&self) -> Self {
+        Self {
+            sessions: self.sessions.clone(),
+        }
+    }
+}
+
+impl<P: Protocol> fmt::Debug for Loopback<P> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Loopback").finish()
+    }
+}
+
+impl<P: Protocol> Loopback<P> {
+    /// Offers a new connection to the acceptor and returns the peer's ends of it.
+    pub fn connect(&self) -> Result<(ToNode<P>, FromNode<P>), AcceptorDropped> {
+        let (connection, to_node, from_node) = Connection::new("loopback".into());
+        self.sessions
+            .unbounded_send(connection)
+            .map_err(|_| AcceptorDropped)?;
+        Ok((to_node, from_node))
+    }
+}
+
+/// The node side of the rendezvous.
+pub struct LoopbackAcceptor<P: Protocol> {
+    sessions: mpsc::UnboundedReceiver<Connection<P>>,
+}
+
+impl<P: Protocol> LoopbackAcceptor<P> {
+    /// Waits for the next connection of the peer.
+    ///
+    /// Returns `None` if all `Loopback` instances were dropped.
+    pub async fn accept(&mut self) -> Option<Connection<P>> {
+        self.sessions.next().await
+    }
+}
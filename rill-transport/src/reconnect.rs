@@ -0,0 +1,225 @@
+//! Delays between attempts to connect to a node.
+
+use rand::Rng;
+use serde::{Deserialize, Deserializer};
+use std::time::Duration;
+
+/// How a connection is restored after a failure.
+///
+/// The delay starts from `initial_delay` and grows by `multiplier`
+/// till `max_delay`. Every delay is reduced by a random part
+/// of `jitter` to spread attempts of many peers over time.
+///
+/// Delays are set in milliseconds in configuration files.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ReconnectPolicy {
+    /// The delay before the first attempt
+    #[serde(deserialize_with = "millis")]
+    pub initial_delay: Duration,
+    /// The growth of the delay after every failed attempt
+    pub multiplier: f64,
+    /// The upper limit of the delay
+    #[serde(deserialize_with = "millis")]
+    pub max_delay: Duration,
+    /// The random part of a delay (from `0.0` to `1.0`)
+    pub jitter: f64,
+    /// Attempts to connect in a row before giving up (unlimited if `None`)
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectPolicy {
+    fn default() -> Self {
+        Self {
+            initial_delay: Duration::from_secs(1),
+            multiplier: 2.0,
+            max_delay: Duration::from_secs(30),
+            jitter: 0.5,
+            max_attempts: None,
+        }
+    }
+}
+
+fn millis<'de, D>(deserializer: D) -> Result<Duration, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    u64::deserialize(deserializer).map(Duration::from_millis)
+}
+
+/// Tracks attempts to connect according to a `ReconnectPolicy`.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    policy: ReconnectPolicy,
+    attempts: u32,
+}
+
+impl Backoff {
+    /// Creates a new tracker with no attempts.
+    pub fn new(policy: ReconnectPolicy) -> Self {
+        Self {
+            policy,
+            attempts: 0,
+        }
+    }
+
+    /// Resets attempts when the connection is established.
+    pub fn reset(&mut self) {
+        self.attempts = 0;
+    }
+
+    /// The number of failed attempts in a row.
+    pub fn attempts(&self) -> u32 {
+        self.attempts
+    }
+
+    /// Returns a delay before the next attempt or `None` if attempts are exhausted.
+    pub fn next_delay(&mut self) -> Option<Duration> {
+        if let Some(max_attempts) = self.policy.max_attempts {
+            if self.attempts >= max_attempts {
+                return None;
+            }
+        }
+        let exp = self.policy.multiplier.max(1.0).powf(self.attempts as f64);
+        self.attempts = self.attempts.saturating_add(1);
+        let max_delay = self.policy.max_delay.as_secs_f64();
+        let delay = (self.policy.initial_delay.as_secs_f64() * exp).min(max_delay);
+        let jitter = self.policy.jitter.clamp(0.0, 1.0);
+        let spread = if jitter > 0.0 {
+            rand::thread_rng().gen_range(0.0..=jitter)
+        } else {
+            0.0
+        };
+        Some(Duration::from_secs_f64(delay * (1.0 - spread)))
+    }
+}
+
+/// The state of a connection to a node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConnectionStatus {
+    /// An attempt to connect is in progress.
+    Connecting,
+    /// The connection is established.
+    Connected,
+    /// Waiting for the next attempt.
+    Backoff {
+        /// The number of failed attempts in a row
+        attempt: u32,
+        /// The delay before the next attempt
+        delay: Duration,
+    },
+    /// Attempts are exhausted and the connection won't be restored.
+    GaveUp,
+    /// The node rejected the peer and the connection won't be restored.
+    Rejected {
+        /// The reason reported by the node
+        reason: String,
+    },
+}
+
+impl ConnectionStatus {
+    /// Returns `true` if no more attempts to connect will be made.
+    pub fn is_final(&self) -> bool {
+        matches!(self, Self::GaveUp | Self::Rejected { .. })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> ReconnectPolicy {
+        ReconnectPolicy {
+            initial_delay: Duration::from_millis(100),
+            multiplier: 2.0,
+            max_delay: Duration::from_millis(1_000),
+            jitter: 0.0,
+            max_attempts: None,
+        }
+    }
+
+    fn delays(backoff: &mut Backoff, count: usize) -> Vec<Option<u128>> {
+        (0..count)
+            .map(|_| backoff.next_delay().map(|delay| delay.as_millis()))
+            .collect()
+    }
+
+    #[test]
+    fn delays_grow_till_the_limit() {
+        let mut backoff = Backoff::new(policy());
+        assert_eq!(
+            delays(&mut backoff, 6),
+            vec![
+                Some(100),
+                Some(200),
+                Some(400),
+                Some(800),
+                Some(1_000),
+                Some(1_000)
+            ]
+        );
+        assert_eq!(backoff.attempts(), 6);
+    }
+
+    #[test]
+    fn reset_restarts_delays() {
+        let mut backoff = Backoff::new(policy());
+        delays(&mut backoff, 3);
+        backoff.reset();
+        assert_eq!(backoff.attempts(), 0);
+        assert_eq!(delays(&mut backoff, 2), vec![Some(100), Some(200)]);
+    }
+
+    #[test]
+    fn attempts_are_exhausted() {
+        let policy = ReconnectPolicy {
+            max_attempts: Some(2),
+            ..policy()
+        };
+        let mut backoff = Backoff::new(policy);
+        assert_eq!(delays(&mut backoff, 3), vec![Some(100), Some(200), None]);
+        assert_eq!(backoff.attempts(), 2);
+        backoff.reset();
+        assert_eq!(delays(&mut backoff, 1), vec![Some(100)]);
+    }
+
+    #[test]
+    fn jitter_reduces_delays() {
+        let policy = ReconnectPolicy {
+            jitter: 0.5,
+            ..policy()
+        };
+        let mut backoff = Backoff::new(policy);
+        for expected in &[100, 200, 400, 800, 1_000] {
+            let delay = backoff.next_delay().unwrap().as_millis();
+            assert!(delay <= *expected, "{} > {}", delay, expected);
+            assert!(delay >= *expected / 2, "{} < {}", delay, expected / 2);
+        }
+    }
+
+    #[test]
+    fn invalid_settings_are_clamped() {
+        let policy = ReconnectPolicy {
+            multiplier: 0.5,
+            jitter: 5.0,
+            ..policy()
+        };
+        let mut backoff = Backoff::new(policy);
+        for _ in 0..3 {
+            let delay = backoff.next_delay().unwrap();
+            assert!(delay <= Duration::from_millis(100));
+        }
+    }
+
+    #[test]
+    fn delays_in_millis() {
+        let policy: ReconnectPolicy = serde_json::from_str(
+            r#"{ "initial_delay": 250, "max_delay": 5000, "max_attempts": 3 }"#,
+        )
+        .unwrap();
+        assert_eq!(policy.initial_delay, Duration::from_millis(250));
+        assert_eq!(policy.max_delay, Duration::from_millis(5_000));
+        assert_eq!(policy.max_attempts, Some(3));
+        assert_eq!(policy.multiplier, 2.0);
+    }
+}
@@ -0,0 +1,42 @@
+//! The outgoing part of a connection.
+
+use futures::channel::mpsc;
+use meio_connect::client::WsSender;
+use meio_protocol::ProtocolData;
+
+/// Sends messages to a node over any transport.
+#[derive(Debug, Clone)]
+pub enum TransportSender<T: ProtocolData> {
+    /// The sender of a WebSocket connection.
+    Ws(WsSender<T>),
+    /// The sender of a framed or an in-process connection.
+    Channel(mpsc::UnboundedSender<T>),
+}
+
+impl<T: ProtocolData> TransportSender<T> {
+    /// Sends a message. The message is dropped if the connection is closed.
+    pub fn send(&self, msg: T) {
+        match self {
+            Self::Ws(sender) => {
+                sender.send(msg);
+            }
+            Self::Channel(sender) => {
+                if let Err(err) = sender.unbounded_send(msg) {
+                    log::error!("Can't send an outgoing message: {}", err);
+                }
+            }
+        }
+    }
+}
+
+impl<T: ProtocolData> From<WsSender<T>> for TransportSender<T> {
+    fn from(sender: WsSender<T>) -> Self {
+        Self::Ws(sender)
+    }
+}
+
+impl<T: ProtocolData> From<mpsc::UnboundedSender<T>> for TransportSender<T> {
+    fn from(sender: mpsc::UnboundedSender<T>) -> Self {
+        Self::Channel(sender)
+    }
+}
@@ -0,0 +1,104 @@
+//! The server side of connections of all transports.
+
+use anyhow::Error;
+use async_trait::async_trait;
+use futures::channel::mpsc;
+use futures::StreamExt;
+use meio::{ActionHandler, Actor, Address, LiteTask, StopReceiver};
+use meio_connect::WsIncoming;
+use meio_protocol::Protocol;
+
+/// Messages sent by a peer to a node.
+pub type ToNode<P> = mpsc::UnboundedSender<<P as Protocol>::ToServer>;
+
+/// Messages received by a peer from a node.
+pub type FromNode<P> = mpsc::UnboundedReceiver<<P as Protocol>::ToClient>;
+
+/// A connection of a peer accepted by a listener.
+///
+/// It's the counterpart of `WsHandler` for other transports:
+/// outgoing messages are sent with `send` and incoming messages
+/// are delivered to an actor by the worker of the connection.
+/// Dropping of the connection closes it.
+pub struct Connection<P: Protocol> {
+    addr: String,
+    tx: mpsc::UnboundedSender<P::ToClient>,
+    rx: Option<mpsc::UnboundedReceiver<P::ToServer>>,
+}
+
+impl<P: Protocol> Connection<P> {
+    /// Creates a connection and the ends of the peer to exchange messages with it.
+    pub(crate) fn new(addr: String) -> (Self, ToNode<P>, FromNode<P>) {
+        let (to_node, rx) = mpsc::unbounded();
+        let (tx, from_node) = mpsc::unbounded();
+        let connection = Self {
+            addr,
+            tx,
+            rx: Some(rx),
+        };
+        (connection, to_node, from_node)
+    }
+
+    /// The address of the peer.
+    pub fn addr(&self) -> &str {
+        &self.addr
+    }
+
+    /// Creates a worker that delivers incoming messages to the actor.
+    ///
+    /// # Panics
+    ///
+    /// If the worker or `recv` already took incoming messages.
+    pub fn worker<A>(&mut self, address: Address<A>) -> ConnectionProcessor<P, A>
+    where
+        A: Actor + ActionHandler<WsIncoming<P::ToServer>>,
+    {
+        let rx = self.rx.take().expect("already started");
+        ConnectionProcessor {
+            addr: self.addr.clone(),
+            rx,
+            address,
+        }
+    }
+
+    /// Sends a message to the peer. The message is dropped if the connection is closed.
+    pub fn send(&self, msg: P::ToClient) {
+        if let Err(err) = self.tx.unbounded_send(msg) {
+            log::error!("Can't send an outgoing message: {}", err);
+        }
+    }
+
+    /// Receives the next message without a worker.
+    ///
+    /// Returns `None` if the peer closed the connection.
+    pub async fn recv(&mut self) -> Option<P::ToServer> {
+        self.rx.as_mut()?.next().await
+    }
+}
+
+/// Delivers incoming messages of a `Connection` to an actor.
+pub struct ConnectionProcessor<P: Protocol, A: Actor> {
+    addr: String,
+    rx: mpsc::UnboundedReceiver<P::ToServer>,
+    address: Address<A>,
+}
+
+#[async_trait]
+impl<P, A> LiteTask for ConnectionProcessor<P, A>
+where
+    P: Protocol,
+    A: Actor + ActionHandler<WsIncoming<P::ToServer>>,
+{
+    type Output = ();
+
+    fn name(&self) -> String {
+        format!("ConnectionProcessor({})", self.addr)
+    }
+
+    async fn routine(mut self, mut stop: StopReceiver) -> Result<Self::Output, Error> {
+        while let Ok(Some(msg)) = stop.or(self.rx.next()).await {
+            self.address.act(WsIncoming(msg)).await?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,216 @@
+//! TLS settings of `wss://` connections.
+//!
+//! Certificates and keys are read from PEM files once by `load`
+//! and the loaded settings are reused by all connections.
+
+use anyhow::Error;
+use rustls_pemfile::Item;
+use std::convert::TryFrom;
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use thiserror::Error;
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::server::AllowAnyAuthenticatedClient;
+use tokio_rustls::rustls::{
+    Certificate, ClientConfig, PrivateKey, RootCertStore, ServerConfig, ServerName,
+};
+use tokio_rustls::{client, server};
+
+/// Bundles of system CAs used if a CA bundle is not set.
+const SYSTEM_CA_BUNDLES: &[&str] = &[
+    "/etc/ssl/certs/ca-certificates.crt",
+    "/etc/pki/tls/certs/ca-bundle.crt",
+    "/etc/ssl/ca-bundle.pem",
+    "/etc/ssl/cert.pem",
+];
+
+#[derive(Error, Debug)]
+enum TlsError {
+    #[error("No CA bundle found. Set the path to a CA bundle explicitly.")]
+    NoCaBundle,
+    #[error("No certificates in {0}")]
+    NoCertificates(PathBuf),
+    #[error("No private key in {0}")]
+    NoPrivateKey(PathBuf),
+    #[error("Both a certificate and a key are required for client authentication.")]
+    IncompleteIdentity,
+    #[error("Invalid server name: {0}")]
+    InvalidServerName(String),
+}
+
+/// TLS configuration of a client.
+#[derive(Debug, Clone, Default)]
+pub struct TlsConfig {
+    /// The path to a PEM bundle of trusted CAs
+    pub ca: Option<PathBuf>,
+    /// The path to a PEM certificate chain of the client
+    pub cert: Option<PathBuf>,
+    /// The path to a PEM private key of the client
+    pub key: Option<PathBuf>,
+    /// The name to verify the server certificate against instead of the host
+    ///
+    /// Certificates are verified against DNS names only,
+    /// so it's required to reach a node by an IP address.
+    pub server_name: Option<String>,
+}
+
+impl TlsConfig {
+    fn roots(&self) -> Result<RootCertStore, Error> {
+        let path = match &self.ca {
+            Some(path) => path.clone(),
+            None => SYSTEM_CA_BUNDLES
+                .iter()
+                .map(PathBuf::from)
+                .find(|path| path.exists())
+                .ok_or(TlsError::NoCaBundle)?,
+        };
+        load_roots(&path)
+    }
+
+    /// Reads certificates and keys of the client.
+    pub fn load(&self) -> Result<TlsConnector, Error> {
+        let builder = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(self.roots()?);
+        let config = match (&self.cert, &self.key) {
+            (Some(cert), Some(key)) => {
+                builder.with_single_cert(load_chain(cert)?, load_key(key)?)?
+            }
+            (None, None) => builder.with_no_client_auth(),
+            _ => return Err(TlsError::IncompleteIdentity.into()),
+        };
+        Ok(TlsConnector {
+            connector: Arc::new(config).into(),
+            server_name: self.server_name.clone(),
+        })
+    }
+}
+
+/// Loaded TLS settings of a client.
+#[derive(Clone)]
+pub struct TlsConnector {
+    connector: tokio_rustls::TlsConnector,
+    server_name: Option<String>,
+}
+
+impl fmt::Debug for TlsConnector {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsConnector")
+            .field("server_name", &self.server_name)
+            .finish()
+    }
+}
+
+impl TlsConnector {
+    /// Establishes a TLS session over a TCP stream.
+    pub(crate) async fn connect(
+        &self,
+        host: &str,
+        stream: TcpStream,
+    ) -> Result<client::TlsStream<TcpStream>, Error> {
+        let name = self.server_name.as_deref().unwrap_or(host);
+        let server_name =
+            ServerName::try_from(name).map_err(|_| TlsError::InvalidServerName(name.into()))?;
+        let stream = self.connector.connect(server_name, stream).await?;
+        Ok(stream)
+    }
+}
+
+/// TLS configuration of a listener.
+#[derive(Debug, Clone)]
+pub struct TlsServerConfig {
+    /// The path to a PEM certificate chain of the server
+    pub cert: PathBuf,
+    /// The path to a PEM private key of the server
+    pub key: PathBuf,
+    /// The path to a PEM bundle of CAs of clients
+    ///
+    /// If it's set, clients have to authenticate with certificates.
+    pub ca: Option<PathBuf>,
+}
+
+impl TlsServerConfig {
+    /// Reads certificates and keys of the server.
+    pub fn load(&self) -> Result<TlsAcceptor, Error> {
+        let builder = ServerConfig::builder().with_safe_defaults();
+        let builder = match &self.ca {
+            Some(ca) => {
+                builder.with_client_cert_verifier(AllowAnyAuthenticatedClient::new(load_roots(ca)?))
+            }
+            None => builder.with_no_client_auth(),
+        };
+        let config = builder.with_single_cert(load_chain(&self.cert)?, load_key(&self.key)?)?;
+        Ok(TlsAcceptor {
+            acceptor: Arc::new(config).into(),
+        })
+    }
+}
+
+/// Loaded TLS settings of a listener.
+#[derive(Clone)]
+pub struct TlsAcceptor {
+    acceptor: tokio_rustls::TlsAcceptor,
+}
+
+impl fmt::Debug for TlsAcceptor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TlsAcceptor").finish()
+    }
+}
+
+impl TlsAcceptor {
+    /// Establishes a TLS session over an accepted TCP stream.
+    pub(crate) async fn accept(
+        &self,
+        stream: TcpStream,
+    ) -> Result<server::TlsStream<TcpStream>, Error> {
+        let stream = self.acceptor.accept(stream).await?;
+        Ok(stream)
+    }
+}
+
+fn load_roots(path: &Path) -> Result<RootCertStore, Error> {
+    let mut roots = RootCertStore::empty();
+    let certs = load_certs(path)?;
+    let (added, ignored) = roots.add_parsable_certificates(&certs);
+    if ignored > 0 {
+        log::warn!(
+            "{} certificates of {} were ignored",
+            ignored,
+            path.display()
+        );
+    }
+    if added == 0 {
+        return Err(TlsError::NoCertificates(path.into()).into());
+    }
+    Ok(roots)
+}
+
+fn load_chain(path: &Path) -> Result<Vec<Certificate>, Error> {
+    Ok(load_certs(path)?.into_iter().map(Certificate).collect())
+}
+
+fn load_certs(path: &Path) -> Result<Vec<Vec<u8>>, Error> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let certs = rustls_pemfile::certs(&mut reader)?;
+    if certs.is_empty() {
+        return Err(TlsError::NoCertificates(path.into()).into());
+    }
+    Ok(certs)
+}
+
+fn load_key(path: &Path) -> Result<PrivateKey, Error> {
+    let mut reader = BufReader::new(File::open(path)?);
+    while let Some(item) = rustls_pemfile::read_one(&mut reader)? {
+        match item {
+            Item::RSAKey(key) | Item::PKCS8Key(key) | Item::ECKey(key) => {
+                return Ok(PrivateKey(key));
+            }
+            _ => {}
+        }
+    }
+    Err(TlsError::NoPrivateKey(path.into()).into())
+}
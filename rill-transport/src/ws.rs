@@ -0,0 +1,87 @@
+//! WebSocket sessions over custom streams.
+
+use crate::framed::ConnectionDropped;
+use anyhow::Error;
+use futures::channel::mpsc;
+use futures::future::{self, Either};
+use futures::{Future, SinkExt, StreamExt};
+use meio::{ActionHandler, Actor, Address};
+use meio_connect::WsIncoming;
+use meio_protocol::{Protocol, ProtocolCodec, ProtocolData};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_tungstenite::WebSocketStream;
+use tungstenite::Message;
+
+/// Exchanges binary messages of a client till one of the sides closes the connection.
+pub(crate) async fn talk<P, A, S>(
+    address: Address<A>,
+    connection: WebSocketStream<S>,
+    rx: mpsc::UnboundedReceiver<P::ToServer>,
+) -> Result<(), Error>
+where
+    P: Protocol,
+    A: Actor + ActionHandler<WsIncoming<P::ToClient>>,
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    exchange::<P::Codec, _, _, _, _, _>(connection, rx, move |msg| {
+        let mut address = address.clone();
+        async move { address.act(WsIncoming(msg)).await }
+    })
+    .await
+}
+
+/// Exchanges binary messages of an accepted connection till one of the sides closes it.
+pub(crate) async fn serve<P, S>(
+    connection: WebSocketStream<S>,
+    tx: mpsc::UnboundedSender<P::ToServer>,
+    rx: mpsc::UnboundedReceiver<P::ToClient>,
+) -> Result<(), Error>
+where
+    P: Protocol,
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+{
+    exchange::<P::Codec, _, _, _, _, _>(connection, rx, move |msg| {
+        future::ready(tx.unbounded_send(msg).map_err(|_| ConnectionDropped.into()))
+    })
+    .await
+}
+
+async fn exchange<C, I, O, S, F, Fut>(
+    connection: WebSocketStream<S>,
+    mut rx: mpsc::UnboundedReceiver<O>,
+    mut deliver: F,
+) -> Result<(), Error>
+where
+    C: ProtocolCodec,
+    I: ProtocolData,
+    O: ProtocolData,
+    S: AsyncRead + AsyncWrite + Unpin + Send,
+    F: FnMut(I) -> Fut,
+    Fut: Future<Output = Result<(), Error>>,
+{
+    let (mut sink, mut stream) = connection.split();
+    let reading = async move {
+        while let Some(msg) = stream.next().await {
+            let msg = msg?;
+            if msg.is_text() || msg.is_binary() {
+                let decoded = C::decode(&msg.into_data())?;
+                deliver(decoded).await?;
+            } else if msg.is_close() {
+                break;
+            }
+        }
+        Ok::<_, Error>(())
+    };
+    let writing = async move {
+        while let Some(msg) = rx.next().await {
+            let encoded = C::encode(&msg)?;
+            sink.send(Message::Binary(encoded)).await?;
+        }
+        sink.close().await?;
+        Ok::<_, Error>(())
+    };
+    futures::pin_mut!(reading, writing);
+    match future::select(reading, writing).await {
+        Either::Left((res, _)) | Either::Right((res, _)) => res,
+    }
+}